@@ -4,19 +4,60 @@
 
 use std::ops::Range;
 
+use codespan_reporting::diagnostic::{Diagnostic, Label, Severity};
 use codespan_reporting::files::{Error, Files};
 
 // WARNING: Be extremely careful when adding new imports here, as it could break
 // the compatible version range that we claim in our `Cargo.toml`. This could
 // potentially break down-stream builds on a `cargo update`. This is an
 // absolute no-no, breaking much of what we enjoy about Cargo!
-use lsp_types::{Position as LspPosition, Range as LspRange};
+//
+// The diagnostic types below have the same shape in every version in the
+// range. In particular, `DiagnosticSeverity` is an enum up to `0.89`, and
+// only became a struct with associated constants in `0.90`.
+use lsp_types::{
+    Diagnostic as LspDiagnostic, DiagnosticRelatedInformation, DiagnosticSeverity,
+    Location as LspLocation, NumberOrString, Position as LspPosition, Range as LspRange, Url,
+};
+
+/// The encoding used to count the `character` offsets of LSP positions.
+///
+/// The protocol requires clients to support UTF-16, but some clients allow
+/// negotiating a different encoding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PositionEncoding {
+    /// Characters are counted in UTF-8 code units (bytes).
+    Utf8,
+    /// Characters are counted in UTF-16 code units.
+    Utf16,
+    /// Characters are counted in Unicode scalar values.
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// The number of code units in the given string.
+    fn len(self, s: &str) -> usize {
+        match self {
+            PositionEncoding::Utf8 => s.len(),
+            PositionEncoding::Utf16 => s.encode_utf16().count(),
+            PositionEncoding::Utf32 => s.chars().count(),
+        }
+    }
+}
+
+/// The given line without its trailing line terminator, if any. A `\r\n`,
+/// `\n`, or lone `\r` is treated as a line terminator.
+fn line_content(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
 
 fn location_to_position(
     line_str: &str,
     line: usize,
     column: usize,
     byte_index: usize,
+    encoding: PositionEncoding,
 ) -> Result<LspPosition, Error> {
     if column > line_str.len() {
         let max = line_str.len();
@@ -28,8 +69,10 @@ fn location_to_position(
 
         Err(Error::InvalidCharBoundary { given })
     } else {
-        let line_utf16 = line_str[..column].encode_utf16();
-        let character = line_utf16.count() as u32;
+        // Positions within the line terminator are placed at the end of the line
+        let content = line_content(line_str);
+        let column = std::cmp::min(column, content.len());
+        let character = encoding.len(&content[..column]) as u32;
         let line = line as u32;
 
         Ok(LspPosition { line, character })
@@ -45,8 +88,28 @@ where
     F: Files<'a> + ?Sized,
 {
     let source = files.source(file_id)?;
-    let source = source.as_ref();
 
+    byte_index_to_position_in(
+        files,
+        file_id,
+        source.as_ref(),
+        byte_index,
+        PositionEncoding::Utf16,
+    )
+}
+
+/// Like `byte_index_to_position`, but reusing a source that has already been
+/// looked up, and counting characters in the given encoding.
+fn byte_index_to_position_in<'a, F>(
+    files: &'a F,
+    file_id: F::FileId,
+    source: &str,
+    byte_index: usize,
+    encoding: PositionEncoding,
+) -> Result<LspPosition, Error>
+where
+    F: Files<'a> + ?Sized,
+{
     let line_index = files.line_index(file_id, byte_index)?;
     let line_span = files.line_range(file_id, line_index).unwrap();
 
@@ -62,7 +125,7 @@ where
         })?;
     let column = byte_index - line_span.start;
 
-    location_to_position(line_str, line_index, column, byte_index)
+    location_to_position(line_str, line_index, column, byte_index, encoding)
 }
 
 pub fn byte_span_to_range<'a, F>(
@@ -80,6 +143,8 @@ where
 }
 
 fn character_to_line_offset(line: &str, character: u32) -> Result<usize, Error> {
+    // The line terminator is not counted towards the characters of the line
+    let line = line_content(line);
     let line_len = line.len();
     let mut character_offset = 0;
 
@@ -117,7 +182,18 @@ where
     let source = files.source(file_id)?;
     let source = source.as_ref();
 
-    let line_span = files.line_range(file_id, position.line as usize).unwrap();
+    let line_index = position.line as usize;
+    let line_span = files
+        .line_range(file_id, line_index)
+        .map_err(|error| match error {
+            // Report the line that was requested, rather than the index of the
+            // following line that the file database may have failed to find.
+            Error::LineTooLarge { max, .. } => Error::LineTooLarge {
+                given: line_index,
+                max,
+            },
+            error => error,
+        })?;
     let line_str = source.get(line_span.clone()).unwrap();
 
     let byte_offset = character_to_line_offset(line_str, position.character)?;
@@ -137,12 +213,253 @@ where
         ..position_to_byte_index(files, file_id, &range.end)?)
 }
 
+/// Convert a batch of diagnostics into LSP diagnostics, resolving positions
+/// using the given encoding.
+///
+/// LSP diagnostics are published per-document, so the range of each LSP
+/// diagnostic is taken from [`Diagnostic::primary_label`], which is always in
+/// the file of the first primary label, falling back to `0:0` if the
+/// diagnostic has no primary labels. The remaining labels, including those
+/// that point into other files, are converted to
+/// [`DiagnosticRelatedInformation`], which is why the names of the files must
+/// be convertible into the URIs of the documents.
+///
+/// Notes and rich notes are appended to the message, each on their own line,
+/// with rich notes prefixed by their severity unless they are plain. The
+/// messages of suggestions are appended as `help` notes, but their edits are
+/// left out.
+///
+/// The source of each file is only looked up once for the whole batch.
+pub fn make_lsp_diagnostics<'a, F>(
+    files: &'a F,
+    diagnostics: &[Diagnostic<F::FileId>],
+    encoding: PositionEncoding,
+) -> Result<Vec<LspDiagnostic>, Error>
+where
+    F: Files<'a> + ?Sized,
+    F::Name: Into<Url>,
+{
+    let mut sources = Vec::new();
+
+    diagnostics
+        .iter()
+        .map(|diagnostic| make_lsp_diagnostic_in(files, &mut sources, diagnostic, encoding))
+        .collect()
+}
+
+/// Convert a single diagnostic into an LSP diagnostic, counting the
+/// characters of positions in UTF-16 code units like [`byte_span_to_range`].
+///
+/// See [`make_lsp_diagnostics`] for how the parts of the diagnostic are
+/// converted. A diagnostic without primary labels is placed at `0:0`.
+pub fn make_lsp_diagnostic<'a, F>(
+    files: &'a F,
+    diagnostic: &Diagnostic<F::FileId>,
+) -> Result<LspDiagnostic, Error>
+where
+    F: Files<'a> + ?Sized,
+    F::Name: Into<Url>,
+{
+    make_lsp_diagnostic_in(files, &mut Vec::new(), diagnostic, PositionEncoding::Utf16)
+}
+
+fn make_lsp_diagnostic_in<'a, F>(
+    files: &'a F,
+    sources: &mut Vec<(F::FileId, F::Source)>,
+    diagnostic: &Diagnostic<F::FileId>,
+    encoding: PositionEncoding,
+) -> Result<LspDiagnostic, Error>
+where
+    F: Files<'a> + ?Sized,
+    F::Name: Into<Url>,
+{
+    let mut label_range = |file_id: F::FileId, span: &Range<usize>| -> Result<LspRange, Error> {
+        let index = match sources.iter().position(|(id, _)| *id == file_id) {
+            Some(index) => index,
+            None => {
+                sources.push((file_id, files.source(file_id)?));
+                sources.len() - 1
+            }
+        };
+        let source = sources[index].1.as_ref();
+
+        Ok(LspRange {
+            start: byte_index_to_position_in(files, file_id, source, span.start, encoding)?,
+            end: byte_index_to_position_in(files, file_id, source, span.end, encoding)?,
+        })
+    };
+
+    let primary_label = diagnostic.primary_label();
+
+    let range = match primary_label {
+        Some(label) => label_range(label.file_id, &label.range)?,
+        None => {
+            let position = LspPosition {
+                line: 0,
+                character: 0,
+            };
+            LspRange {
+                start: position,
+                end: position,
+            }
+        }
+    };
+
+    let mut related_information = Vec::new();
+    for label in &diagnostic.labels {
+        if matches!(primary_label, Some(primary_label) if std::ptr::eq(label, primary_label)) {
+            continue;
+        }
+        related_information.push(DiagnosticRelatedInformation {
+            location: LspLocation {
+                uri: files.name(label.file_id)?.into(),
+                range: label_range(label.file_id, &label.range)?,
+            },
+            message: label.message.clone(),
+        });
+    }
+
+    let severity = severity_to_lsp(diagnostic.severity);
+
+    let code = diagnostic
+        .code
+        .as_ref()
+        .filter(|code| !code.is_empty())
+        .map(|code| NumberOrString::String(code.clone()));
+
+    let mut message = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        message.push('\n');
+        message.push_str(note);
+    }
+    for note in &diagnostic.rich_notes {
+        message.push('\n');
+        if !note.plain {
+            message.push_str(note.severity.as_str());
+            message.push_str(": ");
+        }
+        message.push_str(&note.message);
+    }
+    for suggestion in diagnostic
+        .suggestions
+        .iter()
+        .filter(|s| !s.message.is_empty())
+    {
+        message.push_str("\nhelp: ");
+        message.push_str(&suggestion.message);
+    }
+
+    Ok(LspDiagnostic::new(
+        range,
+        Some(severity),
+        code,
+        None,
+        message,
+        if related_information.is_empty() {
+            None
+        } else {
+            Some(related_information)
+        },
+        None,
+    ))
+}
+
+/// Convert a severity to an LSP severity, treating bugs as errors.
+fn severity_to_lsp(severity: Severity) -> DiagnosticSeverity {
+    match severity {
+        Severity::Bug | Severity::Error => DiagnosticSeverity::Error,
+        Severity::Warning => DiagnosticSeverity::Warning,
+        Severity::Note => DiagnosticSeverity::Information,
+        Severity::Help => DiagnosticSeverity::Hint,
+    }
+}
+
+/// Convert an LSP severity to a severity.
+fn severity_from_lsp(severity: DiagnosticSeverity) -> Severity {
+    match severity {
+        DiagnosticSeverity::Error => Severity::Error,
+        DiagnosticSeverity::Warning => Severity::Warning,
+        DiagnosticSeverity::Information => Severity::Note,
+        DiagnosticSeverity::Hint => Severity::Help,
+    }
+}
+
+/// Convert an LSP diagnostic for the document of the given file back into a
+/// diagnostic, for example to handle diagnostics that were sent by the client
+/// in a code action request.
+///
+/// This is the reverse of [`make_lsp_diagnostics`]:
+///
+/// * The range becomes the primary label, and the severity, code, and message
+///   are carried across. A missing severity is treated as an error.
+/// * Related information in the same document becomes secondary labels.
+///   Related information in other documents cannot be mapped to a file, so it
+///   becomes notes of the form `uri: message` instead.
+///
+/// Positions are counted in UTF-16 code units, like [`range_to_byte_span`].
+pub fn lsp_diagnostic_to_codespan<'a, F>(
+    files: &'a F,
+    file_id: F::FileId,
+    diagnostic: &LspDiagnostic,
+) -> Result<Diagnostic<F::FileId>, Error>
+where
+    F: Files<'a> + ?Sized,
+    F::Name: Into<Url>,
+{
+    let severity = match &diagnostic.severity {
+        Some(severity) => severity_from_lsp(*severity),
+        None => Severity::Error,
+    };
+
+    let mut labels = vec![Label::primary(
+        file_id,
+        range_to_byte_span(files, file_id, &diagnostic.range)?,
+    )];
+    let mut notes = Vec::new();
+    let uri: Url = files.name(file_id)?.into();
+    for related in diagnostic.related_information.iter().flatten() {
+        if related.location.uri == uri {
+            let range = range_to_byte_span(files, file_id, &related.location.range)?;
+            labels.push(Label::secondary(file_id, range).with_message(&related.message));
+        } else {
+            notes.push(format!("{}: {}", related.location.uri, related.message));
+        }
+    }
+
+    let mut result = Diagnostic::new(severity)
+        .with_message(&diagnostic.message)
+        .with_labels(labels)
+        .with_notes(notes);
+    match &diagnostic.code {
+        Some(NumberOrString::Number(code)) => result = result.with_code(code.to_string()),
+        Some(NumberOrString::String(code)) => result = result.with_code(code),
+        None => {}
+    }
+
+    Ok(result)
+}
+
 #[cfg(test)]
 mod tests {
+    use codespan_reporting::diagnostic::Label;
     use codespan_reporting::files::{Location, SimpleFiles};
 
     use super::*;
 
+    #[test]
+    fn severity_round_trip() {
+        let severities = [
+            (Severity::Help, Severity::Help),
+            (Severity::Note, Severity::Note),
+            (Severity::Warning, Severity::Warning),
+            (Severity::Error, Severity::Error),
+            (Severity::Bug, Severity::Error),
+        ];
+        for (severity, expected) in &severities {
+            assert_eq!(severity_from_lsp(severity_to_lsp(*severity)), *expected);
+        }
+    }
+
     #[test]
     fn position() {
         let text = r#"
@@ -234,4 +551,282 @@ test
             }
         );
     }
+
+    #[test]
+    fn crlf_get_byte_index() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("crlf", "a\r\nb");
+
+        // The end of the first line is before the `\r`
+        let result = position_to_byte_index(
+            &files,
+            file_id,
+            &LspPosition {
+                line: 0,
+                character: 1,
+            },
+        );
+        assert_eq!(result.unwrap(), 1);
+
+        // The line terminator does not count towards the length of the line
+        let result = position_to_byte_index(
+            &files,
+            file_id,
+            &LspPosition {
+                line: 0,
+                character: 2,
+            },
+        );
+        assert!(matches!(result, Err(Error::ColumnTooLarge { .. })));
+
+        let result = position_to_byte_index(
+            &files,
+            file_id,
+            &LspPosition {
+                line: 1,
+                character: 1,
+            },
+        );
+        assert_eq!(result.unwrap(), 4);
+    }
+
+    #[test]
+    fn crlf_get_position() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("crlf", "a\r\nb");
+
+        let end_of_line = LspPosition {
+            line: 0,
+            character: 1,
+        };
+
+        // Both the `\r` and the `\n` are at the end of the first line
+        for byte_index in 1..3 {
+            let result = byte_index_to_position(&files, file_id, byte_index);
+            assert_eq!(result.unwrap(), end_of_line);
+        }
+
+        // Round-tripping the end of the line is stable
+        let byte_index = position_to_byte_index(&files, file_id, &end_of_line).unwrap();
+        assert_eq!(byte_index, 1);
+        let result = byte_index_to_position(&files, file_id, byte_index);
+        assert_eq!(result.unwrap(), end_of_line);
+
+        let result = byte_index_to_position(&files, file_id, 3);
+        assert_eq!(
+            result.unwrap(),
+            LspPosition {
+                line: 1,
+                character: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn position_line_out_of_range() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "let a = 1\nlet b = 2\n");
+
+        // The file has three lines (the last one empty), so `3` is the first
+        // line index past the end of the file.
+        for &line in &[3, 4, 100] {
+            let result =
+                position_to_byte_index(&files, file_id, &LspPosition { line, character: 0 });
+            match result {
+                Err(Error::LineTooLarge { given, max }) => {
+                    assert_eq!(given, line as usize);
+                    assert_eq!(max, 2);
+                }
+                result => panic!("expected `LineTooLarge`, found {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn diagnostics_in_one_file() {
+        let uri = Url::parse("file:///test.fun").unwrap();
+        let mut files = SimpleFiles::new();
+        let file_id = files.add(uri.clone(), "let x = 1;\nlet y = \"å\" + x;\n");
+
+        let diagnostics = vec![
+            Diagnostic::warning()
+                .with_message("unused variable")
+                .with_code("W001")
+                .with_labels(vec![Label::primary(file_id, 4..5)]),
+            Diagnostic::error()
+                .with_message("mismatched types")
+                .with_labels(vec![
+                    Label::primary(file_id, 19..23).with_message("expected `Int`"),
+                    Label::secondary(file_id, 4..5).with_message("defined here"),
+                ])
+                .with_notes(vec!["expected `Int`, found `String`".to_owned()]),
+        ];
+
+        let result = make_lsp_diagnostics(&files, &diagnostics, PositionEncoding::Utf16).unwrap();
+
+        assert_eq!(
+            result,
+            vec![
+                LspDiagnostic::new(
+                    LspRange::new(LspPosition::new(0, 4), LspPosition::new(0, 5)),
+                    Some(DiagnosticSeverity::Warning),
+                    Some(NumberOrString::String("W001".to_owned())),
+                    None,
+                    "unused variable".to_owned(),
+                    None,
+                    None,
+                ),
+                LspDiagnostic::new(
+                    LspRange::new(LspPosition::new(1, 8), LspPosition::new(1, 11)),
+                    Some(DiagnosticSeverity::Error),
+                    None,
+                    None,
+                    "mismatched types\nexpected `Int`, found `String`".to_owned(),
+                    Some(vec![DiagnosticRelatedInformation {
+                        location: LspLocation::new(
+                            uri,
+                            LspRange::new(LspPosition::new(0, 4), LspPosition::new(0, 5)),
+                        ),
+                        message: "defined here".to_owned(),
+                    }]),
+                    None,
+                ),
+            ],
+        );
+
+        let result =
+            make_lsp_diagnostics(&files, &diagnostics[1..], PositionEncoding::Utf8).unwrap();
+        assert_eq!(
+            result[0].range,
+            LspRange::new(LspPosition::new(1, 8), LspPosition::new(1, 12)),
+        );
+    }
+
+    #[test]
+    fn single_diagnostic() {
+        let uri = Url::parse("file:///test.fun").unwrap();
+        let mut files = SimpleFiles::new();
+        let file_id = files.add(uri, "let y = \"å\" + x;\n");
+
+        let diagnostic = Diagnostic::error()
+            .with_message("mismatched types")
+            .with_labels(vec![Label::primary(file_id, 8..12)]);
+        assert_eq!(
+            make_lsp_diagnostic(&files, &diagnostic).unwrap(),
+            make_lsp_diagnostics(&files, &[diagnostic], PositionEncoding::Utf16).unwrap()[0],
+        );
+
+        let diagnostic = Diagnostic::<usize>::warning().with_message("no labels");
+        assert_eq!(
+            make_lsp_diagnostic(&files, &diagnostic).unwrap().range,
+            LspRange::new(LspPosition::new(0, 0), LspPosition::new(0, 0)),
+        );
+    }
+
+    #[test]
+    fn primary_labels_in_several_files() {
+        use codespan_reporting::diagnostic::{Note, Suggestion};
+
+        let uri = Url::parse("file:///test.fun").unwrap();
+        let other_uri = Url::parse("file:///other.fun").unwrap();
+        let mut files = SimpleFiles::new();
+        let file_id = files.add(uri.clone(), "let x = 1;\nlet y = x;\n");
+        let other_id = files.add(other_uri.clone(), "x\n");
+
+        let diagnostic = Diagnostic::error()
+            .with_message("conflicting definitions")
+            .with_labels(vec![
+                Label::primary(file_id, 15..16),
+                Label::primary(other_id, 0..1),
+                Label::primary(file_id, 4..5),
+            ])
+            .with_rich_notes(vec![
+                Note::help("rename one of them"),
+                Note::plain("see the chapter on scopes"),
+            ])
+            .with_suggestions(vec![
+                Suggestion::new(file_id, 4..5, "z").with_message("rename to `z`")
+            ]);
+
+        let result = make_lsp_diagnostic(&files, &diagnostic).unwrap();
+
+        assert_eq!(
+            result.range,
+            LspRange::new(LspPosition::new(0, 4), LspPosition::new(0, 5)),
+        );
+        assert_eq!(
+            result.related_information.unwrap(),
+            vec![
+                DiagnosticRelatedInformation {
+                    location: LspLocation::new(
+                        uri,
+                        LspRange::new(LspPosition::new(1, 4), LspPosition::new(1, 5)),
+                    ),
+                    message: String::new(),
+                },
+                DiagnosticRelatedInformation {
+                    location: LspLocation::new(
+                        other_uri,
+                        LspRange::new(LspPosition::new(0, 0), LspPosition::new(0, 1)),
+                    ),
+                    message: String::new(),
+                },
+            ],
+        );
+        assert_eq!(
+            result.message,
+            concat!(
+                "conflicting definitions\n",
+                "help: rename one of them\n",
+                "see the chapter on scopes\n",
+                "help: rename to `z`",
+            ),
+        );
+    }
+
+    #[test]
+    fn lsp_diagnostic_round_trip() {
+        let uri = Url::parse("file:///test.fun").unwrap();
+        let other_uri = Url::parse("file:///other.fun").unwrap();
+        let mut files = SimpleFiles::new();
+        let file_id = files.add(uri.clone(), "let x = 1;\nlet y = \"å\" + x;\n");
+
+        let diagnostic = Diagnostic::error()
+            .with_message("mismatched types")
+            .with_code("E001")
+            .with_labels(vec![
+                Label::primary(file_id, 19..23),
+                Label::secondary(file_id, 4..5).with_message("defined here"),
+            ]);
+
+        let mut lsp_diagnostics = make_lsp_diagnostics(
+            &files,
+            std::slice::from_ref(&diagnostic),
+            PositionEncoding::Utf16,
+        )
+        .unwrap();
+        assert_eq!(
+            lsp_diagnostic_to_codespan(&files, file_id, &lsp_diagnostics[0]).unwrap(),
+            diagnostic,
+        );
+
+        let lsp_diagnostic = &mut lsp_diagnostics[0];
+        lsp_diagnostic.severity = Some(DiagnosticSeverity::Hint);
+        lsp_diagnostic.code = Some(NumberOrString::Number(42));
+        lsp_diagnostic.related_information = Some(vec![DiagnosticRelatedInformation {
+            location: LspLocation::new(
+                other_uri,
+                LspRange::new(LspPosition::new(0, 0), LspPosition::new(0, 1)),
+            ),
+            message: "imported here".to_owned(),
+        }]);
+        assert_eq!(
+            lsp_diagnostic_to_codespan(&files, file_id, lsp_diagnostic).unwrap(),
+            Diagnostic::help()
+                .with_message("mismatched types")
+                .with_code("42")
+                .with_labels(vec![Label::primary(file_id, 19..23)])
+                .with_notes(vec!["file:///other.fun: imported here".to_owned()]),
+        );
+    }
 }
@@ -2,7 +2,9 @@
 
 #[cfg(feature = "serialization")]
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::ops::Range;
+use std::str::FromStr;
 use std::string::ToString;
 
 /// A severity level for diagnostic messages.
@@ -32,6 +34,90 @@ pub enum Severity {
     Bug,
 }
 
+impl Severity {
+    /// The lowercase name of the severity, like `"warning"`.
+    ///
+    /// This can be parsed back into a severity with [`str::parse`]:
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::Severity;
+    ///
+    /// assert_eq!(Severity::Warning.as_str(), "warning");
+    /// assert_eq!("WARNING".parse::<Severity>(), Ok(Severity::Warning));
+    /// assert_eq!("warn".parse::<Severity>(), Ok(Severity::Warning));
+    /// assert!("fatal".parse::<Severity>().is_err());
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Help => "help",
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+            Severity::Bug => "bug",
+        }
+    }
+
+    /// A short glyph for the severity, for user interfaces that show
+    /// severities as icons, like status bars.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::Severity;
+    ///
+    /// assert_eq!(Severity::Error.symbol(), "✖");
+    /// assert_eq!(Severity::Help.symbol(), "💡");
+    /// ```
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Severity::Help => "💡",
+            Severity::Note => "ℹ",
+            Severity::Warning => "⚠",
+            Severity::Error => "✖",
+            Severity::Bug => "🐛",
+        }
+    }
+
+    /// A single ASCII letter for the severity, for output where the glyphs of
+    /// [`Severity::symbol`] can not be displayed.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::Severity;
+    ///
+    /// assert_eq!(Severity::Warning.ascii_symbol(), 'W');
+    /// assert_eq!(Severity::Bug.ascii_symbol(), 'B');
+    /// ```
+    pub fn ascii_symbol(self) -> char {
+        match self {
+            Severity::Help => 'H',
+            Severity::Note => 'N',
+            Severity::Warning => 'W',
+            Severity::Error => 'E',
+            Severity::Bug => 'B',
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = &'static str;
+
+    fn from_str(src: &str) -> Result<Severity, &'static str> {
+        match src {
+            _ if src.eq_ignore_ascii_case("help") => Ok(Severity::Help),
+            _ if src.eq_ignore_ascii_case("note") => Ok(Severity::Note),
+            _ if src.eq_ignore_ascii_case("warning") => Ok(Severity::Warning),
+            _ if src.eq_ignore_ascii_case("warn") => Ok(Severity::Warning),
+            _ if src.eq_ignore_ascii_case("error") => Ok(Severity::Error),
+            _ if src.eq_ignore_ascii_case("bug") => Ok(Severity::Bug),
+            _ => Err("valid values: bug, error, warning, note, help"),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd)]
 #[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub enum LabelStyle {
@@ -54,6 +140,12 @@ pub struct Label<FileId> {
     /// An optional message to provide some additional information for the
     /// underlined code. These should not include line breaks.
     pub message: String,
+    /// The priority of the label, used to order the messages of labels that
+    /// cover the same range. Labels with a higher priority have their messages
+    /// rendered closer to the carets, and labels with the same priority are
+    /// rendered in the order that they were specified.
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub priority: i32,
 }
 
 impl<FileId> Label<FileId> {
@@ -68,7 +160,32 @@ impl<FileId> Label<FileId> {
             file_id,
             range: range.into(),
             message: String::new(),
+            priority: 0,
+        }
+    }
+
+    /// Create a new label, checking that the start of the range is not after
+    /// its end.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::{Label, LabelStyle};
+    ///
+    /// assert!(Label::try_new(LabelStyle::Primary, (), 2..4).is_ok());
+    /// assert!(Label::try_new(LabelStyle::Primary, (), 4..4).is_ok());
+    ///
+    /// let error = Label::try_new(LabelStyle::Primary, (), 4..2).unwrap_err();
+    /// assert_eq!(error.range, 4..2);
+    /// ```
+    pub fn try_new(
+        style: LabelStyle,
+        file_id: FileId,
+        range: impl Into<Range<usize>>,
+    ) -> Result<Label<FileId>, LabelRangeError> {
+        let range = range.into();
+        if range.start > range.end {
+            return Err(LabelRangeError { range });
         }
+        Ok(Label::new(style, file_id, range))
     }
 
     /// Create a new label with a style of [`LabelStyle::Primary`].
@@ -90,6 +207,156 @@ impl<FileId> Label<FileId> {
         self.message = message.to_string();
         self
     }
+
+    /// Set the priority of the label.
+    pub fn with_priority(mut self, priority: i32) -> Label<FileId> {
+        self.priority = priority;
+        self
+    }
+
+    /// Move the label to a different file, keeping its style, range, message,
+    /// and priority. The new file id can be of a different type.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::Label;
+    ///
+    /// let label = Label::primary(0, 2..4).with_message("expanded from here");
+    /// let label = label.with_file("parent.rs");
+    ///
+    /// assert_eq!(label.file_id, "parent.rs");
+    /// assert_eq!(label.range, 2..4);
+    /// assert_eq!(label.message, "expanded from here");
+    /// ```
+    pub fn with_file<T>(self, file_id: T) -> Label<T> {
+        self.map_file_id(|_| file_id)
+    }
+
+    /// Replace the file id of the label with the result of applying `f` to it.
+    fn map_file_id<T>(self, f: impl FnOnce(FileId) -> T) -> Label<T> {
+        Label {
+            style: self.style,
+            file_id: f(self.file_id),
+            range: self.range,
+            message: self.message,
+            priority: self.priority,
+        }
+    }
+}
+
+/// A suggested edit to the source code, rendered as a `help` note followed by
+/// the affected lines of source code with the edit applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Suggestion<FileId> {
+    /// The file that the edit applies to.
+    pub file_id: FileId,
+    /// The range in bytes that is replaced. This is empty for insertions.
+    pub range: Range<usize>,
+    /// The text to replace the range with. This may contain line breaks.
+    pub replacement: String,
+    /// An optional message describing the edit, rendered in the `help` note.
+    pub message: String,
+}
+
+impl<FileId> Suggestion<FileId> {
+    /// Create a new suggestion.
+    pub fn new(
+        file_id: FileId,
+        range: impl Into<Range<usize>>,
+        replacement: impl ToString,
+    ) -> Suggestion<FileId> {
+        Suggestion {
+            file_id,
+            range: range.into(),
+            replacement: replacement.to_string(),
+            message: String::new(),
+        }
+    }
+
+    /// Add a message to the suggestion.
+    pub fn with_message(mut self, message: impl ToString) -> Suggestion<FileId> {
+        self.message = message.to_string();
+        self
+    }
+}
+
+/// An error returned by [`Label::try_new`] when the start of a range is after
+/// its end.
+///
+/// [`Label::try_new`]: Label::try_new
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LabelRangeError {
+    /// The offending range.
+    pub range: Range<usize>,
+}
+
+impl fmt::Display for LabelRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid label range {}..{}, start is after end",
+            self.range.start, self.range.end,
+        )
+    }
+}
+
+impl std::error::Error for LabelRangeError {}
+
+/// A note with its own severity, used to visually distinguish notes like
+/// suggested fixes from the other notes of a diagnostic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Note {
+    /// The severity of the note.
+    pub severity: Severity,
+    /// The message of the note.
+    /// This can include line breaks for improved formatting.
+    pub message: String,
+    /// Whether the note is plain text, rendered without a bullet or the name
+    /// of its severity.
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub plain: bool,
+}
+
+impl Note {
+    /// Create a new note.
+    pub fn new(severity: Severity, message: impl ToString) -> Note {
+        Note {
+            severity,
+            message: message.to_string(),
+            plain: false,
+        }
+    }
+
+    /// Create a new note with a severity of [`Severity::Note`].
+    ///
+    /// [`Severity::Note`]: Severity::Note
+    #[allow(clippy::self_named_constructors)]
+    pub fn note(message: impl ToString) -> Note {
+        Note::new(Severity::Note, message)
+    }
+
+    /// Create a new note with a severity of [`Severity::Help`].
+    ///
+    /// [`Severity::Help`]: Severity::Help
+    pub fn help(message: impl ToString) -> Note {
+        Note::new(Severity::Help, message)
+    }
+
+    /// Create a new plain note, for free-form text at the end of a
+    /// diagnostic. This is rendered without a bullet, aligned with the text of
+    /// the other notes:
+    ///
+    /// ```text
+    /// = expected type `Int`
+    ///   See the chapter on conversions for more details.
+    /// ```
+    pub fn plain(message: impl ToString) -> Note {
+        Note {
+            plain: true,
+            ..Note::new(Severity::Note, message)
+        }
+    }
 }
 
 /// Represents a diagnostic message that can provide information like errors and
@@ -116,6 +383,24 @@ pub struct Diagnostic<FileId> {
     /// Notes that are associated with the primary cause of the diagnostic.
     /// These can include line breaks for improved formatting.
     pub notes: Vec<String>,
+    /// Notes with their own severity, which are rendered with a severity-styled
+    /// bullet.
+    ///
+    /// Rich notes are kept separate from [`notes`](Diagnostic::notes), and every
+    /// renderer emits them after all of the plain notes, regardless of the order
+    /// in which the two kinds of note were added. To interleave the two kinds,
+    /// add the plain notes as [`Note::plain`] rich notes instead.
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub rich_notes: Vec<Note>,
+    /// Suggested edits to the source code, which are rendered after the notes.
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub suggestions: Vec<Suggestion<FileId>>,
+    /// The name of the tool or pass that produced the diagnostic, which is
+    /// rendered after the notes if [`Config::show_source_provenance`] is set.
+    ///
+    /// [`Config::show_source_provenance`]: crate::term::Config::show_source_provenance
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub source: Option<String>,
 }
 
 impl<FileId> Diagnostic<FileId> {
@@ -127,6 +412,9 @@ impl<FileId> Diagnostic<FileId> {
             message: String::new(),
             labels: Vec::new(),
             notes: Vec::new(),
+            rich_notes: Vec::new(),
+            suggestions: Vec::new(),
+            source: None,
         }
     }
 
@@ -177,15 +465,576 @@ impl<FileId> Diagnostic<FileId> {
         self
     }
 
+    /// Add a single label to the diagnostic.
+    pub fn with_label(mut self, label: Label<FileId>) -> Diagnostic<FileId> {
+        self.labels.push(label);
+        self
+    }
+
     /// Add some labels to the diagnostic.
     pub fn with_labels(mut self, mut labels: Vec<Label<FileId>>) -> Diagnostic<FileId> {
         self.labels.append(&mut labels);
         self
     }
 
+    /// Add a single note to the diagnostic.
+    pub fn with_note(mut self, note: impl Into<String>) -> Diagnostic<FileId> {
+        self.notes.push(note.into());
+        self
+    }
+
     /// Add some notes to the diagnostic.
     pub fn with_notes(mut self, mut notes: Vec<String>) -> Diagnostic<FileId> {
         self.notes.append(&mut notes);
         self
     }
+
+    /// Add some notes with their own severity to the diagnostic.
+    ///
+    /// These are always rendered after the notes added with
+    /// [`with_notes`](Diagnostic::with_notes).
+    pub fn with_rich_notes(mut self, mut notes: Vec<Note>) -> Diagnostic<FileId> {
+        self.rich_notes.append(&mut notes);
+        self
+    }
+
+    /// Add some suggested edits to the diagnostic.
+    pub fn with_suggestions(
+        mut self,
+        mut suggestions: Vec<Suggestion<FileId>>,
+    ) -> Diagnostic<FileId> {
+        self.suggestions.append(&mut suggestions);
+        self
+    }
+
+    /// Set the name of the tool or pass that produced the diagnostic.
+    pub fn with_source(mut self, source: impl ToString) -> Diagnostic<FileId> {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    /// Remove consecutive repeated notes from the diagnostic, preserving the
+    /// order of the remaining notes.
+    ///
+    /// Repeated notes that are not adjacent to each other are kept.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::Diagnostic;
+    ///
+    /// let mut diagnostic = Diagnostic::<()>::error()
+    ///     .with_note("a")
+    ///     .with_note("a")
+    ///     .with_note("b")
+    ///     .with_note("a");
+    /// diagnostic.dedup_notes();
+    ///
+    /// assert_eq!(diagnostic.notes, ["a", "b", "a"]);
+    /// ```
+    pub fn dedup_notes(&mut self) {
+        self.notes.dedup();
+    }
+
+    /// Returns `true` if the diagnostic carries nothing but its severity: it
+    /// has an empty message, no code, no labels, no notes, no suggestions, and
+    /// no source.
+    ///
+    /// Rendering an empty diagnostic only produces a header containing the
+    /// name of its severity, for example `error: `, so tools may want to skip
+    /// these diagnostics, or check for them before printing a footer.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::Diagnostic;
+    ///
+    /// assert!(Diagnostic::<()>::error().is_empty());
+    /// assert!(Diagnostic::<()>::error().with_code("").is_empty());
+    /// assert!(!Diagnostic::<()>::error().with_message("a message").is_empty());
+    /// assert!(!Diagnostic::<()>::error().with_note("a note").is_empty());
+    /// assert!(!Diagnostic::<()>::error().with_source("clippy").is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.message.is_empty()
+            && self.code.as_deref().unwrap_or("").is_empty()
+            && self.labels.is_empty()
+            && self.notes.is_empty()
+            && self.rich_notes.is_empty()
+            && self.suggestions.is_empty()
+            && self.source.is_none()
+    }
+
+    /// Returns `true` if the diagnostic has any labels, so that it points to a
+    /// location in the source.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::{Diagnostic, Label};
+    ///
+    /// assert!(!Diagnostic::<()>::error().has_labels());
+    /// assert!(Diagnostic::error().with_labels(vec![Label::secondary((), 0..1)]).has_labels());
+    /// ```
+    pub fn has_labels(&self) -> bool {
+        !self.labels.is_empty()
+    }
+
+    /// Put the diagnostic in a canonical form, so that diagnostics that only
+    /// differ in insignificant ways compare as equal, for example after being
+    /// round-tripped through a tool that does not preserve empty codes.
+    ///
+    /// Whitespace is trimmed from the code, message, and source of the
+    /// diagnostic, and the messages of its labels and notes. Codes and sources
+    /// that are left empty are replaced with `None`. Normalizing a diagnostic
+    /// twice has no further effect.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::Diagnostic;
+    ///
+    /// let mut diagnostic = Diagnostic::<()>::error()
+    ///     .with_code("")
+    ///     .with_message("unexpected type ")
+    ///     .with_source(" typeck")
+    ///     .with_notes(vec!["\texpected `Int`\n".to_owned()]);
+    /// diagnostic.normalize();
+    ///
+    /// assert_eq!(
+    ///     diagnostic,
+    ///     Diagnostic::error()
+    ///         .with_message("unexpected type")
+    ///         .with_notes(vec!["expected `Int`".to_owned()])
+    ///         .with_source("typeck"),
+    /// );
+    /// ```
+    pub fn normalize(&mut self) {
+        if let Some(code) = &mut self.code {
+            trim_in_place(code);
+            if code.is_empty() {
+                self.code = None;
+            }
+        }
+        trim_in_place(&mut self.message);
+        for label in &mut self.labels {
+            trim_in_place(&mut label.message);
+        }
+        for note in &mut self.notes {
+            trim_in_place(note);
+        }
+        for note in &mut self.rich_notes {
+            trim_in_place(&mut note.message);
+        }
+        if let Some(source) = &mut self.source {
+            trim_in_place(source);
+            if source.is_empty() {
+                self.source = None;
+            }
+        }
+    }
+
+    /// The primary label that the diagnostic is located at.
+    ///
+    /// This is the primary label with the smallest start in the file of the
+    /// first primary label, regardless of the order of the labels. The first
+    /// such label is returned if several start at the same position.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::{Diagnostic, Label};
+    ///
+    /// let diagnostic = Diagnostic::error().with_labels(vec![
+    ///     Label::primary((), 8..9),
+    ///     Label::secondary((), 0..1),
+    ///     Label::primary((), 4..5),
+    /// ]);
+    ///
+    /// assert_eq!(diagnostic.primary_label().map(|label| label.range.clone()), Some(4..5));
+    /// ```
+    pub fn primary_label(&self) -> Option<&Label<FileId>>
+    where
+        FileId: PartialEq,
+    {
+        let first = self
+            .labels
+            .iter()
+            .find(|label| label.style == LabelStyle::Primary)?;
+        self.labels
+            .iter()
+            .filter(|label| label.style == LabelStyle::Primary && label.file_id == first.file_id)
+            .min_by_key(|label| label.range.start)
+    }
+
+    /// The file and range of each label of the diagnostic, in the order that
+    /// the labels were specified.
+    pub fn covered_ranges(&self) -> impl '_ + Iterator<Item = (&FileId, Range<usize>)> {
+        self.labels
+            .iter()
+            .map(|label| (&label.file_id, label.range.clone()))
+    }
+
+    /// The ranges covered by the labels of the diagnostic, with overlapping
+    /// ranges in the same file merged together.
+    ///
+    /// The files are in the order that they first appear in the labels, and
+    /// the ranges of each file are sorted by their start. Ranges that only
+    /// touch each other are not merged.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::{Diagnostic, Label};
+    ///
+    /// let diagnostic = Diagnostic::error().with_labels(vec![
+    ///     Label::primary("a", 4..8),
+    ///     Label::secondary("b", 0..2),
+    ///     Label::secondary("a", 0..2),
+    ///     Label::secondary("a", 6..10),
+    ///     Label::secondary("a", 2..3),
+    /// ]);
+    ///
+    /// assert_eq!(
+    ///     diagnostic.merged_ranges(),
+    ///     [(&"a", 0..2), (&"a", 2..3), (&"a", 4..10), (&"b", 0..2)],
+    /// );
+    /// ```
+    pub fn merged_ranges(&self) -> Vec<(&FileId, Range<usize>)>
+    where
+        FileId: PartialEq,
+    {
+        let mut files = Vec::<(&FileId, Vec<Range<usize>>)>::new();
+        for (file_id, range) in self.covered_ranges() {
+            match files.iter_mut().find(|(id, _)| *id == file_id) {
+                Some((_, ranges)) => ranges.push(range),
+                None => files.push((file_id, vec![range])),
+            }
+        }
+
+        let mut merged_ranges = Vec::new();
+        for (file_id, mut ranges) in files {
+            ranges.sort_by_key(|range| (range.start, range.end));
+
+            let mut ranges = ranges.into_iter();
+            let mut current = match ranges.next() {
+                Some(range) => range,
+                None => continue,
+            };
+            for range in ranges {
+                if range.start < current.end {
+                    current.end = std::cmp::max(current.end, range.end);
+                } else {
+                    merged_ranges.push((file_id, current));
+                    current = range;
+                }
+            }
+            merged_ranges.push((file_id, current));
+        }
+        merged_ranges
+    }
+
+    /// Convert the file ids of the labels and suggestions of the diagnostic
+    /// with the given function.
+    ///
+    /// This is useful for moving diagnostics that were created with a
+    /// temporary file id type over to the file ids of a file database.
+    pub fn map_file_ids<T>(self, mut f: impl FnMut(FileId) -> T) -> Diagnostic<T> {
+        Diagnostic {
+            severity: self.severity,
+            code: self.code,
+            message: self.message,
+            labels: self
+                .labels
+                .into_iter()
+                .map(|label| label.map_file_id(&mut f))
+                .collect(),
+            notes: self.notes,
+            rich_notes: self.rich_notes,
+            suggestions: self
+                .suggestions
+                .into_iter()
+                .map(|suggestion| Suggestion {
+                    file_id: f(suggestion.file_id),
+                    range: suggestion.range,
+                    replacement: suggestion.replacement,
+                    message: suggestion.message,
+                })
+                .collect(),
+            source: self.source,
+        }
+    }
+}
+
+/// A summary of the number of diagnostics emitted at each severity.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DiagnosticStats {
+    /// The number of bugs.
+    pub bugs: usize,
+    /// The number of errors.
+    pub errors: usize,
+    /// The number of warnings.
+    pub warnings: usize,
+    /// The number of notes.
+    pub notes: usize,
+    /// The number of help messages.
+    pub helps: usize,
+}
+
+impl DiagnosticStats {
+    /// Create an empty summary.
+    pub fn new() -> DiagnosticStats {
+        DiagnosticStats::default()
+    }
+
+    /// Count a diagnostic towards the summary.
+    pub fn add<FileId>(&mut self, diagnostic: &Diagnostic<FileId>) {
+        match diagnostic.severity {
+            Severity::Bug => self.bugs += 1,
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+            Severity::Note => self.notes += 1,
+            Severity::Help => self.helps += 1,
+        }
+    }
+
+    /// The number of diagnostics counted at the given severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        match severity {
+            Severity::Bug => self.bugs,
+            Severity::Error => self.errors,
+            Severity::Warning => self.warnings,
+            Severity::Note => self.notes,
+            Severity::Help => self.helps,
+        }
+    }
+
+    /// The process exit code to use for the counted diagnostics, according
+    /// to the given policy.
+    ///
+    /// This is the largest exit code of the severities that were counted, or
+    /// `0` if no diagnostics were counted.
+    pub fn exit_code(&self, policy: &ExitCodePolicy) -> i32 {
+        [
+            Severity::Help,
+            Severity::Note,
+            Severity::Warning,
+            Severity::Error,
+            Severity::Bug,
+        ]
+        .iter()
+        .filter(|severity| self.count(**severity) > 0)
+        .map(|severity| i32::from(policy.on_severity(*severity)))
+        .max()
+        .unwrap_or(0)
+    }
+}
+
+impl<'a, FileId: 'a> std::iter::FromIterator<&'a Diagnostic<FileId>> for DiagnosticStats {
+    fn from_iter<I: IntoIterator<Item = &'a Diagnostic<FileId>>>(iter: I) -> DiagnosticStats {
+        let mut stats = DiagnosticStats::new();
+        for diagnostic in iter {
+            stats.add(diagnostic);
+        }
+        stats
+    }
+}
+
+/// Maps diagnostic severities to process exit codes.
+///
+/// By default bugs and errors map to an exit code of `1`, and all other
+/// severities map to `0`.
+///
+/// ```rust
+/// use codespan_reporting::diagnostic::{ExitCodePolicy, Severity};
+///
+/// let policy = ExitCodePolicy::new().with_warnings_as_errors(true);
+///
+/// assert_eq!(policy.on_severity(Severity::Error), 1);
+/// assert_eq!(policy.on_severity(Severity::Warning), 1);
+/// assert_eq!(policy.on_severity(Severity::Note), 0);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExitCodePolicy {
+    /// The exit code to use for errors and bugs.
+    pub error_code: u8,
+    /// Whether warnings should be treated as errors, as with `-Werror`.
+    pub warnings_as_errors: bool,
+}
+
+impl ExitCodePolicy {
+    /// Create a new policy, mapping errors and bugs to an exit code of `1`.
+    pub fn new() -> ExitCodePolicy {
+        ExitCodePolicy {
+            error_code: 1,
+            warnings_as_errors: false,
+        }
+    }
+
+    /// Set the exit code to use for errors and bugs.
+    pub fn with_error_code(mut self, error_code: u8) -> ExitCodePolicy {
+        self.error_code = error_code;
+        self
+    }
+
+    /// Set whether warnings should be treated as errors.
+    pub fn with_warnings_as_errors(mut self, warnings_as_errors: bool) -> ExitCodePolicy {
+        self.warnings_as_errors = warnings_as_errors;
+        self
+    }
+
+    /// The exit code to use for a diagnostic with the given severity.
+    pub fn on_severity(&self, severity: Severity) -> u8 {
+        match severity {
+            Severity::Bug | Severity::Error => self.error_code,
+            Severity::Warning if self.warnings_as_errors => self.error_code,
+            Severity::Warning | Severity::Note | Severity::Help => 0,
+        }
+    }
+}
+
+impl Default for ExitCodePolicy {
+    fn default() -> ExitCodePolicy {
+        ExitCodePolicy::new()
+    }
+}
+
+/// Remove leading and trailing whitespace from a string, without reallocating
+/// it if there is none.
+fn trim_in_place(s: &mut String) {
+    if s.trim().len() != s.len() {
+        *s = s.trim().to_owned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severity_round_trip() {
+        let severities = [
+            Severity::Help,
+            Severity::Note,
+            Severity::Warning,
+            Severity::Error,
+            Severity::Bug,
+        ];
+        for severity in &severities {
+            assert_eq!(severity.to_string().parse::<Severity>(), Ok(*severity));
+            assert_eq!(
+                severity.as_str().to_uppercase().parse::<Severity>(),
+                Ok(*severity)
+            );
+        }
+    }
+
+    #[test]
+    fn normalize_is_idempotent() {
+        let mut diagnostic = Diagnostic::warning()
+            .with_code(" \n")
+            .with_message("  unused variable\n")
+            .with_labels(vec![Label::primary((), 0..1).with_message("here ")])
+            .with_notes(vec![" help: remove it".to_owned()])
+            .with_rich_notes(vec![Note::help("or prefix it with `_`\r\n")]);
+
+        diagnostic.normalize();
+        let normalized = diagnostic.clone();
+        diagnostic.normalize();
+
+        assert_eq!(diagnostic, normalized);
+        assert_eq!(diagnostic.code, None);
+        assert_eq!(diagnostic.message, "unused variable");
+        assert_eq!(diagnostic.labels[0].message, "here");
+        assert_eq!(diagnostic.notes, ["help: remove it"]);
+        assert_eq!(diagnostic.rich_notes[0].message, "or prefix it with `_`");
+
+        let mut coded = Diagnostic::<()>::error().with_code(" E0001 ");
+        coded.normalize();
+        assert_eq!(coded.code.as_deref(), Some("E0001"));
+    }
+
+    #[test]
+    fn with_label_and_note_preserve_order() {
+        let diagnostic = Diagnostic::error()
+            .with_label(Label::primary((), 0..1))
+            .with_labels(vec![Label::secondary((), 1..2), Label::secondary((), 2..3)])
+            .with_label(Label::secondary((), 3..4))
+            .with_note("first")
+            .with_notes(vec!["second".to_owned()])
+            .with_note(String::from("third"));
+
+        let ranges = diagnostic
+            .labels
+            .iter()
+            .map(|label| label.range.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, vec![0..1, 1..2, 2..3, 3..4]);
+        assert_eq!(diagnostic.notes, vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn map_file_ids() {
+        let diagnostic = Diagnostic::<u32>::error()
+            .with_message("a message")
+            .with_labels(vec![
+                Label::primary(0, 0..1).with_message("a label"),
+                Label::secondary(1, 1..2),
+            ])
+            .with_suggestions(vec![Suggestion::new(1, 2..3, "a replacement")])
+            .map_file_ids(|file_id| format!("file{}", file_id));
+
+        let file_ids = diagnostic
+            .labels
+            .iter()
+            .map(|label| label.file_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(file_ids, ["file0", "file1"]);
+        assert_eq!(diagnostic.labels[0].message, "a label");
+        assert_eq!(diagnostic.suggestions[0].file_id, "file1");
+        assert_eq!(diagnostic.message, "a message");
+    }
+
+    #[test]
+    fn dedup_notes() {
+        let mut diagnostic = Diagnostic::<()>::error().with_notes(vec![
+            "first".to_owned(),
+            "first".to_owned(),
+            "first".to_owned(),
+            "second".to_owned(),
+            "first".to_owned(),
+            "second".to_owned(),
+            "second".to_owned(),
+        ]);
+        diagnostic.dedup_notes();
+
+        assert_eq!(diagnostic.notes, ["first", "second", "first", "second"]);
+    }
+
+    #[test]
+    fn exit_code_empty() {
+        let stats = DiagnosticStats::new();
+
+        assert_eq!(stats.exit_code(&ExitCodePolicy::new()), 0);
+    }
+
+    #[test]
+    fn exit_code_errors() {
+        let diagnostics = [Diagnostic::<()>::warning(), Diagnostic::error()];
+        let stats = diagnostics.iter().collect::<DiagnosticStats>();
+
+        assert_eq!(stats.exit_code(&ExitCodePolicy::new()), 1);
+        assert_eq!(
+            stats.exit_code(&ExitCodePolicy::new().with_error_code(101)),
+            101,
+        );
+    }
+
+    #[test]
+    fn exit_code_warnings_as_errors() {
+        let diagnostics = [Diagnostic::<()>::warning(), Diagnostic::note()];
+        let stats = diagnostics.iter().collect::<DiagnosticStats>();
+
+        assert_eq!(stats.exit_code(&ExitCodePolicy::new()), 0);
+        assert_eq!(
+            stats.exit_code(&ExitCodePolicy::new().with_warnings_as_errors(true)),
+            1,
+        );
+        assert_eq!(
+            stats.exit_code(
+                &ExitCodePolicy::new()
+                    .with_warnings_as_errors(true)
+                    .with_error_code(2),
+            ),
+            2,
+        );
+    }
 }
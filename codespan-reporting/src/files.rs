@@ -8,6 +8,8 @@
 //!
 //! - [`SimpleFile`]: For single-file use-cases
 //! - [`SimpleFiles`]: For multi-file use-cases
+//! - [`LazyDiskFiles`]: For files that are read from disk when first needed
+//! - [`CachedFiles`]: For files whose sources are generated when first needed
 //!
 //! These data structures provide a pretty minimal API, however,
 //! so end-users are encouraged to create their own implementations for their
@@ -20,10 +22,17 @@
 //! [`Files`]: Files
 //! [`SimpleFile`]: SimpleFile
 //! [`SimpleFiles`]: SimpleFiles
+//! [`LazyDiskFiles`]: LazyDiskFiles
+//! [`CachedFiles`]: CachedFiles
 //!
 //! [`salsa`]: https://crates.io/crates/salsa
 
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
 use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 /// An enum representing an error that happened while looking up a file or a piece of content in that file.
 #[derive(Debug)]
@@ -41,6 +50,13 @@ pub enum Error {
     InvalidCharBoundary { given: usize },
     /// There was a error while doing IO.
     Io(std::io::Error),
+    /// An error that happened while looking up content in the named file.
+    ///
+    /// This is returned by the functions of the [`term`] module, so that it
+    /// is clear which file caused the error.
+    ///
+    /// [`term`]: crate::term
+    InFile { name: String, error: Box<Error> },
 }
 
 impl From<std::io::Error> for Error {
@@ -64,6 +80,7 @@ impl std::fmt::Display for Error {
             }
             Error::InvalidCharBoundary { .. } => write!(f, "index is not a code point boundary"),
             Error::Io(err) => write!(f, "{}", err),
+            Error::InFile { name, error } => write!(f, "{}: {}", name, error),
         }
     }
 }
@@ -72,6 +89,7 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match &self {
             Error::Io(err) => Some(err),
+            Error::InFile { error, .. } => Some(error.as_ref()),
             _ => None,
         }
     }
@@ -94,6 +112,21 @@ pub trait Files<'a> {
     /// The user-facing name of a file.
     fn name(&'a self, id: Self::FileId) -> Result<Self::Name, Error>;
 
+    /// A shorter form of the user-facing name of a file, used in the loci of
+    /// diagnostics if [`Config::compact_locus`] is set.
+    ///
+    /// # Note for trait implementors
+    ///
+    /// This defaults to the [`name`] of the file. Implementors that name files
+    /// by their paths could, for example, return only the last component of
+    /// the path.
+    ///
+    /// [`Config::compact_locus`]: crate::term::Config::compact_locus
+    /// [`name`]: Files::name
+    fn short_name(&'a self, id: Self::FileId) -> Result<Self::Name, Error> {
+        self.name(id)
+    }
+
     /// The source code of a file.
     fn source(&'a self, id: Self::FileId) -> Result<Self::Source, Error>;
 
@@ -164,6 +197,20 @@ pub trait Files<'a> {
 
     /// The byte range of line in the source of the file.
     fn line_range(&'a self, id: Self::FileId, line_index: usize) -> Result<Range<usize>, Error>;
+
+    /// Whether the file contains binary data rather than text.
+    ///
+    /// Source snippets are not rendered for binary files. Instead, the size
+    /// of the file is shown, along with the byte ranges of its labels.
+    ///
+    /// # Note for trait implementors
+    ///
+    /// This defaults to `false`. Implementors that might be given binary files
+    /// could, for example, check whether the source contains a NUL byte.
+    #[allow(unused_variables)]
+    fn is_binary(&'a self, id: Self::FileId) -> bool {
+        false
+    }
 }
 
 /// A user-facing location in a source file.
@@ -294,6 +341,41 @@ where
         &self.source
     }
 
+    /// Return the number of lines in the file.
+    ///
+    /// An empty file has a single empty line, as does the part of a file after
+    /// its last line break.
+    ///
+    /// ```rust
+    /// use codespan_reporting::files::SimpleFile;
+    ///
+    /// assert_eq!(SimpleFile::new("test", "").line_count(), 1);
+    /// assert_eq!(SimpleFile::new("test", "foo\nbar").line_count(), 2);
+    /// assert_eq!(SimpleFile::new("test", "foo\nbar\n").line_count(), 3);
+    /// ```
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Return the length of the source of the file, in bytes.
+    pub fn len(&self) -> usize {
+        self.source.as_ref().len()
+    }
+
+    /// Return whether the source of the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.source.as_ref().is_empty()
+    }
+
+    /// Replace the source of the file.
+    ///
+    /// This will mean that any outstanding byte indexes will now point to
+    /// invalid locations.
+    pub fn update(&mut self, source: Source) {
+        self.line_starts = line_starts(source.as_ref()).collect();
+        self.source = source;
+    }
+
     /// Return the starting byte index of the line with the specified line index.
     /// Convenience method that already generates errors if necessary.
     fn line_start(&self, line_index: usize) -> Result<usize, Error> {
@@ -378,6 +460,33 @@ where
     pub fn get(&self, file_id: usize) -> Result<&SimpleFile<Name, Source>, Error> {
         self.files.get(file_id).ok_or(Error::FileMissing)
     }
+
+    /// Get a mutable reference to the file corresponding to the given id.
+    pub fn get_mut(&mut self, file_id: usize) -> Result<&mut SimpleFile<Name, Source>, Error> {
+        self.files.get_mut(file_id).ok_or(Error::FileMissing)
+    }
+
+    /// Replace the source of the file corresponding to the given id.
+    ///
+    /// This will mean that any outstanding byte indexes will now point to
+    /// invalid locations.
+    pub fn update(&mut self, file_id: usize, source: Source) -> Result<(), Error> {
+        self.get_mut(file_id)?.update(source);
+        Ok(())
+    }
+
+    /// Return the number of lines in the file corresponding to the given id.
+    ///
+    /// See [`SimpleFile::line_count`] for how lines are counted.
+    pub fn line_count(&self, file_id: usize) -> Result<usize, Error> {
+        Ok(self.get(file_id)?.line_count())
+    }
+
+    /// Return the length of the source of the file corresponding to the given
+    /// id, in bytes.
+    pub fn len(&self, file_id: usize) -> Result<usize, Error> {
+        Ok(self.get(file_id)?.len())
+    }
 }
 
 impl<'a, Name, Source> Files<'a> for SimpleFiles<Name, Source>
@@ -406,6 +515,452 @@ where
     }
 }
 
+/// A file database over a static list of names and sources.
+///
+/// Unlike [`SimpleFiles`], this does not allocate: line starts are computed on
+/// demand by scanning the source, so it is suitable for small sources that are
+/// embedded in the binary, but lookups are linear in the length of the file.
+///
+/// ```rust
+/// use codespan_reporting::files::{Files, StaticFiles};
+///
+/// static FILES: StaticFiles = StaticFiles::new(&[
+///     ("main.cfg", "mode = fast\nlevel = 3\n"),
+///     ("extra.cfg", "debug = true\n"),
+/// ]);
+///
+/// assert_eq!(FILES.name(1).unwrap(), "extra.cfg");
+/// assert_eq!(FILES.line_index(0, 14).unwrap(), 1);
+/// assert_eq!(FILES.line_range(0, 1).unwrap(), 12..22);
+/// ```
+///
+/// [`SimpleFiles`]: SimpleFiles
+#[derive(Debug, Copy, Clone)]
+pub struct StaticFiles {
+    /// The names and sources of the files.
+    files: &'static [(&'static str, &'static str)],
+}
+
+impl StaticFiles {
+    /// Create a new files database from a list of names and sources. The
+    /// handle of each file is its index in the list.
+    pub const fn new(files: &'static [(&'static str, &'static str)]) -> StaticFiles {
+        StaticFiles { files }
+    }
+
+    /// Get the name and source of the file corresponding to the given id.
+    pub fn get(&self, file_id: usize) -> Result<(&'static str, &'static str), Error> {
+        self.files.get(file_id).cloned().ok_or(Error::FileMissing)
+    }
+}
+
+impl<'a> Files<'a> for StaticFiles {
+    type FileId = usize;
+    type Name = &'static str;
+    type Source = &'static str;
+
+    fn name(&self, file_id: usize) -> Result<&'static str, Error> {
+        Ok(self.get(file_id)?.0)
+    }
+
+    fn source(&self, file_id: usize) -> Result<&'static str, Error> {
+        Ok(self.get(file_id)?.1)
+    }
+
+    fn line_index(&self, file_id: usize, byte_index: usize) -> Result<usize, Error> {
+        let (_, source) = self.get(file_id)?;
+        let line_count = line_starts(source)
+            .take_while(|line_start| *line_start <= byte_index)
+            .count();
+
+        Ok(line_count - 1)
+    }
+
+    fn line_range(&self, file_id: usize, line_index: usize) -> Result<Range<usize>, Error> {
+        let (_, source) = self.get(file_id)?;
+        let mut starts = line_starts(source).skip(line_index);
+
+        match starts.next() {
+            Some(line_start) => Ok(line_start..starts.next().unwrap_or(source.len())),
+            None => Err(Error::LineTooLarge {
+                given: line_index,
+                max: line_starts(source).count() - 1,
+            }),
+        }
+    }
+}
+
+/// A file database of paths, where the source of each file is read from disk
+/// the first time it is needed.
+///
+/// The source and line starts of a file are cached after they are loaded, so
+/// the cost of reading a file is only paid for files that diagnostics refer
+/// to. Errors while reading a file are returned as [`Error::Io`], and the file
+/// will be read again the next time it is needed.
+///
+/// The names of the files are their paths, and their short names are the last
+/// components of their paths.
+///
+/// ```rust,no_run
+/// use codespan_reporting::files::{Files, LazyDiskFiles};
+///
+/// let mut files = LazyDiskFiles::new();
+/// let file_id = files.add("src/main.rs");
+///
+/// // The file is only read here
+/// let source = files.source(file_id).unwrap();
+/// ```
+///
+/// [`Error::Io`]: Error::Io
+#[derive(Debug, Default)]
+pub struct LazyDiskFiles {
+    files: Vec<LazyDiskFile>,
+}
+
+#[derive(Debug)]
+struct LazyDiskFile {
+    /// The path of the file.
+    path: PathBuf,
+    /// The contents of the file, once it has been read.
+    loaded: RefCell<Option<SimpleFile<String, Rc<str>>>>,
+}
+
+impl LazyDiskFiles {
+    /// Create a new files database.
+    pub fn new() -> LazyDiskFiles {
+        LazyDiskFiles { files: Vec::new() }
+    }
+
+    /// Add the path of a file to the database, returning the handle that can
+    /// be used to refer to it again. The file is not read until its source is
+    /// needed.
+    pub fn add(&mut self, path: impl Into<PathBuf>) -> usize {
+        let file_id = self.files.len();
+        self.files.push(LazyDiskFile {
+            path: path.into(),
+            loaded: RefCell::new(None),
+        });
+        file_id
+    }
+
+    /// Get the path of the file corresponding to the given id.
+    pub fn path(&self, file_id: usize) -> Result<&Path, Error> {
+        Ok(&self.get(file_id)?.path)
+    }
+
+    fn get(&self, file_id: usize) -> Result<&LazyDiskFile, Error> {
+        self.files.get(file_id).ok_or(Error::FileMissing)
+    }
+
+    /// Call a function with the loaded file corresponding to the given id,
+    /// reading it from disk if this has not been done yet.
+    fn with_loaded<T>(
+        &self,
+        file_id: usize,
+        f: impl FnOnce(&SimpleFile<String, Rc<str>>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let file = self.get(file_id)?;
+        let mut loaded = file.loaded.borrow_mut();
+        if loaded.is_none() {
+            let source = std::fs::read_to_string(&file.path)?;
+            *loaded = Some(SimpleFile::new(
+                file.path.display().to_string(),
+                Rc::from(source),
+            ));
+        }
+        f(loaded.as_ref().expect("file was loaded above"))
+    }
+}
+
+impl<'a> Files<'a> for LazyDiskFiles {
+    type FileId = usize;
+    type Name = String;
+    type Source = Rc<str>;
+
+    fn name(&self, file_id: usize) -> Result<String, Error> {
+        Ok(self.path(file_id)?.display().to_string())
+    }
+
+    fn short_name(&self, file_id: usize) -> Result<String, Error> {
+        let path = self.path(file_id)?;
+        match path.file_name() {
+            Some(file_name) => Ok(file_name.to_string_lossy().into_owned()),
+            None => Ok(path.display().to_string()),
+        }
+    }
+
+    fn source(&self, file_id: usize) -> Result<Rc<str>, Error> {
+        self.with_loaded(file_id, |file| Ok(file.source().clone()))
+    }
+
+    fn line_index(&self, file_id: usize, byte_index: usize) -> Result<usize, Error> {
+        self.with_loaded(file_id, |file| file.line_index((), byte_index))
+    }
+
+    fn line_range(&self, file_id: usize, line_index: usize) -> Result<Range<usize>, Error> {
+        self.with_loaded(file_id, |file| file.line_range((), line_index))
+    }
+}
+
+/// A file database where the source of each file is generated by a function
+/// the first time it is needed.
+///
+/// This is useful for virtual files, like the expansions of macros, that are
+/// expensive to compute. The source and line starts of a file are cached after
+/// they are generated, and the source is shared as an [`Rc<str>`] so that it
+/// can be returned from [`Files::source`] without borrowing from the cache.
+///
+/// The names of the files are their ids. They can be given other names by
+/// wrapping the database in a [`MapName`].
+///
+/// ```rust
+/// use codespan_reporting::files::{CachedFiles, Files};
+///
+/// let files = CachedFiles::new(|depth: usize| "(expand\n".repeat(depth));
+///
+/// // The source is only generated here
+/// assert_eq!(&*files.source(3).unwrap(), "(expand\n(expand\n(expand\n");
+/// assert_eq!(files.line_range(3, 1).unwrap(), 8..16);
+/// assert_eq!(files.name(3).unwrap(), 3);
+/// ```
+///
+/// [`Rc<str>`]: std::rc::Rc
+/// [`MapName`]: MapName
+#[derive(Debug)]
+pub struct CachedFiles<FileId, G> {
+    generate: G,
+    cache: RefCell<HashMap<FileId, SimpleFile<String, Rc<str>>>>,
+}
+
+impl<FileId, G> CachedFiles<FileId, G>
+where
+    FileId: Copy + Eq + Hash + std::fmt::Display,
+    G: Fn(FileId) -> String,
+{
+    /// Create a new files database, generating the sources of files with the
+    /// given function.
+    pub fn new(generate: G) -> CachedFiles<FileId, G> {
+        CachedFiles {
+            generate,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Call a function with the generated file corresponding to the given id,
+    /// generating it if this has not been done yet.
+    fn with_generated<T>(
+        &self,
+        file_id: FileId,
+        f: impl FnOnce(&SimpleFile<String, Rc<str>>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        if !self.cache.borrow().contains_key(&file_id) {
+            // Generate the source before borrowing the cache mutably, in case
+            // the function looks at other files
+            let source = Rc::from((self.generate)(file_id));
+            let file = SimpleFile::new(file_id.to_string(), source);
+            self.cache.borrow_mut().insert(file_id, file);
+        }
+        f(&self.cache.borrow()[&file_id])
+    }
+}
+
+impl<'a, FileId, G> Files<'a> for CachedFiles<FileId, G>
+where
+    FileId: 'a + Copy + Eq + Hash + std::fmt::Display,
+    G: Fn(FileId) -> String,
+{
+    type FileId = FileId;
+    type Name = FileId;
+    type Source = Rc<str>;
+
+    fn name(&self, file_id: FileId) -> Result<FileId, Error> {
+        Ok(file_id)
+    }
+
+    fn source(&self, file_id: FileId) -> Result<Rc<str>, Error> {
+        self.with_generated(file_id, |file| Ok(file.source().clone()))
+    }
+
+    fn line_index(&self, file_id: FileId, byte_index: usize) -> Result<usize, Error> {
+        self.with_generated(file_id, |file| file.line_index((), byte_index))
+    }
+
+    fn line_range(&self, file_id: FileId, line_index: usize) -> Result<Range<usize>, Error> {
+        self.with_generated(file_id, |file| file.line_range((), line_index))
+    }
+}
+
+/// A file database that changes the names of the files of another database,
+/// forwarding everything else to it unchanged.
+///
+/// This is useful for hiding details like absolute paths from diagnostics.
+///
+/// ```rust
+/// use codespan_reporting::files::{Files, MapName, SimpleFiles};
+///
+/// let mut files = SimpleFiles::new();
+/// let file_id = files.add("/tmp/vendor/lib.rs", "fn main() {}");
+///
+/// let files = MapName::new(files, |name: &str| name.trim_start_matches("/tmp/").to_owned());
+///
+/// assert_eq!(files.name(file_id).unwrap(), "vendor/lib.rs");
+/// ```
+#[derive(Debug, Clone)]
+pub struct MapName<Inner, F> {
+    files: Inner,
+    map: F,
+}
+
+impl<Inner, F> MapName<Inner, F> {
+    /// Wrap a file database, changing the names of its files with the given function.
+    pub fn new(files: Inner, map: F) -> MapName<Inner, F> {
+        MapName { files, map }
+    }
+
+    /// Return the wrapped file database.
+    pub fn into_inner(self) -> Inner {
+        self.files
+    }
+}
+
+impl<'a, Inner, F, Name> Files<'a> for MapName<Inner, F>
+where
+    Inner: Files<'a>,
+    F: Fn(Inner::Name) -> Name,
+    Name: 'a + std::fmt::Display,
+{
+    type FileId = Inner::FileId;
+    type Name = Name;
+    type Source = Inner::Source;
+
+    fn name(&'a self, id: Self::FileId) -> Result<Name, Error> {
+        Ok((self.map)(self.files.name(id)?))
+    }
+
+    fn short_name(&'a self, id: Self::FileId) -> Result<Name, Error> {
+        Ok((self.map)(self.files.short_name(id)?))
+    }
+
+    fn source(&'a self, id: Self::FileId) -> Result<Self::Source, Error> {
+        self.files.source(id)
+    }
+
+    fn line_index(&'a self, id: Self::FileId, byte_index: usize) -> Result<usize, Error> {
+        self.files.line_index(id, byte_index)
+    }
+
+    fn line_number(&'a self, id: Self::FileId, line_index: usize) -> Result<usize, Error> {
+        self.files.line_number(id, line_index)
+    }
+
+    fn column_number(
+        &'a self,
+        id: Self::FileId,
+        line_index: usize,
+        byte_index: usize,
+    ) -> Result<usize, Error> {
+        self.files.column_number(id, line_index, byte_index)
+    }
+
+    fn location(&'a self, id: Self::FileId, byte_index: usize) -> Result<Location, Error> {
+        self.files.location(id, byte_index)
+    }
+
+    fn line_range(&'a self, id: Self::FileId, line_index: usize) -> Result<Range<usize>, Error> {
+        self.files.line_range(id, line_index)
+    }
+
+    fn is_binary(&'a self, id: Self::FileId) -> bool {
+        self.files.is_binary(id)
+    }
+}
+
+/// A file database that adds the name of the file to the errors of another
+/// file database.
+pub(crate) struct NamedErrors<'a, Inner> {
+    files: &'a Inner,
+}
+
+impl<'a, Inner: Files<'a>> NamedErrors<'a, Inner> {
+    pub(crate) fn new(files: &'a Inner) -> NamedErrors<'a, Inner> {
+        NamedErrors { files }
+    }
+
+    fn in_file(&self, id: Inner::FileId, error: Error) -> Error {
+        match (error, self.files.name(id)) {
+            (error @ Error::FileMissing, _) | (error @ Error::InFile { .. }, _) => error,
+            (error, Ok(name)) => Error::InFile {
+                name: name.to_string(),
+                error: Box::new(error),
+            },
+            (error, Err(_)) => error,
+        }
+    }
+}
+
+impl<'a, 'b, Inner> Files<'b> for NamedErrors<'a, Inner>
+where
+    'a: 'b,
+    Inner: Files<'a>,
+{
+    type FileId = Inner::FileId;
+    type Name = Inner::Name;
+    type Source = Inner::Source;
+
+    fn name(&'b self, id: Self::FileId) -> Result<Self::Name, Error> {
+        self.files.name(id)
+    }
+
+    fn short_name(&'b self, id: Self::FileId) -> Result<Self::Name, Error> {
+        self.files.short_name(id)
+    }
+
+    fn source(&'b self, id: Self::FileId) -> Result<Self::Source, Error> {
+        self.files
+            .source(id)
+            .map_err(|error| self.in_file(id, error))
+    }
+
+    fn line_index(&'b self, id: Self::FileId, byte_index: usize) -> Result<usize, Error> {
+        self.files
+            .line_index(id, byte_index)
+            .map_err(|error| self.in_file(id, error))
+    }
+
+    fn line_number(&'b self, id: Self::FileId, line_index: usize) -> Result<usize, Error> {
+        self.files
+            .line_number(id, line_index)
+            .map_err(|error| self.in_file(id, error))
+    }
+
+    fn column_number(
+        &'b self,
+        id: Self::FileId,
+        line_index: usize,
+        byte_index: usize,
+    ) -> Result<usize, Error> {
+        self.files
+            .column_number(id, line_index, byte_index)
+            .map_err(|error| self.in_file(id, error))
+    }
+
+    fn location(&'b self, id: Self::FileId, byte_index: usize) -> Result<Location, Error> {
+        self.files
+            .location(id, byte_index)
+            .map_err(|error| self.in_file(id, error))
+    }
+
+    fn line_range(&'b self, id: Self::FileId, line_index: usize) -> Result<Range<usize>, Error> {
+        self.files
+            .line_range(id, line_index)
+            .map_err(|error| self.in_file(id, error))
+    }
+
+    fn is_binary(&'b self, id: Self::FileId) -> bool {
+        self.files.is_binary(id)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -440,4 +995,141 @@ mod test {
 
         assert_eq!(line_sources, ["foo\n", "bar\r\n", "\n", "baz"]);
     }
+
+    #[test]
+    fn update() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "foo\nbar");
+        let other_id = files.add("other", "baz");
+
+        files.update(file_id, TEST_SOURCE).unwrap();
+
+        assert_eq!(files.source(file_id).unwrap(), TEST_SOURCE);
+        assert_eq!(files.get(file_id).unwrap().line_starts, [0, 4, 9, 10]);
+        assert_eq!(files.line_range(file_id, 3).unwrap(), 10..13);
+        assert_eq!(files.source(other_id).unwrap(), "baz");
+
+        assert!(matches!(files.update(2, ""), Err(Error::FileMissing)));
+    }
+
+    #[test]
+    fn line_count_and_len() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", TEST_SOURCE);
+        let empty_id = files.add("empty", "");
+
+        assert_eq!(files.line_count(file_id).unwrap(), 4);
+        assert_eq!(files.len(file_id).unwrap(), 13);
+        assert_eq!(files.line_count(empty_id).unwrap(), 1);
+        assert_eq!(files.len(empty_id).unwrap(), 0);
+        assert!(files.get(empty_id).unwrap().is_empty());
+
+        assert!(matches!(files.line_count(2), Err(Error::FileMissing)));
+        assert!(matches!(files.len(2), Err(Error::FileMissing)));
+    }
+
+    #[test]
+    fn lazy_disk_files() {
+        let path = std::env::temp_dir().join("codespan_reporting_lazy_disk_files.txt");
+        std::fs::write(&path, TEST_SOURCE).unwrap();
+
+        let mut files = LazyDiskFiles::new();
+        let file_id = files.add(&path);
+        let missing_id = files.add(path.with_extension("missing"));
+
+        assert!(files.get(file_id).unwrap().loaded.borrow().is_none());
+        assert_eq!(files.name(file_id).unwrap(), path.display().to_string());
+        assert_eq!(
+            files.short_name(file_id).unwrap(),
+            "codespan_reporting_lazy_disk_files.txt",
+        );
+        assert!(files.get(file_id).unwrap().loaded.borrow().is_none());
+
+        assert_eq!(&*files.source(file_id).unwrap(), TEST_SOURCE);
+        assert_eq!(files.line_index(file_id, 11).unwrap(), 3);
+        assert_eq!(files.line_range(file_id, 1).unwrap(), 4..9);
+
+        // The cached source is used after the first load
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&*files.source(file_id).unwrap(), TEST_SOURCE);
+
+        assert!(matches!(files.source(missing_id), Err(Error::Io(_))));
+        assert!(matches!(files.source(2), Err(Error::FileMissing)));
+    }
+
+    #[test]
+    fn cached_files() {
+        let calls = RefCell::new(Vec::new());
+        let files = CachedFiles::new(|file_id: usize| {
+            calls.borrow_mut().push(file_id);
+            TEST_SOURCE.repeat(file_id)
+        });
+
+        assert_eq!(files.name(2).unwrap(), 2);
+        assert!(calls.borrow().is_empty());
+
+        assert_eq!(&*files.source(1).unwrap(), TEST_SOURCE);
+        assert_eq!(files.line_index(1, 11).unwrap(), 3);
+        assert_eq!(files.line_range(1, 1).unwrap(), 4..9);
+        assert_eq!(files.line_range(2, 4).unwrap(), 17..22);
+        assert_eq!(&*calls.borrow(), &[1, 2]);
+
+        // The source is only generated once
+        assert_eq!(&*files.source(2).unwrap(), TEST_SOURCE.repeat(2));
+        assert_eq!(&*calls.borrow(), &[1, 2]);
+    }
+
+    #[test]
+    fn named_errors() {
+        let file = SimpleFile::new("test", TEST_SOURCE);
+        let files = NamedErrors::new(&file);
+
+        assert_eq!(files.line_range((), 3).unwrap(), 10..13);
+
+        let error = files.line_range((), 5).unwrap_err();
+        assert!(matches!(
+            &error,
+            Error::InFile { name, error } if name == "test"
+                && matches!(**error, Error::LineTooLarge { given: 5, max: 3 }),
+        ));
+        assert_eq!(error.to_string(), "test: invalid line 5, maximum line is 3");
+    }
+
+    #[test]
+    fn map_name() {
+        let file = SimpleFile::new("/tmp/test", TEST_SOURCE);
+        let files = MapName::new(file.clone(), |name: &str| name.len());
+
+        assert_eq!(files.name(()).unwrap(), 9);
+        assert_eq!(files.source(()).unwrap(), TEST_SOURCE);
+        assert_eq!(files.line_range((), 1).unwrap(), 4..9);
+        assert_eq!(
+            files.location((), 11).unwrap(),
+            file.location((), 11).unwrap()
+        );
+    }
+
+    #[test]
+    fn static_files_match_simple_file() {
+        let file = SimpleFile::new("test", TEST_SOURCE);
+        let files = StaticFiles::new(&[("other", "baz"), ("test", TEST_SOURCE)]);
+
+        for byte_index in 0..=TEST_SOURCE.len() + 1 {
+            assert_eq!(
+                files.line_index(1, byte_index).unwrap(),
+                file.line_index((), byte_index).unwrap(),
+            );
+        }
+        for line_index in 0..4 {
+            assert_eq!(
+                files.line_range(1, line_index).unwrap(),
+                file.line_range((), line_index).unwrap(),
+            );
+        }
+        assert!(matches!(
+            files.line_range(1, 4),
+            Err(Error::LineTooLarge { given: 4, max: 3 }),
+        ));
+        assert!(matches!(files.source(2), Err(Error::FileMissing)));
+    }
 }
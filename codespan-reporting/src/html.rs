@@ -0,0 +1,310 @@
+//! HTML back-end for rendering diagnostics.
+//!
+//! This is useful for embedding diagnostics in documentation, where they can be
+//! styled with CSS.
+
+use std::io::{self, Write};
+use termcolor::{Color, ColorSpec, WriteColor};
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::files::{Error, Files};
+use crate::term::{self, Config, Styles};
+
+/// Render a diagnostic as a `<pre>` block of HTML.
+///
+/// The diagnostic is laid out using the given config, like [`term::emit`],
+/// but the styles of the config are ignored. Instead, each styled part of the
+/// output is wrapped in a `<span>` with a class describing what it is:
+///
+/// * `header-bug`, `header-error`, `header-warning`, `header-note`,
+///   `header-help`, `header-message`, and `header-index` for the header.
+/// * `label-primary-bug`, `label-primary-error`, `label-primary-warning`,
+///   `label-primary-note`, `label-primary-help`, `label-secondary`, and
+///   `label-secondary-subdued` for labels.
+/// * `line-number`, `source-border`, `note-bullet`, `column-guide`, and
+///   `provenance` for the gutter, notes, and guides.
+///
+/// The source code and messages are escaped, so the output can be inserted
+/// directly into a HTML document.
+///
+/// ```rust
+/// use codespan_reporting::diagnostic::{Diagnostic, Label};
+/// use codespan_reporting::files::SimpleFile;
+/// use codespan_reporting::html;
+/// use codespan_reporting::term::Config;
+///
+/// let file = SimpleFile::new("test", "(+ test \"\")");
+/// let diagnostic = Diagnostic::error()
+///     .with_message("unexpected type in `+` application")
+///     .with_labels(vec![Label::primary((), 8..10)]);
+///
+/// let html = html::emit_html(&Config::default(), &file, &diagnostic).unwrap();
+///
+/// assert!(html.starts_with("<pre><span class=\"header-error\">error</span>"));
+/// assert!(html.contains("(+ test <span class=\"label-primary-error\">&quot;&quot;</span>)"));
+/// ```
+///
+/// The return value covers all error cases. These error case can arise if:
+/// * a file was removed from the file database.
+/// * a file was changed so that it is too small to have an index
+///
+/// [`term::emit`]: crate::term::emit
+pub fn emit_html<'files, F: Files<'files>>(
+    config: &Config,
+    files: &'files F,
+    diagnostic: &Diagnostic<F::FileId>,
+) -> Result<String, Error> {
+    let styles = semantic_styles();
+    let config = Config {
+        styles: styles.clone(),
+        // Terminal hyperlinks would be written into the HTML as-is
+        code_link: None,
+        ..config.clone()
+    };
+
+    let mut buffer = b"<pre>".to_vec();
+    {
+        let mut writer = HtmlWriter::new(&mut buffer, &styles);
+        term::emit(&mut writer, &config, files, diagnostic)?;
+        writer.reset()?;
+    }
+    buffer.extend_from_slice(b"</pre>");
+
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+/// A writer that renders colored output as HTML.
+///
+/// Text is escaped, and colors are turned into `<span>` elements with a class
+/// named after the field of the [`Styles`] that uses that color, like
+/// `header-error` for [`Styles::header_error`]. If several fields use the same
+/// color, the first field in declaration order names the class. Colors that
+/// are not used by the styles are written without a `<span>`.
+pub struct HtmlWriter<W> {
+    upstream: W,
+    classes: Vec<(ColorSpec, &'static str)>,
+    in_span: bool,
+}
+
+impl<W: Write> HtmlWriter<W> {
+    /// Create a new writer, naming the classes of colors after the given
+    /// styles.
+    pub fn new(upstream: W, styles: &Styles) -> HtmlWriter<W> {
+        HtmlWriter {
+            upstream,
+            classes: class_names(styles),
+            in_span: false,
+        }
+    }
+
+    /// Get the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.upstream
+    }
+}
+
+impl<W: Write> Write for HtmlWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut last_term = 0;
+        for (i, byte) in buf.iter().enumerate() {
+            let escape = match byte {
+                b'<' => &b"&lt;"[..],
+                b'>' => &b"&gt;"[..],
+                b'&' => &b"&amp;"[..],
+                b'"' => &b"&quot;"[..],
+                _ => continue,
+            };
+            self.upstream.write_all(&buf[last_term..i])?;
+            self.upstream.write_all(escape)?;
+            last_term = i + 1;
+        }
+        self.upstream.write_all(&buf[last_term..])?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.upstream.flush()
+    }
+}
+
+impl<W: Write> WriteColor for HtmlWriter<W> {
+    fn supports_color(&self) -> bool {
+        true
+    }
+
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        self.reset()?;
+
+        let class = self
+            .classes
+            .iter()
+            .find(|(class_spec, _)| class_spec == spec);
+        if let Some((_, class)) = class {
+            write!(self.upstream, "<span class=\"{}\">", class)?;
+            self.in_span = true;
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        if self.in_span {
+            self.upstream.write_all(b"</span>")?;
+            self.in_span = false;
+        }
+        Ok(())
+    }
+}
+
+/// The class names of the colors used by some styles.
+fn class_names(styles: &Styles) -> Vec<(ColorSpec, &'static str)> {
+    let mut classes = vec![
+        (styles.header_bug.clone(), "header-bug"),
+        (styles.header_error.clone(), "header-error"),
+        (styles.header_warning.clone(), "header-warning"),
+        (styles.header_note.clone(), "header-note"),
+        (styles.header_help.clone(), "header-help"),
+        (styles.header_message.clone(), "header-message"),
+        (styles.header_index.clone(), "header-index"),
+        (styles.primary_label_bug.clone(), "label-primary-bug"),
+        (styles.primary_label_error.clone(), "label-primary-error"),
+        (
+            styles.primary_label_warning.clone(),
+            "label-primary-warning",
+        ),
+        (styles.primary_label_note.clone(), "label-primary-note"),
+        (styles.primary_label_help.clone(), "label-primary-help"),
+        (styles.secondary_label.clone(), "label-secondary"),
+        (
+            styles.secondary_label_subdued.clone(),
+            "label-secondary-subdued",
+        ),
+        (styles.line_number.clone(), "line-number"),
+        (styles.source_border.clone(), "source-border"),
+    ];
+    if styles.source_border_by_severity.is_some() {
+        let borders = [
+            (Severity::Help, "source-border-help"),
+            (Severity::Note, "source-border-note"),
+            (Severity::Warning, "source-border-warning"),
+            (Severity::Error, "source-border-error"),
+            (Severity::Bug, "source-border-bug"),
+        ];
+        classes.extend(
+            borders
+                .iter()
+                .map(|(severity, name)| (styles.border(*severity).clone(), *name)),
+        );
+    }
+    classes.push((styles.note_bullet.clone(), "note-bullet"));
+    classes.push((styles.column_guide.clone(), "column-guide"));
+    classes.push((styles.provenance.clone(), "provenance"));
+    classes
+}
+
+/// Styles where every field has a different color, so that the class names of
+/// [`HtmlWriter`] always match the field that was used.
+fn semantic_styles() -> Styles {
+    let mut index = 0;
+    let mut next = || {
+        index += 1;
+        ColorSpec::new().set_fg(Some(Color::Ansi256(index))).clone()
+    };
+
+    Styles {
+        header_bug: next(),
+        header_error: next(),
+        header_warning: next(),
+        header_note: next(),
+        header_help: next(),
+        header_message: next(),
+        header_index: next(),
+        primary_label_bug: next(),
+        primary_label_error: next(),
+        primary_label_warning: next(),
+        primary_label_note: next(),
+        primary_label_help: next(),
+        secondary_label: next(),
+        secondary_label_subdued: next(),
+        line_number: next(),
+        source_border: next(),
+        source_border_by_severity: None,
+        note_bullet: next(),
+        column_guide: next(),
+        provenance: next(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::diagnostic::Label;
+    use crate::files::SimpleFile;
+
+    #[test]
+    fn escapes_text() {
+        let mut writer = HtmlWriter::new(Vec::new(), &Styles::default());
+        write!(writer, "<a href=\"x\">&</a>").unwrap();
+
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;",
+        );
+    }
+
+    #[test]
+    fn first_style_names_shared_colors() {
+        let styles = Styles::default();
+        let mut writer = HtmlWriter::new(Vec::new(), &styles);
+        writer.set_color(&styles.primary_label_bug).unwrap();
+        write!(writer, "bug").unwrap();
+        writer.set_color(&styles.primary_label_error).unwrap();
+        write!(writer, "error").unwrap();
+        writer.set_color(&ColorSpec::new()).unwrap();
+        write!(writer, "plain").unwrap();
+
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            concat!(
+                "<span class=\"label-primary-bug\">bug</span>",
+                "<span class=\"label-primary-bug\">error</span>",
+                "plain",
+            ),
+        );
+    }
+
+    #[test]
+    fn emit_html_classes() {
+        let file = SimpleFile::new("main.rs", "let x: Vec<u8> = 1;\n");
+        let diagnostic = Diagnostic::warning()
+            .with_message("mismatched types")
+            .with_labels(vec![
+                Label::primary((), 17..18).with_message("expected `Vec<u8>`"),
+                Label::secondary((), 7..14),
+            ])
+            .with_notes(vec!["found `{integer}`".to_owned()]);
+
+        let html = emit_html(&Config::default(), &file, &diagnostic).unwrap();
+
+        assert_eq!(
+            html,
+            concat!(
+                "<pre><span class=\"header-warning\">warning</span>",
+                "<span class=\"header-message\">: mismatched types</span>\n",
+                "  <span class=\"source-border\">┌─</span> main.rs:1:18\n",
+                "  <span class=\"source-border\">│</span>\n",
+                "<span class=\"line-number\">1</span> <span class=\"source-border\">│</span> ",
+                "let x: Vec&lt;u8&gt; = ",
+                "<span class=\"label-primary-warning\">1</span>;\n",
+                "  <span class=\"source-border\">│</span>        ",
+                "<span class=\"label-secondary\">-------</span>   ",
+                "<span class=\"label-primary-warning\">^</span> ",
+                "<span class=\"label-primary-warning\">expected `Vec&lt;u8&gt;`</span>\n",
+                "  <span class=\"source-border\">│</span>\n",
+                "  <span class=\"note-bullet\">=</span> found `{integer}`\n",
+                "\n",
+                "</pre>",
+            ),
+        );
+    }
+}
@@ -4,4 +4,10 @@
 
 pub mod diagnostic;
 pub mod files;
+#[cfg(feature = "html")]
+pub mod html;
+#[cfg(feature = "markdown")]
+pub mod markdown;
+#[cfg(feature = "sarif")]
+pub mod sarif;
 pub mod term;
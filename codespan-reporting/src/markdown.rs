@@ -0,0 +1,383 @@
+//! Markdown back-end for rendering diagnostics.
+//!
+//! This is useful for pasting diagnostics into places that render Markdown,
+//! like issue trackers and documentation.
+
+use std::fmt::Write;
+
+use crate::diagnostic::{Diagnostic, Label, LabelStyle};
+use crate::files::{Error, Files};
+use crate::term::Config;
+
+/// Render a batch of diagnostics as Markdown.
+///
+/// Each diagnostic is rendered as a heading containing its severity, code, and
+/// message, followed by a fenced code block for each file referenced by its
+/// labels, and a list of its notes. The code blocks contain the lines of
+/// source code that are labeled, each followed by a `>` annotation line for
+/// each label that starts on it. Labels that span multiple lines are
+/// underlined on their first line. Tabs in the source code are expanded to
+/// spaces according to [`Config::tab_width`], severities are named according
+/// to [`Config::severity_names`], and the other fields of the config are
+/// ignored.
+///
+/// ````markdown
+/// ### error[E0308]: mismatched types
+///
+/// ```text
+/// --> test:2:9
+/// 2 | (+ test "")
+///   >         ^^ expected `Int` but found `String`
+/// ```
+///
+/// - expected type `Int`
+///   found type `String`
+/// ````
+///
+/// The return value covers all error cases. These error case can arise if:
+/// * a file was removed from the file database.
+/// * a file was changed so that it is too small to have an index
+///
+/// This requires the `markdown` feature.
+///
+/// [`Config::tab_width`]: crate::term::Config::tab_width
+/// [`Config::severity_names`]: crate::term::Config::severity_names
+pub fn to_markdown<'files, F: Files<'files>>(
+    config: &Config,
+    files: &'files F,
+    diagnostics: &[Diagnostic<F::FileId>],
+) -> Result<String, Error> {
+    let mut output = String::new();
+    for diagnostic in diagnostics {
+        render_diagnostic(&mut output, config, files, diagnostic)?;
+    }
+    Ok(output)
+}
+
+fn render_diagnostic<'files, F: Files<'files>>(
+    output: &mut String,
+    config: &Config,
+    files: &'files F,
+    diagnostic: &Diagnostic<F::FileId>,
+) -> Result<(), Error> {
+    // Heading
+    //
+    // ```text
+    // ### error[E0308]: mismatched types
+    // ```
+    let names = &config.severity_names;
+    write!(output, "### {}", names.name(diagnostic.severity)).unwrap();
+    if let Some(code) = diagnostic.code.as_deref().filter(|code| !code.is_empty()) {
+        write!(output, "[{}]", code).unwrap();
+    }
+    writeln!(output, ": {}", diagnostic.message).unwrap();
+    writeln!(output).unwrap();
+
+    // Source code, grouped by file in the order that they first appear
+    let mut file_ids = Vec::<F::FileId>::new();
+    for label in &diagnostic.labels {
+        if !file_ids.contains(&label.file_id) {
+            file_ids.push(label.file_id);
+        }
+    }
+    for file_id in file_ids {
+        let labels = diagnostic
+            .labels
+            .iter()
+            .filter(|label| label.file_id == file_id)
+            .collect::<Vec<_>>();
+        render_file(output, config, files, file_id, &labels)?;
+        writeln!(output).unwrap();
+    }
+
+    // Notes
+    //
+    // ```text
+    // - expected type `Int`
+    //   found type `String`
+    // ```
+    let notes = diagnostic
+        .notes
+        .iter()
+        .map(|note| (None, note.as_str()))
+        .chain(
+            diagnostic
+                .rich_notes
+                .iter()
+                .map(|note| (Some(note.severity), note.message.as_str())),
+        )
+        .collect::<Vec<_>>();
+    for (severity, note) in &notes {
+        write!(output, "-").unwrap();
+        if let Some(severity) = severity {
+            write!(output, " {}:", names.name(*severity)).unwrap();
+        }
+        for (i, line) in note.lines().enumerate() {
+            match i {
+                0 => writeln!(output, " {}", line).unwrap(),
+                _ => writeln!(output, "  {}", line).unwrap(),
+            }
+        }
+    }
+    if !notes.is_empty() {
+        writeln!(output).unwrap();
+    }
+
+    Ok(())
+}
+
+/// A fenced code block containing the labeled lines of a file.
+///
+/// The fence is made longer than any run of backticks in the block, so that
+/// the source code and messages cannot close it early.
+///
+/// ````text
+/// ```text
+/// --> test:2:9
+/// 2 | (+ test "")
+///   >         ^^ expected `Int` but found `String`
+/// ```
+/// ````
+fn render_file<'files, F: Files<'files>>(
+    output: &mut String,
+    config: &Config,
+    files: &'files F,
+    file_id: F::FileId,
+    labels: &[&Label<F::FileId>],
+) -> Result<(), Error> {
+    let source = files.source(file_id)?;
+    let source = source.as_ref();
+
+    // Labeled lines, sorted by line index, with the labels starting on them
+    let mut lines = Vec::<(usize, Vec<&Label<F::FileId>>)>::new();
+    for label in labels {
+        let line_index = files.line_index(file_id, label.range.start)?;
+        match lines.iter_mut().find(|(index, _)| *index == line_index) {
+            Some((_, line_labels)) => line_labels.push(label),
+            None => lines.push((line_index, vec![label])),
+        }
+    }
+    lines.sort_by_key(|(line_index, _)| *line_index);
+
+    let mut gutter_width = 0;
+    for (line_index, _) in &lines {
+        let line_number = files.line_number(file_id, *line_index)?;
+        gutter_width = std::cmp::max(gutter_width, line_number.to_string().len());
+    }
+
+    // The locus of the first primary label, or the first label
+    let locus_label = labels
+        .iter()
+        .find(|label| label.style == LabelStyle::Primary)
+        .unwrap_or(&labels[0]);
+    let location = files.location(file_id, locus_label.range.start)?;
+
+    let mut block = String::new();
+    writeln!(
+        block,
+        "--> {}:{}:{}",
+        files.name(file_id)?,
+        location.line_number,
+        location.column_number,
+    )
+    .unwrap();
+
+    for (line_index, line_labels) in &mut lines {
+        let line_range = files.line_range(file_id, *line_index)?;
+        let line_source = source
+            .get(line_range.clone())
+            .ok_or(Error::InvalidCharBoundary {
+                given: line_range.start,
+            })?
+            .trim_end_matches(['\n', '\r', '\0'].as_ref());
+        let line_number = files.line_number(file_id, *line_index)?;
+
+        // Source line
+        //
+        // ```text
+        // 2 | (+ test "")
+        // ```
+        write!(block, "{:>width$} |", line_number, width = gutter_width).unwrap();
+        if !line_source.is_empty() {
+            write!(block, " {}", expand_tabs(config, line_source)).unwrap();
+        }
+        writeln!(block).unwrap();
+
+        // Annotation lines
+        //
+        // ```text
+        //   >         ^^ expected `Int` but found `String`
+        // ```
+        line_labels.sort_by_key(|label| label.range.start);
+        for label in line_labels.iter() {
+            let start = std::cmp::min(label.range.start - line_range.start, line_source.len());
+            // Labels that span multiple lines are underlined to the end of the line
+            let end = std::cmp::min(label.range.end - line_range.start, line_source.len());
+            let end = std::cmp::max(start, end);
+            let caret = match label.style {
+                LabelStyle::Primary => "^",
+                LabelStyle::Secondary => "-",
+            };
+
+            let prefix_width = display_width(config, line_source, start, label.range.start)?;
+            let end_width = display_width(config, line_source, end, label.range.end)?;
+
+            write!(block, "{:>width$} > ", "", width = gutter_width).unwrap();
+            write!(block, "{}", " ".repeat(prefix_width)).unwrap();
+            // Ensure that we print at least one caret, even when we have a
+            // zero-length source range.
+            let caret_width = std::cmp::max(end_width - prefix_width, 1);
+            write!(block, "{}", caret.repeat(caret_width)).unwrap();
+            if !label.message.is_empty() {
+                write!(block, " {}", label.message).unwrap();
+            }
+            writeln!(block).unwrap();
+        }
+    }
+
+    let fence = "`".repeat(std::cmp::max(longest_backtick_run(&block) + 1, 3));
+    writeln!(output, "{}text", fence).unwrap();
+    output.push_str(&block);
+    writeln!(output, "{}", fence).unwrap();
+
+    Ok(())
+}
+
+/// Replace the tabs in a line of source code with spaces.
+fn expand_tabs(config: &Config, source: &str) -> String {
+    let mut expanded = String::with_capacity(source.len());
+    let mut column = 0;
+    for ch in source.chars() {
+        let width = config.char_display_width(column, ch);
+        match ch {
+            '\t' => expanded.push_str(&" ".repeat(width)),
+            ch => expanded.push(ch),
+        }
+        column += width;
+    }
+    expanded
+}
+
+/// The number of columns that a line of source code occupies up to the given
+/// byte index, after expanding tabs.
+fn display_width(config: &Config, source: &str, end: usize, given: usize) -> Result<usize, Error> {
+    let source = source
+        .get(..end)
+        .ok_or(Error::InvalidCharBoundary { given })?;
+    Ok(source.chars().fold(0, |column, ch| {
+        column + config.char_display_width(column, ch)
+    }))
+}
+
+/// The length of the longest run of backticks in some text.
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|ch| ch != '`').map(str::len).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::diagnostic::Note;
+    use crate::files::SimpleFiles;
+
+    #[test]
+    fn one_line() {
+        let mut files = SimpleFiles::new();
+
+        let file_id = files.add(
+            "one_line.rs",
+            "fn main() {\n    let mut v = vec![Some(\"foo\"), Some(\"bar\")];\n    v.push(v.pop().unwrap());\n}\n",
+        );
+        let diagnostics = vec![
+            Diagnostic::error()
+                .with_code("E0499")
+                .with_message("cannot borrow `v` as mutable more than once at a time")
+                .with_labels(vec![
+                    Label::primary(file_id, 71..72)
+                        .with_message("second mutable borrow occurs here"),
+                    Label::secondary(file_id, 64..65)
+                        .with_message("first borrow later used by call"),
+                    Label::secondary(file_id, 66..70)
+                        .with_message("first mutable borrow occurs here"),
+                ]),
+            Diagnostic::error()
+                .with_message("aborting due to previous error")
+                .with_notes(vec![
+                    "For more information about this error, try `rustc --explain E0499`."
+                        .to_owned(),
+                ]),
+        ];
+
+        assert_eq!(
+            to_markdown(&Config::default(), &files, &diagnostics).unwrap(),
+            concat!(
+                "### error[E0499]: cannot borrow `v` as mutable more than once at a time\n",
+                "\n",
+                "```text\n",
+                "--> one_line.rs:3:12\n",
+                "3 |     v.push(v.pop().unwrap());\n",
+                "  >     - first borrow later used by call\n",
+                "  >       ---- first mutable borrow occurs here\n",
+                "  >            ^ second mutable borrow occurs here\n",
+                "```\n",
+                "\n",
+                "### error: aborting due to previous error\n",
+                "\n",
+                "- For more information about this error, try `rustc --explain E0499`.\n",
+                "\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn tabs_and_backticks() {
+        let mut files = SimpleFiles::new();
+
+        let file_id = files.add("tabs.md", "\tlet s = ```;\n");
+        let diagnostics = vec![Diagnostic::warning()
+            .with_message("unterminated code span")
+            .with_labels(vec![
+                Label::primary(file_id, 9..12).with_message("opened by ```")
+            ])];
+
+        let config = Config {
+            tab_width: 2,
+            ..Config::default()
+        };
+        assert_eq!(
+            to_markdown(&config, &files, &diagnostics).unwrap(),
+            concat!(
+                "### warning: unterminated code span\n",
+                "\n",
+                "````text\n",
+                "--> tabs.md:1:10\n",
+                "1 |   let s = ```;\n",
+                "  >           ^^^ opened by ```\n",
+                "````\n",
+                "\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn severity_names() {
+        let files = SimpleFiles::<&str, &str>::new();
+        let diagnostics = vec![Diagnostic::<usize>::warning()
+            .with_message("unused variable")
+            .with_rich_notes(vec![Note::help("remove it")])];
+
+        let mut config = Config::default();
+        config.severity_names.warning = "avertissement".to_owned();
+        config.severity_names.help = "aide".to_owned();
+        assert_eq!(
+            to_markdown(&config, &files, &diagnostics).unwrap(),
+            concat!(
+                "### avertissement: unused variable\n",
+                "\n",
+                "- aide: remove it\n",
+                "\n",
+            ),
+        );
+    }
+}
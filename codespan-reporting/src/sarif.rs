@@ -0,0 +1,252 @@
+//! [SARIF] back-end for reporting diagnostics to static analysis tools.
+//!
+//! This is useful for uploading diagnostics from continuous integration, for
+//! example to GitHub code scanning.
+//!
+//! [SARIF]: https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+
+use serde_json::{json, Value};
+
+use crate::diagnostic::{Diagnostic, Label, LabelStyle, Severity};
+use crate::files::{Error, Files};
+use crate::term::SeverityNames;
+
+/// Convert a batch of diagnostics to a SARIF 2.1.0 `run` object, reported by
+/// the tool with the given name.
+///
+/// Each diagnostic becomes a `result`:
+///
+/// * The severity is mapped to the `level` of the result. Bugs and errors are
+///   `"error"`, warnings are `"warning"`, and notes and help are `"note"`.
+/// * The code is used as the `ruleId`. Diagnostics without a code are given a
+///   rule id made from the words of their message, like
+///   `"unexpected-type-in-application"`.
+/// * The labels become `locations`, starting with the primary labels, with
+///   regions made of the line and column numbers from the file database.
+/// * The notes become `relatedLocations` that only contain a message.
+/// * The source of the diagnostic, if any, is stored as the `source` entry of
+///   the `properties` of the result.
+///
+/// Columns are counted in Unicode code points, like [`Files::column_number`].
+///
+/// The run can be wrapped in a SARIF log like so:
+///
+/// ```rust
+/// use codespan_reporting::diagnostic::{Diagnostic, Label};
+/// use codespan_reporting::files::SimpleFile;
+/// use codespan_reporting::sarif;
+///
+/// let file = SimpleFile::new("test", "(+ test \"\")");
+/// let diagnostics = [Diagnostic::error()
+///     .with_message("unexpected type in `+` application")
+///     .with_labels(vec![Label::primary((), 8..10)])];
+///
+/// let log = serde_json::json!({
+///     "version": "2.1.0",
+///     "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+///     "runs": [sarif::to_sarif("my-compiler", &file, &diagnostics).unwrap()],
+/// });
+///
+/// assert_eq!(
+///     log["runs"][0]["results"][0]["ruleId"],
+///     "unexpected-type-in-application",
+/// );
+/// ```
+///
+/// The return value covers all error cases. These error case can arise if:
+/// * a file was removed from the file database.
+/// * a file was changed so that it is too small to have an index
+///
+/// [`Files::column_number`]: crate::files::Files::column_number
+pub fn to_sarif<'files, F: Files<'files>>(
+    tool_name: &str,
+    files: &'files F,
+    diagnostics: &[Diagnostic<F::FileId>],
+) -> Result<Value, Error> {
+    let results = diagnostics
+        .iter()
+        .map(|diagnostic| to_result(files, diagnostic))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(json!({
+        "tool": {
+            "driver": {
+                "name": tool_name,
+            },
+        },
+        "columnKind": "unicodeCodePoints",
+        "results": results,
+    }))
+}
+
+fn to_result<'files, F: Files<'files>>(
+    files: &'files F,
+    diagnostic: &Diagnostic<F::FileId>,
+) -> Result<Value, Error> {
+    let level = match diagnostic.severity {
+        Severity::Bug | Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note | Severity::Help => "note",
+    };
+    let rule_id = match diagnostic.code.as_deref() {
+        Some(code) if !code.is_empty() => code.to_owned(),
+        _ => rule_id_from_message(&diagnostic.message),
+    };
+
+    // Primary labels come first, as tools often only show the first location.
+    let mut labels = diagnostic.labels.iter().collect::<Vec<_>>();
+    labels.sort_by_key(|label| label.style != LabelStyle::Primary);
+    let locations = labels
+        .into_iter()
+        .map(|label| to_location(files, label))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let names = SeverityNames::default();
+    let related_locations = diagnostic
+        .notes
+        .iter()
+        .cloned()
+        .chain(diagnostic.rich_notes.iter().map(|note| match note.plain {
+            true => note.message.clone(),
+            false => format!("{}: {}", names.name(note.severity), note.message),
+        }))
+        .map(|note| json!({ "message": { "text": note } }))
+        .collect::<Vec<_>>();
+
+    let mut result = json!({
+        "ruleId": rule_id,
+        "level": level,
+        "message": {
+            "text": diagnostic.message,
+        },
+        "locations": locations,
+    });
+    if !related_locations.is_empty() {
+        result["relatedLocations"] = Value::Array(related_locations);
+    }
+    if let Some(source) = &diagnostic.source {
+        result["properties"] = json!({ "source": source });
+    }
+
+    Ok(result)
+}
+
+fn to_location<'files, F: Files<'files>>(
+    files: &'files F,
+    label: &Label<F::FileId>,
+) -> Result<Value, Error> {
+    let start = files.location(label.file_id, label.range.start)?;
+    let end = files.location(label.file_id, label.range.end)?;
+
+    let mut location = json!({
+        "physicalLocation": {
+            "artifactLocation": {
+                "uri": files.name(label.file_id)?.to_string(),
+            },
+            "region": {
+                "startLine": start.line_number,
+                "startColumn": start.column_number,
+                "endLine": end.line_number,
+                "endColumn": end.column_number,
+            },
+        },
+    });
+    if !label.message.is_empty() {
+        location["message"] = json!({ "text": label.message });
+    }
+
+    Ok(location)
+}
+
+/// Make a rule id out of the alphanumeric words of a message, for
+/// diagnostics without a code.
+fn rule_id_from_message(message: &str) -> String {
+    let words = message
+        .split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+
+    if words.is_empty() {
+        "diagnostic".to_owned()
+    } else {
+        words.join("-")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::files::SimpleFiles;
+
+    #[test]
+    fn results() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "(+ test \"\")\n(+ 1 🦀)\n");
+
+        let diagnostics = vec![
+            Diagnostic::warning()
+                .with_message("Unused crab!")
+                .with_labels(vec![
+                    Label::secondary(file_id, 0..1),
+                    Label::primary(file_id, 17..21).with_message("this crab"),
+                ])
+                .with_notes(vec!["crabs are not numbers".to_owned()]),
+            Diagnostic::bug()
+                .with_code("B0001")
+                .with_message("")
+                .with_source("borrowck"),
+        ];
+
+        assert_eq!(
+            to_sarif("test-tool", &files, &diagnostics).unwrap(),
+            json!({
+                "tool": { "driver": { "name": "test-tool" } },
+                "columnKind": "unicodeCodePoints",
+                "results": [
+                    {
+                        "ruleId": "unused-crab",
+                        "level": "warning",
+                        "message": { "text": "Unused crab!" },
+                        "locations": [
+                            {
+                                "physicalLocation": {
+                                    "artifactLocation": { "uri": "test" },
+                                    "region": {
+                                        "startLine": 2,
+                                        "startColumn": 6,
+                                        "endLine": 2,
+                                        "endColumn": 7,
+                                    },
+                                },
+                                "message": { "text": "this crab" },
+                            },
+                            {
+                                "physicalLocation": {
+                                    "artifactLocation": { "uri": "test" },
+                                    "region": {
+                                        "startLine": 1,
+                                        "startColumn": 1,
+                                        "endLine": 1,
+                                        "endColumn": 2,
+                                    },
+                                },
+                            },
+                        ],
+                        "relatedLocations": [
+                            { "message": { "text": "crabs are not numbers" } },
+                        ],
+                    },
+                    {
+                        "ruleId": "B0001",
+                        "level": "error",
+                        "message": { "text": "" },
+                        "locations": [],
+                        "properties": { "source": "borrowck" },
+                    },
+                ],
+            }),
+        );
+    }
+}
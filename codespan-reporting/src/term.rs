@@ -1,18 +1,24 @@
 //! Terminal back-end for emitting diagnostics.
 
+use std::fmt;
 use std::str::FromStr;
 use termcolor::{ColorChoice, WriteColor};
 
 use crate::diagnostic::Diagnostic;
-use crate::files::Files;
+use crate::files::{Error, Files, NamedErrors};
 
 mod config;
+#[cfg(feature = "json")]
+mod json;
 mod renderer;
 mod views;
 
 pub use termcolor;
 
-pub use self::config::{Chars, Config, DisplayStyle, Styles};
+pub use self::config::{
+    CaretRowOrder, Chars, Config, DisplayStyle, FileSortOrder, LocusFormat, SecondaryEmphasis,
+    SeverityNames, Styles,
+};
 
 /// A command line argument that configures the coloring of the output.
 ///
@@ -72,6 +78,30 @@ impl FromStr for ColorArg {
     }
 }
 
+impl ColorArg {
+    /// The canonical name of the argument, as listed in [`ColorArg::VARIANTS`].
+    pub fn as_str(&self) -> &'static str {
+        match self.0 {
+            ColorChoice::Auto => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::AlwaysAnsi => "ansi",
+            ColorChoice::Never => "never",
+        }
+    }
+}
+
+impl AsRef<str> for ColorArg {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for ColorArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl From<ColorArg> for ColorChoice {
     fn from(x: ColorArg) -> Self {
         x.0
@@ -91,13 +121,798 @@ pub fn emit<'files, F: Files<'files>>(
     diagnostic: &Diagnostic<F::FileId>,
 ) -> Result<(), super::files::Error> {
     use self::renderer::Renderer;
-    use self::views::{RichDiagnostic, ShortDiagnostic};
 
-    let mut renderer = Renderer::new(writer, config);
+    render(
+        &mut Renderer::new(writer, config),
+        config,
+        files,
+        diagnostic,
+        0,
+    )
+}
+
+/// Render a diagnostic in the configured display style, with a gutter at least
+/// `min_outer_padding` columns wide in the [`DisplayStyle::Rich`] style.
+fn render<'files, F: Files<'files>>(
+    renderer: &mut self::renderer::Renderer<'_, '_>,
+    config: &Config,
+    files: &'files F,
+    diagnostic: &Diagnostic<F::FileId>,
+    min_outer_padding: usize,
+) -> Result<(), super::files::Error> {
+    use self::views::{LinearDiagnostic, RichDiagnostic, ShortDiagnostic};
+
+    // Name the file in errors that happen while looking up its contents
+    let files = &NamedErrors::new(files);
+    check_label_ranges(files, diagnostic)?;
+
     match config.display_style {
-        DisplayStyle::Rich => RichDiagnostic::new(diagnostic, config).render(files, &mut renderer),
-        DisplayStyle::Medium => ShortDiagnostic::new(diagnostic, true).render(files, &mut renderer),
-        DisplayStyle::Short => ShortDiagnostic::new(diagnostic, false).render(files, &mut renderer),
+        DisplayStyle::Rich if config.number_labels => {
+            let diagnostic = number_labels(diagnostic);
+            RichDiagnostic::new(&diagnostic, config)
+                .with_min_outer_padding(min_outer_padding)
+                .render(files, renderer)
+        }
+        DisplayStyle::Rich => RichDiagnostic::new(diagnostic, config)
+            .with_min_outer_padding(min_outer_padding)
+            .render(files, renderer),
+        DisplayStyle::Medium => ShortDiagnostic::new(
+            diagnostic,
+            true,
+            config.medium_includes_first_note,
+            config.compact_locus,
+        )
+        .render(files, renderer),
+        DisplayStyle::Short => ShortDiagnostic::new(diagnostic, false, false, config.compact_locus)
+            .render(files, renderer),
+        DisplayStyle::Linear => LinearDiagnostic::new(diagnostic, config).render(files, renderer),
+    }
+}
+
+/// Reject labels that extend past the end of their file, for example after the
+/// file was updated, rather than rendering them at its end.
+fn check_label_ranges<'files, F: Files<'files>>(
+    files: &'files F,
+    diagnostic: &Diagnostic<F::FileId>,
+) -> Result<(), super::files::Error> {
+    for label in &diagnostic.labels {
+        let max = files.source(label.file_id)?.as_ref().len();
+        if label.range.end > max {
+            return Err(Error::InFile {
+                name: files.name(label.file_id)?.to_string(),
+                error: Box::new(Error::IndexTooLarge {
+                    given: label.range.end,
+                    max,
+                }),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The width of the line numbers in the gutter of a diagnostic, when it is
+/// rendered in the [`DisplayStyle::Rich`] display style.
+///
+/// This is computed in the same way as when the diagnostic is emitted, taking
+/// into account the context lines, suggestions, and line number offset of the
+/// config, so it can be used to align other output with the source snippets.
+///
+/// ```rust
+/// use codespan_reporting::diagnostic::{Diagnostic, Label};
+/// use codespan_reporting::files::SimpleFile;
+/// use codespan_reporting::term::{self, Config};
+///
+/// let file = SimpleFile::new("test", "a\n".repeat(100));
+/// let diagnostic = Diagnostic::error().with_labels(vec![Label::primary((), 0..1)]);
+///
+/// let config = Config::default();
+/// assert_eq!(term::gutter_width(&config, &file, &diagnostic).unwrap(), 1);
+///
+/// let config = Config {
+///     line_number_offset: 99,
+///     ..Config::default()
+/// };
+/// assert_eq!(term::gutter_width(&config, &file, &diagnostic).unwrap(), 3);
+/// ```
+///
+/// See [`emit`] for the conditions under which this can fail.
+///
+/// [`DisplayStyle::Rich`]: DisplayStyle::Rich
+pub fn gutter_width<'files, F: Files<'files>>(
+    config: &Config,
+    files: &'files F,
+    diagnostic: &Diagnostic<F::FileId>,
+) -> Result<usize, super::files::Error> {
+    use self::views::RichDiagnostic;
+
+    RichDiagnostic::new(diagnostic, config).outer_padding(&NamedErrors::new(files))
+}
+
+/// Prefix the message of each label with its 1-based position in the labels of
+/// the diagnostic, for [`Config::number_labels`].
+fn number_labels<FileId: Clone>(diagnostic: &Diagnostic<FileId>) -> Diagnostic<FileId> {
+    let mut diagnostic = diagnostic.clone();
+    for (index, label) in diagnostic.labels.iter_mut().enumerate() {
+        label.message = if label.message.is_empty() {
+            format!("[{}]", index + 1)
+        } else {
+            format!("[{}] {}", index + 1, label.message)
+        };
+    }
+    diagnostic
+}
+
+/// Render a diagnostic to a string without color, along with the source line
+/// that each line of the output corresponds to.
+///
+/// Lines of output that show a line of source code, as well as the carets and
+/// label messages rendered underneath it, map to the file and index of that
+/// line. Other lines, like headers, borders, and notes, map to `None`. This is
+/// useful for mapping rows of output back to the source code, for example to
+/// open the source when a row is clicked in a terminal user interface.
+///
+/// See [`emit`] for the conditions under which this can fail.
+#[allow(clippy::type_complexity)]
+pub fn emit_with_line_map<'files, F: Files<'files>>(
+    config: &Config,
+    files: &'files F,
+    diagnostic: &Diagnostic<F::FileId>,
+) -> Result<(String, Vec<Option<(F::FileId, usize)>>), super::files::Error> {
+    use self::renderer::Renderer;
+
+    let mut writer = termcolor::NoColor::new(Vec::<u8>::new());
+    let mut renderer = Renderer::new(&mut writer, config);
+    renderer.track_source_lines();
+    render(&mut renderer, config, files, diagnostic, 0)?;
+
+    let line_map = renderer
+        .into_source_lines()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|source_line| {
+            source_line.map(|(label_index, line_index)| {
+                (diagnostic.labels[label_index].file_id, line_index)
+            })
+        })
+        .collect();
+    let output = String::from_utf8_lossy(&writer.into_inner()).into_owned();
+
+    Ok((output, line_map))
+}
+
+/// Emit a diagnostic as a single line of JSON using the given writer and files.
+///
+/// The resulting object contains the severity, code, message, notes, and rich
+/// notes of the diagnostic, along with the file name, priority, and the start
+/// and end line and column numbers of each label and suggested edit. The code
+/// is left out if it is missing or empty, and the source if it is missing. The keys of each object are sorted,
+/// so the output is stable.
+///
+/// ```text
+/// {"code":"E0001","labels":[{"end":{"column":11,"line":2},"file":"test","message":"expected `Int` but found `String`","priority":0,"start":{"column":9,"line":2},"style":"primary"}],"message":"unexpected type in `+` application","notes":[],"rich_notes":[],"severity":"error","suggestions":[]}
+/// ```
+///
+/// This requires the `json` feature.
+///
+/// See [`emit`] for the conditions under which this can fail.
+#[cfg(feature = "json")]
+pub fn emit_json<'files, F: Files<'files>>(
+    writer: &mut dyn std::io::Write,
+    files: &'files F,
+    diagnostic: &Diagnostic<F::FileId>,
+) -> Result<(), super::files::Error> {
+    json::emit(writer, files, diagnostic)
+}
+
+/// Emit a diagnostic without color to a [`fmt::Write`], like a `String`.
+///
+/// The output is written directly to the writer, without going through an
+/// intermediate byte buffer, so a writer backed by a fixed-size buffer can be
+/// used to avoid allocating the output. The output is cut off if it does not
+/// fit, and an [`Error::Io`] is returned. Note that the renderer still
+/// allocates while laying out the diagnostic, so this crate cannot be used
+/// without an allocator.
+///
+/// ```rust
+/// use codespan_reporting::diagnostic::Diagnostic;
+/// use codespan_reporting::files::SimpleFile;
+/// use codespan_reporting::term::{self, Config};
+///
+/// let file = SimpleFile::new("test", "");
+/// let diagnostic = Diagnostic::warning().with_message("empty file");
+///
+/// let mut output = String::new();
+/// term::emit_to_fmt_write(&mut output, &Config::default(), &file, &diagnostic).unwrap();
+///
+/// assert_eq!(output, "warning: empty file\n\n");
+/// ```
+///
+/// See [`emit`] for the conditions under which this can fail.
+///
+/// [`fmt::Write`]: std::fmt::Write
+/// [`Error::Io`]: super::files::Error::Io
+pub fn emit_to_fmt_write<'files, F: Files<'files>>(
+    writer: &mut dyn fmt::Write,
+    config: &Config,
+    files: &'files F,
+    diagnostic: &Diagnostic<F::FileId>,
+) -> Result<(), super::files::Error> {
+    emit(&mut FmtWriter { writer }, config, files, diagnostic)
+}
+
+/// Emit a diagnostic using the given writer, flushing it after each line of
+/// output.
+///
+/// The text of each line is collected and written to the writer in one go
+/// before it is flushed, so that slow consumers like sockets see partial output
+/// promptly without a write for every piece of the line.
+///
+/// See [`emit`] for the conditions under which this can fail.
+pub fn emit_streaming<'files, F: Files<'files>>(
+    writer: &mut dyn WriteColor,
+    config: &Config,
+    files: &'files F,
+    diagnostic: &Diagnostic<F::FileId>,
+) -> Result<(), super::files::Error> {
+    let mut writer = LineFlushWriter {
+        writer,
+        line: Vec::new(),
+    };
+    emit(&mut writer, config, files, diagnostic)?;
+    std::io::Write::flush(&mut writer)?;
+    Ok(())
+}
+
+/// Emit just the source snippets and notes of a diagnostic, without its header.
+///
+/// This is useful when the header is shown elsewhere, for example in a tooltip
+/// or a list of diagnostics, and only the source context needs to be displayed.
+/// The snippets are always rendered in the [`DisplayStyle::Rich`] style,
+/// whatever the display style of the config.
+///
+/// ```rust
+/// use codespan_reporting::diagnostic::{Diagnostic, Label};
+/// use codespan_reporting::files::SimpleFile;
+/// use codespan_reporting::term::{self, Config};
+/// use codespan_reporting::term::termcolor::NoColor;
+///
+/// let file = SimpleFile::new("test", "(+ test \"\")\n");
+/// let diagnostic = Diagnostic::error()
+///     .with_message("unexpected type in `+` application")
+///     .with_labels(vec![Label::primary((), 8..10)]);
+///
+/// let mut writer = NoColor::new(Vec::new());
+/// term::emit_snippet_only(&mut writer, &Config::default(), &file, &diagnostic).unwrap();
+///
+/// let output = String::from_utf8(writer.into_inner()).unwrap();
+/// assert!(output.starts_with("  ┌─ test:1:9\n"));
+/// ```
+///
+/// See [`emit`] for the conditions under which this can fail.
+pub fn emit_snippet_only<'files, F: Files<'files>>(
+    writer: &mut dyn WriteColor,
+    config: &Config,
+    files: &'files F,
+    diagnostic: &Diagnostic<F::FileId>,
+) -> Result<(), super::files::Error> {
+    use self::renderer::Renderer;
+    use self::views::RichDiagnostic;
+
+    let files = &NamedErrors::new(files);
+    check_label_ranges(files, diagnostic)?;
+
+    let renderer = &mut Renderer::new(writer, config);
+    if config.number_labels {
+        let diagnostic = number_labels(diagnostic);
+        RichDiagnostic::new(&diagnostic, config)
+            .without_header()
+            .render(files, renderer)
+    } else {
+        RichDiagnostic::new(diagnostic, config)
+            .without_header()
+            .render(files, renderer)
+    }
+}
+
+/// Emit a batch of diagnostics like [`emit_all`], but always in the order they
+/// were given in, regardless of [`Config::sort_diagnostics`].
+///
+/// If [`Config::show_index`] is set, each diagnostic is prefixed with its
+/// 1-based position in the batch, for example `[3/17]`.
+///
+/// See [`emit`] for the conditions under which this can fail.
+///
+/// [`Config::sort_diagnostics`]: Config::sort_diagnostics
+/// [`Config::show_index`]: Config::show_index
+pub fn emit_many<'files, F: Files<'files>>(
+    writer: &mut dyn WriteColor,
+    config: &Config,
+    files: &'files F,
+    diagnostics: &[Diagnostic<F::FileId>],
+) -> Result<(), super::files::Error> {
+    let diagnostics = diagnostics.iter().collect::<Vec<_>>();
+    emit_in_order(writer, config, files, &diagnostics, 0)
+}
+
+/// Emit a batch of diagnostics using the given writer, context, config, and
+/// files, spacing them uniformly.
+///
+/// In the [`DisplayStyle::Rich`] and [`DisplayStyle::Linear`] styles, each
+/// diagnostic is separated from the next by a single empty line, regardless of
+/// whether it was rendered with a source snippet. In the other styles, each
+/// diagnostic starts on the line following the previous one.
+///
+/// If [`Config::sort_diagnostics`] is set, the diagnostics are emitted with
+/// the most severe first, and then in order of the start of their
+/// [primary label]. Diagnostics without a primary label are emitted after the
+/// others of the same severity.
+///
+/// If [`Config::dedup_trailing_notes`] is set, the last note of a diagnostic
+/// is left out when it is the same as the last note of the diagnostic emitted
+/// before it.
+///
+/// Emitting stops at the first error. See [`emit`] for the conditions under
+/// which this can fail.
+///
+/// [`DisplayStyle::Rich`]: DisplayStyle::Rich
+/// [`DisplayStyle::Linear`]: DisplayStyle::Linear
+/// [`Config::sort_diagnostics`]: Config::sort_diagnostics
+/// [`Config::dedup_trailing_notes`]: Config::dedup_trailing_notes
+/// [primary label]: Diagnostic::primary_label
+pub fn emit_all<'files, F: Files<'files>>(
+    writer: &mut dyn WriteColor,
+    config: &Config,
+    files: &'files F,
+    diagnostics: &[Diagnostic<F::FileId>],
+) -> Result<(), super::files::Error> {
+    let diagnostics = sort_diagnostics(config, diagnostics);
+    emit_in_order(writer, config, files, &diagnostics, 0)
+}
+
+/// Emit a batch of diagnostics like [`emit_all`], with the gutters of all the
+/// diagnostics made as wide as the widest one, so that the left borders of
+/// their source snippets line up across the whole output.
+///
+/// This only affects the [`DisplayStyle::Rich`] style, where the width of the
+/// gutter is worked out like [`gutter_width`].
+///
+/// ```rust
+/// use codespan_reporting::diagnostic::{Diagnostic, Label};
+/// use codespan_reporting::files::SimpleFile;
+/// use codespan_reporting::term::{self, termcolor::NoColor, Config};
+///
+/// let file = SimpleFile::new("test", "a\n".repeat(100));
+/// let diagnostics = [
+///     Diagnostic::error().with_labels(vec![Label::primary((), 0..1)]),
+///     Diagnostic::error().with_labels(vec![Label::primary((), 198..199)]),
+/// ];
+///
+/// let mut writer = NoColor::new(Vec::new());
+/// term::emit_all_aligned(&mut writer, &Config::default(), &file, &diagnostics).unwrap();
+///
+/// let output = String::from_utf8(writer.into_inner()).unwrap();
+/// assert!(output.contains("\n  1 │ a\n"));
+/// assert!(output.contains("\n100 │ a\n"));
+/// ```
+///
+/// Emitting stops at the first error. See [`emit`] for the conditions under
+/// which this can fail.
+///
+/// [`DisplayStyle::Rich`]: DisplayStyle::Rich
+pub fn emit_all_aligned<'files, F: Files<'files>>(
+    writer: &mut dyn WriteColor,
+    config: &Config,
+    files: &'files F,
+    diagnostics: &[Diagnostic<F::FileId>],
+) -> Result<(), super::files::Error> {
+    let mut outer_padding = 0;
+    for diagnostic in diagnostics {
+        outer_padding = std::cmp::max(outer_padding, gutter_width(config, files, diagnostic)?);
+    }
+
+    let diagnostics = sort_diagnostics(config, diagnostics);
+    emit_in_order(writer, config, files, &diagnostics, outer_padding)
+}
+
+/// Put a batch of diagnostics in the order they are emitted in by
+/// [`emit_all`], sorting them if [`Config::sort_diagnostics`] is set.
+///
+/// [`Config::sort_diagnostics`]: Config::sort_diagnostics
+fn sort_diagnostics<'diagnostics, FileId: PartialEq>(
+    config: &Config,
+    diagnostics: &'diagnostics [Diagnostic<FileId>],
+) -> Vec<&'diagnostics Diagnostic<FileId>> {
+    use std::cmp::Reverse;
+
+    let mut diagnostics = diagnostics.iter().collect::<Vec<_>>();
+    if config.sort_diagnostics {
+        diagnostics.sort_by_key(|diagnostic| {
+            let primary_start = diagnostic
+                .primary_label()
+                .map_or(usize::MAX, |label| label.range.start);
+
+            (Reverse(diagnostic.severity), primary_start)
+        });
+    }
+    diagnostics
+}
+
+/// Emit a batch of diagnostics like [`emit_all`], but in the order given by a
+/// comparison function instead of [`Config::sort_diagnostics`].
+///
+/// The sort is stable, so diagnostics that compare as equal are emitted in the
+/// order they were given in.
+///
+/// ```rust
+/// use codespan_reporting::diagnostic::Diagnostic;
+/// use codespan_reporting::files::SimpleFile;
+/// use codespan_reporting::term::{self, termcolor::NoColor, Config};
+///
+/// let file = SimpleFile::new("test", "");
+/// let diagnostics = [
+///     Diagnostic::error().with_message("b"),
+///     Diagnostic::warning().with_message("a"),
+/// ];
+///
+/// let mut writer = NoColor::new(Vec::new());
+/// let config = Config::default();
+/// term::emit_all_sorted(&mut writer, &config, &file, &diagnostics, |a, b| {
+///     a.message.cmp(&b.message)
+/// })
+/// .unwrap();
+///
+/// let output = String::from_utf8(writer.into_inner()).unwrap();
+/// assert_eq!(output, "warning: a\n\nerror: b\n\n");
+/// ```
+///
+/// See [`emit`] for the conditions under which this can fail.
+///
+/// [`Config::sort_diagnostics`]: Config::sort_diagnostics
+pub fn emit_all_sorted<'files, F: Files<'files>>(
+    writer: &mut dyn WriteColor,
+    config: &Config,
+    files: &'files F,
+    diagnostics: &[Diagnostic<F::FileId>],
+    mut cmp: impl FnMut(&Diagnostic<F::FileId>, &Diagnostic<F::FileId>) -> std::cmp::Ordering,
+) -> Result<(), super::files::Error> {
+    let mut diagnostics = diagnostics.iter().collect::<Vec<_>>();
+    diagnostics.sort_by(|a, b| cmp(a, b));
+
+    emit_in_order(writer, config, files, &diagnostics, 0)
+}
+
+/// Emit a batch of diagnostics in the given order, for [`emit_all`],
+/// [`emit_all_aligned`], and [`emit_all_sorted`].
+fn emit_in_order<'files, F: Files<'files>>(
+    writer: &mut dyn WriteColor,
+    config: &Config,
+    files: &'files F,
+    diagnostics: &[&Diagnostic<F::FileId>],
+    min_outer_padding: usize,
+) -> Result<(), super::files::Error> {
+    use self::renderer::Renderer;
+    use std::io::Write;
+
+    let separate = match config.display_style {
+        DisplayStyle::Rich | DisplayStyle::Linear => true,
+        DisplayStyle::Medium | DisplayStyle::Short => false,
+    };
+
+    let mut writer = SpacingWriter::new(writer);
+    let mut previous_note = None;
+    for (index, diagnostic) in diagnostics.iter().enumerate() {
+        if separate && index > 0 && !writer.ends_with_empty_line() {
+            writeln!(writer)?;
+        }
+        if config.show_index {
+            Renderer::new(&mut writer, config).render_index(index + 1, diagnostics.len())?;
+        }
+
+        let renderer = &mut Renderer::new(&mut writer, config);
+        let last_note = diagnostic.notes.last();
+        if config.dedup_trailing_notes && last_note.is_some() && last_note == previous_note {
+            let mut diagnostic = (*diagnostic).clone();
+            diagnostic.notes.pop();
+            render(renderer, config, files, &diagnostic, min_outer_padding)?;
+        } else {
+            render(renderer, config, files, diagnostic, min_outer_padding)?;
+        }
+        previous_note = last_note;
+    }
+
+    Ok(())
+}
+
+/// A writer that keeps track of the number of newlines at the end of the
+/// text written to it, for spacing out diagnostics in [`emit_all`].
+struct SpacingWriter<'writer> {
+    writer: &'writer mut dyn WriteColor,
+    trailing_newlines: usize,
+}
+
+impl<'writer> SpacingWriter<'writer> {
+    fn new(writer: &'writer mut dyn WriteColor) -> SpacingWriter<'writer> {
+        SpacingWriter {
+            writer,
+            trailing_newlines: 0,
+        }
+    }
+
+    /// Whether the last line written was empty.
+    fn ends_with_empty_line(&self) -> bool {
+        self.trailing_newlines >= 2
+    }
+}
+
+impl<'writer> std::io::Write for SpacingWriter<'writer> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let len = self.writer.write(buf)?;
+        let written = &buf[..len];
+        let newlines = written.iter().rev().take_while(|&&b| b == b'\n').count();
+        if newlines == written.len() {
+            self.trailing_newlines += newlines;
+        } else {
+            self.trailing_newlines = newlines;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<'writer> WriteColor for SpacingWriter<'writer> {
+    fn supports_color(&self) -> bool {
+        self.writer.supports_color()
+    }
+
+    fn set_color(&mut self, spec: &termcolor::ColorSpec) -> std::io::Result<()> {
+        self.writer.set_color(spec)
+    }
+
+    fn reset(&mut self) -> std::io::Result<()> {
+        self.writer.reset()
+    }
+
+    fn is_synchronous(&self) -> bool {
+        self.writer.is_synchronous()
+    }
+}
+
+/// A writer without color that forwards to a [`fmt::Write`], for
+/// [`emit_to_fmt_write`].
+struct FmtWriter<'writer> {
+    writer: &'writer mut dyn fmt::Write,
+}
+
+impl<'writer> std::io::Write for FmtWriter<'writer> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        use std::io::{Error, ErrorKind};
+
+        // The renderer only writes whole strings, so this should not fail
+        let text = std::str::from_utf8(buf).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        self.writer
+            .write_str(text)
+            .map_err(|_| Error::from(ErrorKind::Other))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'writer> WriteColor for FmtWriter<'writer> {
+    fn supports_color(&self) -> bool {
+        false
+    }
+
+    fn set_color(&mut self, _: &termcolor::ColorSpec) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn reset(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A writer that collects the text of each line, writing and flushing it once
+/// the line is complete, for [`emit_streaming`].
+struct LineFlushWriter<'writer> {
+    writer: &'writer mut dyn WriteColor,
+    line: Vec<u8>,
+}
+
+impl<'writer> LineFlushWriter<'writer> {
+    /// Write the text collected so far, without flushing.
+    fn write_line(&mut self) -> std::io::Result<()> {
+        self.writer.write_all(&self.line)?;
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl<'writer> std::io::Write for LineFlushWriter<'writer> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match buf.iter().rposition(|&b| b == b'\n') {
+            Some(line_end) => {
+                self.line.extend_from_slice(&buf[..=line_end]);
+                self.flush()?;
+                self.line.extend_from_slice(&buf[line_end + 1..]);
+            }
+            None => self.line.extend_from_slice(buf),
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.write_line()?;
+        self.writer.flush()
+    }
+}
+
+impl<'writer> WriteColor for LineFlushWriter<'writer> {
+    fn supports_color(&self) -> bool {
+        self.writer.supports_color()
+    }
+
+    fn set_color(&mut self, spec: &termcolor::ColorSpec) -> std::io::Result<()> {
+        // Keep the text in order with the colors
+        self.write_line()?;
+        self.writer.set_color(spec)
+    }
+
+    fn reset(&mut self) -> std::io::Result<()> {
+        self.write_line()?;
+        self.writer.reset()
+    }
+
+    fn is_synchronous(&self) -> bool {
+        self.writer.is_synchronous()
+    }
+}
+
+/// A writer that counts the changes to the color of the output, forwarding
+/// everything to another writer.
+///
+/// Each change of color is written as an escape sequence on most terminals,
+/// so this is useful for measuring the cost of rendering diagnostics:
+///
+/// ```rust
+/// use codespan_reporting::diagnostic::{Diagnostic, Label};
+/// use codespan_reporting::files::SimpleFile;
+/// use codespan_reporting::term::termcolor::Ansi;
+/// use codespan_reporting::term::{self, ColorChangeCounter, Config};
+///
+/// let file = SimpleFile::new("test", "(+ test \"\")");
+/// let diagnostic = Diagnostic::error()
+///     .with_message("unexpected type in `+` application")
+///     .with_labels(vec![Label::primary((), 8..10)]);
+///
+/// let mut writer = ColorChangeCounter::new(Ansi::new(Vec::new()));
+/// term::emit(&mut writer, &Config::default(), &file, &diagnostic).unwrap();
+///
+/// assert!(writer.set_color_count() > 0);
+/// assert!(writer.reset_count() > 0);
+/// ```
+#[derive(Debug)]
+pub struct ColorChangeCounter<W> {
+    writer: W,
+    set_color_count: usize,
+    reset_count: usize,
+}
+
+impl<W> ColorChangeCounter<W> {
+    /// Wrap a writer, starting the counts at zero.
+    pub fn new(writer: W) -> ColorChangeCounter<W> {
+        ColorChangeCounter {
+            writer,
+            set_color_count: 0,
+            reset_count: 0,
+        }
+    }
+
+    /// The number of calls to [`WriteColor::set_color`] so far.
+    pub fn set_color_count(&self) -> usize {
+        self.set_color_count
+    }
+
+    /// The number of calls to [`WriteColor::reset`] so far.
+    pub fn reset_count(&self) -> usize {
+        self.reset_count
+    }
+
+    /// Get a reference to the wrapped writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Return the wrapped writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: std::io::Write> std::io::Write for ColorChangeCounter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        self.writer.write_all(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: WriteColor> WriteColor for ColorChangeCounter<W> {
+    fn supports_color(&self) -> bool {
+        self.writer.supports_color()
+    }
+
+    fn set_color(&mut self, spec: &termcolor::ColorSpec) -> std::io::Result<()> {
+        self.set_color_count += 1;
+        self.writer.set_color(spec)
+    }
+
+    fn reset(&mut self) -> std::io::Result<()> {
+        self.reset_count += 1;
+        self.writer.reset()
+    }
+
+    fn is_synchronous(&self) -> bool {
+        self.writer.is_synchronous()
+    }
+}
+
+/// Emits diagnostics one at a time as they become available.
+///
+/// This bundles a writer, config, and files together so that diagnostics can
+/// be rendered as they arrive, without first collecting them into a `Vec`.
+/// The sink is synchronous, but can be driven from any loop, including one
+/// that awaits diagnostics from an asynchronous channel:
+///
+/// ```rust,ignore
+/// let mut sink = DiagnosticSink::new(writer.lock(), &config, &files);
+/// while let Some(diagnostic) = rx.recv().await {
+///     sink.push(&diagnostic)?;
+/// }
+/// ```
+pub struct DiagnosticSink<'files, 'config, W, F> {
+    writer: W,
+    config: &'config Config,
+    files: &'files F,
+}
+
+impl<'files, 'config, W, F> DiagnosticSink<'files, 'config, W, F>
+where
+    W: WriteColor,
+    F: Files<'files>,
+{
+    /// Create a new sink that emits to the given writer.
+    pub fn new(writer: W, config: &'config Config, files: &'files F) -> Self {
+        DiagnosticSink {
+            writer,
+            config,
+            files,
+        }
+    }
+
+    /// Emit a single diagnostic to the underlying writer.
+    ///
+    /// See [`emit`] for the conditions under which this can fail.
+    ///
+    /// [`emit`]: crate::term::emit
+    pub fn push(&mut self, diagnostic: &Diagnostic<F::FileId>) -> Result<(), super::files::Error> {
+        emit(&mut self.writer, self.config, self.files, diagnostic)
+    }
+
+    /// Consume the sink, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
     }
 }
 
@@ -106,7 +921,18 @@ mod tests {
     use super::*;
 
     use crate::diagnostic::Label;
-    use crate::files::SimpleFiles;
+    use crate::files::{SimpleFile, SimpleFiles};
+
+    #[test]
+    fn color_arg_round_trip() {
+        for variant in ColorArg::VARIANTS {
+            let arg = ColorArg::from_str(variant).unwrap();
+
+            assert_eq!(arg.to_string(), *variant);
+            assert_eq!(arg.as_ref(), *variant);
+            assert_eq!(arg.to_string().parse::<ColorArg>(), Ok(arg));
+        }
+    }
 
     #[test]
     fn unsized_emit() {
@@ -118,4 +944,570 @@ mod tests {
 
         emit(&mut writer, &Config::default(), &files, &diagnostic).unwrap();
     }
+
+    #[test]
+    fn out_of_bounds_label() {
+        let mut files = SimpleFiles::new();
+
+        let id = files.add("test", "(+ test \"\")\n");
+        let diagnostic = Diagnostic::error().with_labels(vec![Label::primary(id, 9999..10000)]);
+
+        let mut writer = termcolor::NoColor::new(Vec::<u8>::new());
+        let result = emit(&mut writer, &Config::default(), &files, &diagnostic);
+
+        assert!(matches!(
+            result,
+            Err(Error::InFile { ref name, ref error }) if name == "test"
+                && matches!(**error, Error::IndexTooLarge { given: 10000, max: 12 }),
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn json() {
+        let mut files = SimpleFiles::new();
+
+        let id = files.add("test", "(let test \"\")\n(+ test \"\")\n");
+        let diagnostics = vec![
+            Diagnostic::error()
+                .with_code("E0001")
+                .with_message("unexpected type in `+` application")
+                .with_labels(vec![
+                    Label::primary(id, 22..24).with_message("expected `Int` but found `String`"),
+                    Label::secondary(id, 14..14),
+                ])
+                .with_notes(vec![
+                    "expected type `Int`\n   found type `\"String\"`".to_owned()
+                ]),
+            Diagnostic::warning().with_code("").with_source("lint"),
+        ];
+
+        let mut writer = Vec::<u8>::new();
+        for diagnostic in &diagnostics {
+            emit_json(&mut writer, &files, diagnostic).unwrap();
+        }
+
+        assert_eq!(
+            String::from_utf8(writer).unwrap(),
+            concat!(
+                r#"{"code":"E0001","labels":["#,
+                r#"{"end":{"column":11,"line":2},"file":"test","message":"expected `Int` but found `String`","priority":0,"start":{"column":9,"line":2},"style":"primary"},"#,
+                r#"{"end":{"column":1,"line":2},"file":"test","message":"","priority":0,"start":{"column":1,"line":2},"style":"secondary"}"#,
+                r#"],"message":"unexpected type in `+` application","#,
+                r#""notes":["expected type `Int`\n   found type `\"String\"`"],"rich_notes":[],"severity":"error","suggestions":[]}"#,
+                "\n",
+                r#"{"labels":[],"message":"","notes":[],"rich_notes":[],"severity":"warning","source":"lint","suggestions":[]}"#,
+                "\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn sink_push() {
+        let mut files = SimpleFiles::new();
+
+        let id = files.add("test", "let x = 1;\nlet y = x;\n");
+        let diagnostics = vec![
+            Diagnostic::error()
+                .with_message("first")
+                .with_labels(vec![Label::primary(id, 4..5)]),
+            Diagnostic::warning()
+                .with_message("second")
+                .with_labels(vec![Label::primary(id, 15..16)]),
+            Diagnostic::note().with_message("third"),
+        ];
+
+        let config = Config {
+            display_style: DisplayStyle::Short,
+            ..Config::default()
+        };
+        let mut sink =
+            DiagnosticSink::new(termcolor::NoColor::new(Vec::<u8>::new()), &config, &files);
+        for diagnostic in &diagnostics {
+            sink.push(diagnostic).unwrap();
+        }
+
+        let output = String::from_utf8(sink.into_inner().into_inner()).unwrap();
+        assert_eq!(
+            output,
+            "test:1:5: error: first\ntest:2:5: warning: second\nnote: third\n",
+        );
+    }
+
+    #[test]
+    fn emit_all_sort_diagnostics() {
+        let mut files = SimpleFiles::new();
+
+        let id = files.add("test", "let x = 1;\nlet y = x;\n");
+        let diagnostics = vec![
+            Diagnostic::warning()
+                .with_message("first")
+                .with_labels(vec![Label::primary(id, 4..5)]),
+            Diagnostic::error().with_message("second"),
+            Diagnostic::error()
+                .with_message("third")
+                .with_labels(vec![Label::secondary(id, 0..3), Label::primary(id, 15..16)]),
+            Diagnostic::error()
+                .with_message("fourth")
+                .with_labels(vec![Label::primary(id, 8..9)]),
+            Diagnostic::error()
+                .with_message("fifth")
+                .with_labels(vec![Label::primary(id, 11..14), Label::primary(id, 0..3)]),
+        ];
+
+        let config = Config {
+            display_style: DisplayStyle::Short,
+            sort_diagnostics: true,
+            ..Config::default()
+        };
+        let mut writer = termcolor::NoColor::new(Vec::<u8>::new());
+        emit_all(&mut writer, &config, &files, &diagnostics).unwrap();
+
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(
+            output,
+            concat!(
+                "test:1:1: error: fifth\n",
+                "test:2:1: error: fifth\n",
+                "test:1:9: error: fourth\n",
+                "test:2:5: error: third\n",
+                "error: second\n",
+                "test:1:5: warning: first\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn emit_all_sorted_is_stable() {
+        let file = SimpleFile::new("test", "");
+        let diagnostics = vec![
+            Diagnostic::warning().with_message("first"),
+            Diagnostic::error().with_message("second"),
+            Diagnostic::warning().with_message("third"),
+            Diagnostic::error().with_message("fourth"),
+        ];
+
+        let config = Config {
+            display_style: DisplayStyle::Short,
+            ..Config::default()
+        };
+        let mut writer = termcolor::NoColor::new(Vec::<u8>::new());
+        emit_all_sorted(&mut writer, &config, &file, &diagnostics, |a, b| {
+            b.severity.cmp(&a.severity)
+        })
+        .unwrap();
+
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(
+            output,
+            concat!(
+                "error: second\n",
+                "error: fourth\n",
+                "warning: first\n",
+                "warning: third\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn emit_all_spacing() {
+        let mut files = SimpleFiles::new();
+
+        let id = files.add("test", "let x = 1;\nlet y = x;\n");
+        let diagnostics = vec![
+            Diagnostic::error()
+                .with_message("first")
+                .with_labels(vec![Label::primary(id, 4..5)]),
+            Diagnostic::warning()
+                .with_message("second")
+                .with_labels(vec![Label::primary(id, 15..16)])
+                .with_notes(vec!["note".to_owned()]),
+            Diagnostic::note().with_message("third"),
+            Diagnostic::note().with_message("fourth"),
+        ];
+
+        let config = Config {
+            display_style: DisplayStyle::Linear,
+            ..Config::default()
+        };
+        let mut writer = termcolor::NoColor::new(Vec::<u8>::new());
+        emit_all(&mut writer, &config, &files, &diagnostics).unwrap();
+
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(
+            output,
+            concat!(
+                "error: first\n",
+                "test:1:5 │ let x = 1; │ ^\n",
+                "\n",
+                "warning: second\n",
+                "  ┌─ test:2:5\n",
+                "  │\n",
+                "2 │ let y = x;\n",
+                "  │     ^\n",
+                "  │\n",
+                "  = note\n",
+                "\n",
+                "note: third\n",
+                "\n",
+                "note: fourth\n",
+                "\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn emit_all_dedup_trailing_notes() {
+        let mut files = SimpleFiles::new();
+
+        let id = files.add("test", "let x = 1;\nlet y = x;\n");
+        let explain = "try `--explain E0001`".to_owned();
+        let diagnostics = vec![
+            Diagnostic::error()
+                .with_message("first")
+                .with_labels(vec![Label::primary(id, 4..5)])
+                .with_notes(vec![explain.clone()]),
+            Diagnostic::error()
+                .with_message("second")
+                .with_labels(vec![Label::primary(id, 15..16)])
+                .with_notes(vec!["other".to_owned(), explain.clone()]),
+            Diagnostic::error()
+                .with_message("third")
+                .with_notes(vec![explain.clone()]),
+            Diagnostic::error().with_message("fourth"),
+            Diagnostic::error()
+                .with_message("fifth")
+                .with_notes(vec![explain]),
+        ];
+
+        let config = Config {
+            display_style: DisplayStyle::Medium,
+            dedup_trailing_notes: true,
+            ..Config::default()
+        };
+        let mut writer = termcolor::NoColor::new(Vec::<u8>::new());
+        emit_all(&mut writer, &config, &files, &diagnostics).unwrap();
+
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(
+            output,
+            concat!(
+                "test:1:5: error: first\n",
+                " = try `--explain E0001`\n",
+                "test:2:5: error: second\n",
+                " = other\n",
+                "error: third\n",
+                "error: fourth\n",
+                "error: fifth\n",
+                " = try `--explain E0001`\n",
+            ),
+        );
+    }
+
+    #[test]
+    fn emit_to_fmt_write_matches_emit() {
+        let mut files = SimpleFiles::new();
+
+        let id = files.add("test", "let 🦀 = 1;\n");
+        let diagnostic = Diagnostic::error()
+            .with_message("crab")
+            .with_labels(vec![Label::primary(id, 4..8).with_message("here")])
+            .with_notes(vec!["crabs are not identifiers".to_owned()]);
+
+        let config = Config::default();
+        let mut output = String::new();
+        emit_to_fmt_write(&mut output, &config, &files, &diagnostic).unwrap();
+
+        let mut writer = termcolor::NoColor::new(Vec::<u8>::new());
+        emit(&mut writer, &config, &files, &diagnostic).unwrap();
+
+        assert_eq!(output, String::from_utf8(writer.into_inner()).unwrap());
+    }
+
+    #[test]
+    fn emit_streaming_flushes_lines() {
+        /// Records the text written before each flush.
+        #[derive(Default)]
+        struct FlushRecorder {
+            pending: Vec<u8>,
+            flushed: Vec<String>,
+        }
+
+        impl std::io::Write for FlushRecorder {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.pending.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                let pending = std::mem::take(&mut self.pending);
+                self.flushed.push(String::from_utf8(pending).unwrap());
+                Ok(())
+            }
+        }
+
+        let mut files = SimpleFiles::new();
+
+        let id = files.add("test", "(+ test \"\")\n");
+        let diagnostic = Diagnostic::error()
+            .with_message("unexpected type in `+` application")
+            .with_labels(vec![
+                Label::primary(id, 8..10).with_message("expected `Int`")
+            ]);
+
+        let config = Config::default();
+        let mut writer = termcolor::NoColor::new(FlushRecorder::default());
+        emit_streaming(&mut writer, &config, &files, &diagnostic).unwrap();
+        let recorder = writer.into_inner();
+
+        let mut expected = termcolor::NoColor::new(Vec::<u8>::new());
+        emit(&mut expected, &config, &files, &diagnostic).unwrap();
+        let expected = String::from_utf8(expected.into_inner()).unwrap();
+
+        assert!(recorder.pending.is_empty());
+        assert_eq!(recorder.flushed.concat(), expected);
+        let lines = expected
+            .lines()
+            .map(|line| format!("{}\n", line))
+            .collect::<Vec<_>>();
+        assert_eq!(recorder.flushed[..lines.len()], lines[..]);
+    }
+
+    #[test]
+    fn emit_to_fixed_capacity_fmt_write() {
+        /// A writer backed by a fixed-size array, like those used without an
+        /// allocator.
+        struct ArrayWriter {
+            buf: [u8; 128],
+            len: usize,
+        }
+
+        impl fmt::Write for ArrayWriter {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                let end = self.len + s.len();
+                let dest = self.buf.get_mut(self.len..end).ok_or(fmt::Error)?;
+                dest.copy_from_slice(s.as_bytes());
+                self.len = end;
+                Ok(())
+            }
+        }
+
+        let mut files = SimpleFiles::new();
+
+        let id = files.add("test", "(+ test \"\")\n");
+        let diagnostic = Diagnostic::error()
+            .with_message("unexpected type")
+            .with_labels(vec![Label::primary(id, 8..10)]);
+
+        let mut writer = ArrayWriter {
+            buf: [0; 128],
+            len: 0,
+        };
+        emit_to_fmt_write(&mut writer, &Config::default(), &files, &diagnostic).unwrap();
+
+        let mut expected = String::new();
+        emit_to_fmt_write(&mut expected, &Config::default(), &files, &diagnostic).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&writer.buf[..writer.len]),
+            Ok(&*expected)
+        );
+
+        let diagnostic = diagnostic.with_message("x".repeat(128));
+        let mut writer = ArrayWriter {
+            buf: [0; 128],
+            len: 0,
+        };
+        let result = emit_to_fmt_write(&mut writer, &Config::default(), &files, &diagnostic);
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn emit_all_aligned_borders() {
+        let mut files = SimpleFiles::new();
+
+        let id = files.add("test", "let x = 1;\n".repeat(2000));
+        let diagnostics = vec![
+            Diagnostic::warning()
+                .with_message("unused variable")
+                .with_labels(vec![Label::primary(id, 4..5)])
+                .with_notes(vec!["prefix it with `_`".to_owned()]),
+            Diagnostic::error()
+                .with_message("mismatched types")
+                .with_labels(vec![Label::primary(id, 16_508..16_509)]),
+        ];
+
+        let mut writer = termcolor::NoColor::new(Vec::<u8>::new());
+        emit_all_aligned(&mut writer, &Config::default(), &files, &diagnostics).unwrap();
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert!(output.contains("\n   1 │ let x = 1;\n"));
+        assert!(output.contains("\n1501 │ let x = 1;\n"));
+        let border_columns = output
+            .lines()
+            .filter_map(|line| line.find(&['│', '┌', '='][..]))
+            .collect::<Vec<_>>();
+        assert_eq!(border_columns.len(), 10);
+        assert!(border_columns.iter().all(|column| *column == 5));
+    }
+
+    #[test]
+    fn emit_snippet_only_skips_header() {
+        let mut files = SimpleFiles::new();
+
+        let id = files.add("test", "(+ test \"\")\n");
+        let diagnostic = Diagnostic::error()
+            .with_message("unexpected type in `+` application")
+            .with_labels(vec![
+                Label::primary(id, 8..10).with_message("expected `Int`")
+            ])
+            .with_notes(vec!["expected type `Int`".to_owned()]);
+
+        let mut expected = String::new();
+        emit_to_fmt_write(&mut expected, &Config::default(), &files, &diagnostic).unwrap();
+        let expected = &expected[expected.find('\n').unwrap() + 1..];
+
+        // The snippets are rendered the same way whatever the display style
+        for display_style in &[DisplayStyle::Rich, DisplayStyle::Short] {
+            let config = Config {
+                display_style: display_style.clone(),
+                ..Config::default()
+            };
+            let mut writer = termcolor::NoColor::new(Vec::<u8>::new());
+            emit_snippet_only(&mut writer, &config, &files, &diagnostic).unwrap();
+
+            assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn color_change_counter() {
+        let mut writer = ColorChangeCounter::new(termcolor::Ansi::new(Vec::<u8>::new()));
+        writer.set_color(&termcolor::ColorSpec::new()).unwrap();
+        std::io::Write::write_all(&mut writer, b"text").unwrap();
+        writer.reset().unwrap();
+        writer.reset().unwrap();
+
+        assert_eq!(writer.set_color_count(), 1);
+        assert_eq!(writer.reset_count(), 2);
+        assert!(writer.supports_color());
+        assert_eq!(writer.get_ref().get_ref(), b"\x1b[0mtext\x1b[0m\x1b[0m");
+    }
+
+    /// A file named by its path, with the last component of the path as its
+    /// short name.
+    struct PathFile(SimpleFile<&'static str, &'static str>);
+
+    impl PathFile {
+        fn new() -> PathFile {
+            PathFile(SimpleFile::new("/src/lib.rs", "fn main() {}\n"))
+        }
+    }
+
+    impl<'a> Files<'a> for PathFile {
+        type FileId = ();
+        type Name = &'static str;
+        type Source = &'a str;
+
+        fn name(&self, (): ()) -> Result<&'static str, Error> {
+            Files::name(&self.0, ())
+        }
+
+        fn short_name(&self, (): ()) -> Result<&'static str, Error> {
+            let name = Files::name(&self.0, ())?;
+            Ok(name.rsplit('/').next().unwrap_or(name))
+        }
+
+        fn source(&self, (): ()) -> Result<&str, Error> {
+            Files::source(&self.0, ())
+        }
+
+        fn line_index(&self, (): (), byte_index: usize) -> Result<usize, Error> {
+            self.0.line_index((), byte_index)
+        }
+
+        fn line_range(&self, (): (), line_index: usize) -> Result<std::ops::Range<usize>, Error> {
+            self.0.line_range((), line_index)
+        }
+    }
+
+    #[test]
+    fn compact_locus() {
+        let file = PathFile::new();
+        let diagnostic = Diagnostic::warning()
+            .with_message("empty function")
+            .with_labels(vec![Label::primary((), 10..12)]);
+
+        let emit_short = |compact_locus| {
+            let config = Config {
+                display_style: DisplayStyle::Short,
+                compact_locus,
+                ..Config::default()
+            };
+            let mut writer = termcolor::NoColor::new(Vec::<u8>::new());
+            emit(&mut writer, &config, &file, &diagnostic).unwrap();
+            String::from_utf8(writer.into_inner()).unwrap()
+        };
+
+        assert_eq!(
+            emit_short(false),
+            "/src/lib.rs:1:11: warning: empty function\n"
+        );
+        assert_eq!(emit_short(true), "lib.rs:1:11: warning: empty function\n");
+    }
+
+    #[test]
+    fn locus_link() {
+        let file = PathFile::new();
+        let diagnostic = Diagnostic::warning()
+            .with_message("empty function")
+            .with_labels(vec![Label::primary((), 10..12)]);
+
+        let config = Config {
+            display_style: DisplayStyle::Short,
+            compact_locus: true,
+            locus_link: Some(|name| format!("file://{}", name)),
+            ..Config::default()
+        };
+
+        // The short name links to the full path
+        let mut writer = termcolor::Ansi::new(Vec::<u8>::new());
+        emit(&mut writer, &config, &file, &diagnostic).unwrap();
+        assert!(String::from_utf8(writer.into_inner())
+            .unwrap()
+            .starts_with("\x1b]8;;file:///src/lib.rs\x1b\\lib.rs\x1b]8;;\x1b\\:1:11: "));
+
+        // No link is written without color
+        let mut writer = termcolor::NoColor::new(Vec::<u8>::new());
+        emit(&mut writer, &config, &file, &diagnostic).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "lib.rs:1:11: warning: empty function\n",
+        );
+    }
+
+    #[test]
+    fn gutter_width_matches_emit() {
+        let mut files = SimpleFiles::new();
+
+        let source = (1..=12)
+            .map(|i| format!("line {}\n", i))
+            .collect::<String>();
+        let id = files.add("test", source);
+        // A multi-line label over lines 7 to 9, whose context reaches line 10
+        let diagnostic = Diagnostic::error()
+            .with_message("lines")
+            .with_labels(vec![Label::primary(id, 42..62)]);
+
+        let config = Config {
+            end_context_lines: 0,
+            after_label_lines: 1,
+            ..Config::default()
+        };
+        let mut writer = termcolor::NoColor::new(Vec::<u8>::new());
+        emit(&mut writer, &config, &files, &diagnostic).unwrap();
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert_eq!(gutter_width(&config, &files, &diagnostic).unwrap(), 2);
+        assert!(output.contains("\n10 │"), "{}", output);
+    }
 }
@@ -19,9 +19,15 @@ pub struct Config {
     pub chars: Chars,
     /// The minimum number of lines to be shown after the line on which a multiline [`Label`] begins.
     ///
+    /// Together with [`Config::end_context_lines`], this limits how much of a
+    /// long multiline label is shown. The lines in between that are not needed
+    /// for other labels are left out, and replaced with a break in the border
+    /// that keeps the lines of the label in the gutter continuous.
+    ///
     /// Defaults to: `3`.
     ///
     /// [`Label`]: crate::diagnostic::Label
+    /// [`Config::end_context_lines`]: Config::end_context_lines
     pub start_context_lines: usize,
     /// The minimum number of lines to be shown before the line on which a multiline [`Label`] ends.
     ///
@@ -29,6 +35,20 @@ pub struct Config {
     ///
     /// [`Label`]: crate::diagnostic::Label
     pub end_context_lines: usize,
+    /// The number of lines to show at each end of a multiline [`Label`],
+    /// overriding both [`Config::start_context_lines`] and
+    /// [`Config::end_context_lines`] when set.
+    ///
+    /// With `Some(n)`, the first `n` lines after the line on which a multiline
+    /// label begins and the last `n` lines before the line on which it ends are
+    /// shown, and the lines in between are replaced with a break.
+    ///
+    /// Defaults to: `None`.
+    ///
+    /// [`Label`]: crate::diagnostic::Label
+    /// [`Config::start_context_lines`]: Config::start_context_lines
+    /// [`Config::end_context_lines`]: Config::end_context_lines
+    pub multi_line_context: Option<usize>,
     /// The minimum number of lines before a label that should be included for context.
     ///
     /// Defaults to: `0`.
@@ -37,6 +57,256 @@ pub struct Config {
     ///
     /// Defaults to: `0`.
     pub after_label_lines: usize,
+    /// The order in which the messages of single-line labels that start at
+    /// the same position are rendered underneath the carets.
+    ///
+    /// Defaults to: [`CaretRowOrder::AsSpecified`].
+    ///
+    /// [`CaretRowOrder::AsSpecified`]: CaretRowOrder::AsSpecified
+    pub caret_row_order: CaretRowOrder,
+    /// The order in which the source snippets of files referenced by the
+    /// labels of a diagnostic are rendered.
+    ///
+    /// Defaults to: [`FileSortOrder::FirstAppearance`].
+    ///
+    /// [`FileSortOrder::FirstAppearance`]: FileSortOrder::FirstAppearance
+    pub file_sort: FileSortOrder,
+    /// A character used to mark the column where the source code begins on
+    /// each line of source in a snippet.
+    ///
+    /// The marker takes the place of the last space of `source_left_pad`,
+    /// which separates the source from the gutter, so it should occupy a single
+    /// column. It is not rendered if `source_left_pad` is `0`.
+    ///
+    /// Defaults to: `None`.
+    pub source_start_marker: Option<char>,
+    /// The names to use for each severity when rendering diagnostic headers.
+    pub severity_names: SeverityNames,
+    /// An offset to add to the line numbers shown in the gutter of source
+    /// snippets. This does not affect the line numbers shown in the locus.
+    ///
+    /// Defaults to: `0`.
+    pub line_number_offset: isize,
+    /// The width of the terminal, in columns. If set, lines of source code
+    /// that are too long to fit are truncated to a window around their
+    /// labels, with an ellipsis marking the truncated ends.
+    ///
+    /// Lines containing the start or end of a multi-line label are not
+    /// truncated.
+    ///
+    /// Defaults to: `None`.
+    pub terminal_width: Option<usize>,
+    /// Whether to prefix the messages of labels with the severity of their
+    /// diagnostic, for example `warning: unused variable`.
+    ///
+    /// Defaults to: `false`.
+    pub inline_label_severity: bool,
+    /// Whether to leave out the severity and code from diagnostic headers,
+    /// rendering only the message without any styling. This is useful for emitting plain
+    /// informational output, like `Compiling module Foo`.
+    ///
+    /// Defaults to: `false`.
+    pub suppress_severity_word: bool,
+    /// The number of spaces between the left border and the source code in
+    /// source snippets.
+    ///
+    /// Defaults to: `1`.
+    pub source_left_pad: usize,
+    /// Whether to prefix each diagnostic emitted with [`emit_many`] with its
+    /// position in the batch, for example `[3/17]`.
+    ///
+    /// Defaults to: `false`.
+    ///
+    /// [`emit_many`]: crate::term::emit_many
+    pub show_index: bool,
+    /// How secondary labels are emphasized relative to primary labels.
+    ///
+    /// Defaults to: [`SecondaryEmphasis::Equal`].
+    ///
+    /// [`SecondaryEmphasis::Equal`]: SecondaryEmphasis::Equal
+    pub secondary_emphasis: SecondaryEmphasis,
+    /// Whether to sort diagnostics emitted with [`emit_all`], so that the
+    /// most severe come first, followed by those of lower severity. Diagnostics
+    /// of the same severity are ordered by the start of their [primary label].
+    ///
+    /// Defaults to: `false`.
+    ///
+    /// [`emit_all`]: crate::term::emit_all
+    /// [primary label]: crate::diagnostic::Diagnostic::primary_label
+    pub sort_diagnostics: bool,
+    /// Whether the empty row separating the final source snippet from the
+    /// notes of a diagnostic is drawn with the left border. If this is
+    /// `false`, a blank line is used instead.
+    ///
+    /// Defaults to: `true`.
+    pub border_before_notes: bool,
+    /// Whether to show the code of a diagnostic in its header, for example
+    /// `error[E0308]`. The code is still available on the [`Diagnostic`] for
+    /// programmatic use.
+    ///
+    /// Defaults to: `true`.
+    ///
+    /// [`Diagnostic`]: crate::diagnostic::Diagnostic
+    pub show_code: bool,
+    /// Whether to show the locus at the start of each source snippet, for
+    /// example `┌─ test:2:9`. If this is `false`, snippets start directly at
+    /// their first line of source, and the snippets of different files are
+    /// separated by a source break.
+    ///
+    /// Defaults to: `true`.
+    pub show_locus: bool,
+    /// The maximum number of carets to use when underlining a single-line
+    /// label. Longer runs of carets are truncated with an ellipsis, and the
+    /// length of the label is noted in its message, for example
+    /// `^^^^… (10000 chars)`.
+    ///
+    /// Defaults to: `None`.
+    pub max_caret_len: Option<usize>,
+    /// Whether the [`DisplayStyle::Medium`] display style should render each
+    /// diagnostic as its located header, followed by a line with the first
+    /// line of its first note, in place of all of its notes.
+    ///
+    /// This keeps the output to at most two lines per diagnostic, which is
+    /// convenient for searching through logs.
+    ///
+    /// ```text
+    /// test:2:9: error[E0001]: unexpected type in `+` application
+    ///  = expected type `Int`
+    /// ```
+    ///
+    /// Defaults to: `false`.
+    pub medium_includes_first_note: bool,
+    /// The number of spaces between the line numbers and the left border of
+    /// source snippets.
+    ///
+    /// Defaults to: `1`.
+    pub gutter_padding: usize,
+    /// Whether to draw the left border of source snippets, along with the
+    /// corner before the locus. If this is `false`, the border is left out,
+    /// and the source and carets are shifted to the left to stay aligned.
+    ///
+    /// Defaults to: `true`.
+    pub draw_border: bool,
+    /// Whether diagnostics emitted with [`emit_all`] should leave out their
+    /// last note if it is the same as the last note of the previous
+    /// diagnostic, for example a repeated `For more information, try
+    /// --explain E0499` note.
+    ///
+    /// Defaults to: `false`.
+    ///
+    /// [`emit_all`]: crate::term::emit_all
+    pub dedup_trailing_notes: bool,
+    /// Whether to draw a guide leading up to the carets of single-line labels
+    /// that start after [`Config::column_guide_threshold`], making it easier
+    /// to see which column they point at.
+    ///
+    /// Defaults to: `false`.
+    ///
+    /// [`Config::column_guide_threshold`]: Config::column_guide_threshold
+    pub show_column_guide: bool,
+    /// The column after which single-line labels are given a guide, if
+    /// [`Config::show_column_guide`] is set. Columns are counted in the same
+    /// way as the carets, expanding tabs and wide characters.
+    ///
+    /// Defaults to: `40`.
+    ///
+    /// [`Config::show_column_guide`]: Config::show_column_guide
+    pub column_guide_threshold: usize,
+    /// A function that maps the code of a diagnostic to the URL of its
+    /// documentation. If set, the code in the header of a diagnostic is made
+    /// into a hyperlink to that URL, using the OSC 8 escape sequence supported
+    /// by many terminals. Links are only written if the writer supports color.
+    ///
+    /// Defaults to: `None`.
+    pub code_link: Option<fn(&str) -> String>,
+    /// A function that maps the full [`Files::name`] of a file to a URL, like
+    /// a `file://` URL of its absolute path. If set, the name of the file in
+    /// each locus is made into a hyperlink to that URL, using the OSC 8 escape
+    /// sequence supported by many terminals.
+    ///
+    /// The link is made from the full name even if [`Config::compact_locus`]
+    /// is set, so that a short name can be shown while the link still resolves
+    /// to the file. Links are only written if the writer supports color, so
+    /// that the escape sequences do not end up in plain text output.
+    ///
+    /// Defaults to: `None`.
+    ///
+    /// [`Files::name`]: crate::files::Files::name
+    /// [`Config::compact_locus`]: Config::compact_locus
+    pub locus_link: Option<fn(&str) -> String>,
+    /// Whether to show the byte offset of the location after the line and
+    /// column numbers of each locus, for example `test:2:9 [offset 37]`.
+    ///
+    /// Defaults to: `false`.
+    pub show_byte_offset: bool,
+    /// Whether to number the labels of diagnostics in the [`DisplayStyle::Rich`]
+    /// display style, prefixing the message of each label with its 1-based
+    /// position in [`Diagnostic::labels`], like `[2]`. Messages can refer to
+    /// labels by these numbers, for example `see [2]`.
+    ///
+    /// Defaults to: `false`.
+    ///
+    /// [`DisplayStyle::Rich`]: DisplayStyle::Rich
+    /// [`Diagnostic::labels`]: crate::diagnostic::Diagnostic::labels
+    pub number_labels: bool,
+    /// Whether to make spaces and tabs visible in the source where they are
+    /// covered by a label, using [`Chars::whitespace_space`] and
+    /// [`Chars::whitespace_tab`], so that it is clear what is being pointed at.
+    /// Tabs still take up the columns given by [`Config::tab_width`].
+    ///
+    /// Defaults to: `false`.
+    ///
+    /// [`Chars::whitespace_space`]: Chars::whitespace_space
+    /// [`Chars::whitespace_tab`]: Chars::whitespace_tab
+    /// [`Config::tab_width`]: Config::tab_width
+    pub highlight_whitespace: bool,
+    /// Whether to use the [`Files::short_name`] of files in the loci of
+    /// diagnostics, rather than their full [`Files::name`]. This can make
+    /// diagnostics easier to read when files are named by deeply nested paths.
+    ///
+    /// Defaults to: `false`.
+    ///
+    /// [`Files::short_name`]: crate::files::Files::short_name
+    /// [`Files::name`]: crate::files::Files::name
+    pub compact_locus: bool,
+    /// Whether to show the [`Diagnostic::source`] of diagnostics that have
+    /// one, as a line after the notes like `= generated by clippy`. This is
+    /// only shown in the [`DisplayStyle::Rich`] display style.
+    ///
+    /// Defaults to: `false`.
+    ///
+    /// [`Diagnostic::source`]: crate::diagnostic::Diagnostic::source
+    /// [`DisplayStyle::Rich`]: DisplayStyle::Rich
+    pub show_source_provenance: bool,
+    /// Whether to write the message of a label after the line of source it
+    /// points at, like `(+ test "")  // expected Int`, rather than underneath
+    /// it. This leaves out the line of carets, for very terse output.
+    ///
+    /// This only applies to lines with exactly one single-line label that has
+    /// a message. Lines with several labels, labels without messages, or
+    /// multi-line labels fall back to being rendered underneath the source.
+    ///
+    /// Defaults to: `false`.
+    pub inline_label_messages: bool,
+    /// How the file name, line number, and column number of loci are
+    /// punctuated, for matching what editors and terminals recognize as links.
+    ///
+    /// Defaults to: [`LocusFormat::Colon`].
+    ///
+    /// [`LocusFormat::Colon`]: LocusFormat::Colon
+    pub locus_format: LocusFormat,
+    /// Whether to render secondary labels with [`Styles::secondary_label_subdued`]
+    /// on lines of source that also have a primary label, so that it is
+    /// easier to tell the labels apart where they overlap.
+    ///
+    /// Secondary labels on other lines are rendered according to
+    /// [`Config::secondary_emphasis`].
+    ///
+    /// Defaults to: `false`.
+    ///
+    /// [`Styles::secondary_label_subdued`]: Styles::secondary_label_subdued
+    /// [`Config::secondary_emphasis`]: Config::secondary_emphasis
+    pub dim_secondary_with_primary: bool,
 }
 
 impl Default for Config {
@@ -48,8 +318,66 @@ impl Default for Config {
             chars: Chars::default(),
             start_context_lines: 3,
             end_context_lines: 1,
+            multi_line_context: None,
             before_label_lines: 0,
             after_label_lines: 0,
+            caret_row_order: CaretRowOrder::AsSpecified,
+            file_sort: FileSortOrder::FirstAppearance,
+            source_start_marker: None,
+            severity_names: SeverityNames::default(),
+            line_number_offset: 0,
+            terminal_width: None,
+            inline_label_severity: false,
+            suppress_severity_word: false,
+            source_left_pad: 1,
+            show_index: false,
+            secondary_emphasis: SecondaryEmphasis::Equal,
+            sort_diagnostics: false,
+            border_before_notes: true,
+            show_code: true,
+            show_locus: true,
+            max_caret_len: None,
+            medium_includes_first_note: false,
+            gutter_padding: 1,
+            draw_border: true,
+            dedup_trailing_notes: false,
+            show_column_guide: false,
+            column_guide_threshold: 40,
+            code_link: None,
+            locus_link: None,
+            show_byte_offset: false,
+            number_labels: false,
+            highlight_whitespace: false,
+            compact_locus: false,
+            show_source_provenance: false,
+            inline_label_messages: false,
+            locus_format: LocusFormat::Colon,
+            dim_secondary_with_primary: false,
+        }
+    }
+}
+
+impl Config {
+    /// The number of columns the given line of source code would occupy when
+    /// rendered, ignoring any trailing whitespace.
+    ///
+    /// Tabs are expanded to the next tab stop, according to `tab_width`.
+    pub fn line_display_width(&self, source_line: &str) -> usize {
+        source_line
+            .trim_end()
+            .chars()
+            .fold(0, |column, ch| column + self.char_display_width(column, ch))
+    }
+
+    /// The number of columns the given character would occupy when rendered
+    /// at the given column, expanding tabs to the next tab stop.
+    pub(crate) fn char_display_width(&self, column: usize, ch: char) -> usize {
+        use unicode_width::UnicodeWidthChar;
+
+        match (ch, self.tab_width) {
+            ('\t', 0) => 0, // Guard divide-by-zero
+            ('\t', tab_width) => tab_width - (column % tab_width),
+            (ch, _) => ch.width().unwrap_or(0),
         }
     }
 }
@@ -90,6 +418,106 @@ pub enum DisplayStyle {
     /// error[E0002]: Bad config found
     /// ```
     Short,
+    /// Output a compact diagnostic, with the locus, source line, and label
+    /// message packed onto a single line underneath the header.
+    ///
+    /// ```text
+    /// error[E0001]: unexpected type in `+` application
+    /// test:2:9 │ (+ test "") │ ^^ expected `Int` but found `String`
+    /// ```
+    ///
+    /// This only applies to diagnostics with a single, single-line label and
+    /// no notes. All other diagnostics are rendered as with [`DisplayStyle::Rich`].
+    ///
+    /// [`DisplayStyle::Rich`]: DisplayStyle::Rich
+    Linear,
+}
+
+/// The order in which the messages of single-line labels that start at the
+/// same position are rendered underneath the carets.
+///
+/// The examples below show a secondary label with the message `Expected '('`
+/// that was specified before a primary label with the message `Unexpected '{'`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CaretRowOrder {
+    /// Render the messages in the order that the labels were specified in the
+    /// diagnostic.
+    ///
+    /// ```text
+    /// 1 │ ::S { }
+    ///   │     ^
+    ///   │     │
+    ///   │     Expected '('
+    ///   │     Unexpected '{'
+    /// ```
+    AsSpecified,
+    /// Render the messages of primary labels directly underneath the source,
+    /// above the messages of secondary labels.
+    ///
+    /// ```text
+    /// 1 │ ::S { }
+    ///   │     ^
+    ///   │     │
+    ///   │     Unexpected '{'
+    ///   │     Expected '('
+    /// ```
+    PrimaryFirst,
+}
+
+/// The order in which the source snippets of files referenced by the labels
+/// of a diagnostic are rendered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FileSortOrder {
+    /// Render the files in the order that they first appear in the labels of
+    /// the diagnostic.
+    FirstAppearance,
+    /// Render the files containing primary labels first, followed by the
+    /// remaining files in the order that they first appear in the labels of
+    /// the diagnostic.
+    PrimaryFirst,
+}
+
+/// How the file name, line number, and column number of a locus are
+/// punctuated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LocusFormat {
+    /// Separate the parts with colons.
+    ///
+    /// ```text
+    /// test:2:9
+    /// ```
+    Colon,
+    /// Separate the parts with colons, with a colon after the column number.
+    ///
+    /// ```text
+    /// test:2:9:
+    /// ```
+    ColonTrailing,
+    /// Put the line and column numbers in parentheses after the file name.
+    ///
+    /// ```text
+    /// test(2,9)
+    /// ```
+    Parens,
+}
+
+/// How secondary labels are emphasized relative to primary labels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SecondaryEmphasis {
+    /// Render secondary labels with the same emphasis as primary labels,
+    /// using [`Styles::secondary_label`] and [`Chars::single_secondary_caret`].
+    ///
+    /// [`Styles::secondary_label`]: Styles::secondary_label
+    /// [`Chars::single_secondary_caret`]: Chars::single_secondary_caret
+    Equal,
+    /// Render secondary labels more faintly than primary labels, using
+    /// [`Styles::secondary_label_subdued`] and
+    /// [`Chars::single_secondary_caret_subdued`], so that the primary cause
+    /// of the diagnostic stands out.
+    ///
+    /// [`Styles::secondary_label_subdued`]: Styles::secondary_label_subdued
+    /// [`Chars::single_secondary_caret_subdued`]: Chars::single_secondary_caret_subdued
+    Subdued,
 }
 
 /// Styles to use when rendering the diagnostic.
@@ -113,6 +541,9 @@ pub struct Styles {
     /// The style to use when the main diagnostic message.
     /// Defaults to `bold intense`.
     pub header_message: ColorSpec,
+    /// The style to use when rendering the index of a diagnostic in a batch.
+    /// Defaults to `bold intense`.
+    pub header_index: ColorSpec,
 
     /// The style to use when rendering bug labels.
     /// Defaults to `fg:red`.
@@ -132,6 +563,12 @@ pub struct Styles {
     /// The style to use when rendering secondary labels.
     /// Defaults `fg:blue` (or `fg:cyan` on windows).
     pub secondary_label: ColorSpec,
+    /// The style to use when rendering secondary labels with
+    /// [`SecondaryEmphasis::Subdued`].
+    /// Defaults `fg:black intense`, which most terminals render as grey.
+    ///
+    /// [`SecondaryEmphasis::Subdued`]: SecondaryEmphasis::Subdued
+    pub secondary_label_subdued: ColorSpec,
 
     /// The style to use when rendering the line numbers.
     /// Defaults `fg:blue` (or `fg:cyan` on windows).
@@ -139,9 +576,20 @@ pub struct Styles {
     /// The style to use when rendering the source code borders.
     /// Defaults `fg:blue` (or `fg:cyan` on windows).
     pub source_border: ColorSpec,
+    /// The styles to use when rendering the source code borders of diagnostics
+    /// at each severity, indexed in the order help, note, warning, error, bug.
+    /// If set, this overrides `source_border`.
+    /// Defaults to `None`.
+    pub source_border_by_severity: Option<[ColorSpec; 5]>,
     /// The style to use when rendering the note bullets.
     /// Defaults `fg:blue` (or `fg:cyan` on windows).
     pub note_bullet: ColorSpec,
+    /// The style to use when rendering the guides leading up to carets.
+    /// Defaults `fg:black intense`, which most terminals render as grey.
+    pub column_guide: ColorSpec,
+    /// The style to use when rendering the tool that produced a diagnostic.
+    /// Defaults `fg:black intense`, which most terminals render as grey.
+    pub provenance: ColorSpec,
 }
 
 impl Styles {
@@ -168,6 +616,114 @@ impl Styles {
         }
     }
 
+    /// The style used to mark the source code borders at a given severity.
+    pub fn border(&self, severity: Severity) -> &ColorSpec {
+        match &self.source_border_by_severity {
+            Some(borders) => match severity {
+                Severity::Help => &borders[0],
+                Severity::Note => &borders[1],
+                Severity::Warning => &borders[2],
+                Severity::Error => &borders[3],
+                Severity::Bug => &borders[4],
+            },
+            None => &self.source_border,
+        }
+    }
+
+    /// Styles based on the [Solarized] dark color scheme.
+    ///
+    /// [Solarized]: https://ethanschoonover.com/solarized/
+    pub fn solarized_dark() -> Styles {
+        Styles::from_palette(&Palette {
+            bug: 125,
+            error: 160,
+            warning: 136,
+            note: 64,
+            help: 37,
+            secondary: 33,
+            gutter: 33,
+            subdued: 240,
+        })
+    }
+
+    /// Styles based on the [Monokai] color scheme.
+    ///
+    /// [Monokai]: https://monokai.pro/
+    pub fn monokai() -> Styles {
+        Styles::from_palette(&Palette {
+            bug: 141,
+            error: 197,
+            warning: 208,
+            note: 148,
+            help: 81,
+            secondary: 186,
+            gutter: 242,
+            subdued: 242,
+        })
+    }
+
+    /// Styles based on the [Gruvbox] dark color scheme.
+    ///
+    /// [Gruvbox]: https://github.com/morhetz/gruvbox
+    pub fn gruvbox_dark() -> Styles {
+        Styles::from_palette(&Palette {
+            bug: 175,
+            error: 167,
+            warning: 214,
+            note: 142,
+            help: 108,
+            secondary: 109,
+            gutter: 245,
+            subdued: 241,
+        })
+    }
+
+    /// Styles based on the [Dracula] color scheme.
+    ///
+    /// [Dracula]: https://draculatheme.com/
+    pub fn dracula() -> Styles {
+        Styles::from_palette(&Palette {
+            bug: 212,
+            error: 203,
+            warning: 215,
+            note: 84,
+            help: 117,
+            secondary: 141,
+            gutter: 61,
+            subdued: 61,
+        })
+    }
+
+    fn from_palette(palette: &Palette) -> Styles {
+        let header = ColorSpec::new().set_bold(true).clone();
+        let fg = |color| ColorSpec::new().set_fg(Some(Color::Ansi256(color))).clone();
+
+        Styles {
+            header_bug: fg(palette.bug).set_bold(true).clone(),
+            header_error: fg(palette.error).set_bold(true).clone(),
+            header_warning: fg(palette.warning).set_bold(true).clone(),
+            header_note: fg(palette.note).set_bold(true).clone(),
+            header_help: fg(palette.help).set_bold(true).clone(),
+            header_message: header.clone(),
+            header_index: header,
+
+            primary_label_bug: fg(palette.bug),
+            primary_label_error: fg(palette.error),
+            primary_label_warning: fg(palette.warning),
+            primary_label_note: fg(palette.note),
+            primary_label_help: fg(palette.help),
+            secondary_label: fg(palette.secondary),
+            secondary_label_subdued: fg(palette.subdued),
+
+            line_number: fg(palette.gutter),
+            source_border: fg(palette.gutter),
+            source_border_by_severity: None,
+            note_bullet: fg(palette.gutter),
+            column_guide: fg(palette.subdued),
+            provenance: fg(palette.subdued),
+        }
+    }
+
     #[doc(hidden)]
     pub fn with_blue(blue: Color) -> Styles {
         let header = ColorSpec::new().set_bold(true).set_intense(true).clone();
@@ -178,7 +734,8 @@ impl Styles {
             header_warning: header.clone().set_fg(Some(Color::Yellow)).clone(),
             header_note: header.clone().set_fg(Some(Color::Green)).clone(),
             header_help: header.clone().set_fg(Some(Color::Cyan)).clone(),
-            header_message: header,
+            header_message: header.clone(),
+            header_index: header,
 
             primary_label_bug: ColorSpec::new().set_fg(Some(Color::Red)).clone(),
             primary_label_error: ColorSpec::new().set_fg(Some(Color::Red)).clone(),
@@ -186,14 +743,39 @@ impl Styles {
             primary_label_note: ColorSpec::new().set_fg(Some(Color::Green)).clone(),
             primary_label_help: ColorSpec::new().set_fg(Some(Color::Cyan)).clone(),
             secondary_label: ColorSpec::new().set_fg(Some(blue)).clone(),
+            secondary_label_subdued: ColorSpec::new()
+                .set_fg(Some(Color::Black))
+                .set_intense(true)
+                .clone(),
 
             line_number: ColorSpec::new().set_fg(Some(blue)).clone(),
             source_border: ColorSpec::new().set_fg(Some(blue)).clone(),
+            source_border_by_severity: None,
             note_bullet: ColorSpec::new().set_fg(Some(blue)).clone(),
+            column_guide: ColorSpec::new()
+                .set_fg(Some(Color::Black))
+                .set_intense(true)
+                .clone(),
+            provenance: ColorSpec::new()
+                .set_fg(Some(Color::Black))
+                .set_intense(true)
+                .clone(),
         }
     }
 }
 
+/// The 256-color palette of a preset of [`Styles`].
+struct Palette {
+    bug: u8,
+    error: u8,
+    warning: u8,
+    note: u8,
+    help: u8,
+    secondary: u8,
+    gutter: u8,
+    subdued: u8,
+}
+
 impl Default for Styles {
     fn default() -> Styles {
         // Blue is really difficult to see on the standard windows command line
@@ -232,6 +814,22 @@ pub struct Chars {
     /// The character to use for marking a single-line secondary label.
     /// Defaults to: `'-'`.
     pub single_secondary_caret: char,
+    /// The character to use for marking a single-line secondary label with
+    /// [`SecondaryEmphasis::Subdued`].
+    /// Defaults to: `'-'`.
+    ///
+    /// [`SecondaryEmphasis::Subdued`]: SecondaryEmphasis::Subdued
+    pub single_secondary_caret_subdued: char,
+    /// The character to use for filling the underline of a single-line
+    /// primary label, up to the caret in its last column. If this is `None`,
+    /// the caret is repeated instead.
+    /// Defaults to: `None`.
+    pub single_primary_underline_fill: Option<char>,
+    /// The character to use for filling the underline of a single-line
+    /// secondary label, up to the caret in its last column. If this is `None`,
+    /// the caret is repeated instead.
+    /// Defaults to: `None`.
+    pub single_secondary_underline_fill: Option<char>,
 
     /// The character to use for marking the start of a multi-line primary label.
     /// Defaults to: `'^'`.
@@ -264,6 +862,26 @@ pub struct Chars {
     /// The character to use for the left of a pointer underneath a caret.
     /// Defaults to: `'│'` or `'|'` with [`Chars::ascii()`].
     pub pointer_left: char,
+
+    /// The characters to use for marking the truncated ends of long lines of source.
+    /// Defaults to: `"…"` or `"..."` with [`Chars::ascii()`].
+    pub source_ellipsis: String,
+    /// The character to use for marking the end of a run of carets that was
+    /// truncated by [`Config::max_caret_len`].
+    /// Defaults to: `'…'` or `'.'` with [`Chars::ascii()`].
+    pub caret_ellipsis: char,
+    /// The character to use for the guides leading up to carets, when
+    /// [`Config::show_column_guide`] is set.
+    /// Defaults to: `'·'` or `'.'` with [`Chars::ascii()`].
+    pub column_guide: char,
+    /// The character to use for spaces covered by a label, when
+    /// [`Config::highlight_whitespace`] is set.
+    /// Defaults to: `'·'` or `'.'` with [`Chars::ascii()`].
+    pub whitespace_space: char,
+    /// The character to use for the first column of tabs covered by a label,
+    /// when [`Config::highlight_whitespace`] is set.
+    /// Defaults to: `'→'` or `'>'` with [`Chars::ascii()`].
+    pub whitespace_tab: char,
 }
 
 impl Default for Chars {
@@ -284,6 +902,9 @@ impl Chars {
 
             single_primary_caret: '^',
             single_secondary_caret: '-',
+            single_secondary_caret_subdued: '-',
+            single_primary_underline_fill: None,
+            single_secondary_underline_fill: None,
 
             multi_primary_caret_start: '^',
             multi_primary_caret_end: '^',
@@ -296,6 +917,12 @@ impl Chars {
             multi_left: '│',
 
             pointer_left: '│',
+
+            source_ellipsis: "…".to_owned(),
+            caret_ellipsis: '…',
+            column_guide: '·',
+            whitespace_space: '·',
+            whitespace_tab: '→',
         }
     }
 
@@ -303,7 +930,17 @@ impl Chars {
     ///
     /// This is useful if your terminal's font does not support box drawing
     /// characters well and results in output that looks similar to rustc's
-    /// diagnostic output.
+    /// diagnostic output. Source snippets rendered with this character set
+    /// only contain non-ASCII characters if the source code does.
+    ///
+    /// ```rust
+    /// use codespan_reporting::term::{Chars, Config};
+    ///
+    /// let config = Config {
+    ///     chars: Chars::ascii(),
+    ///     ..Config::default()
+    /// };
+    /// ```
     pub fn ascii() -> Chars {
         Chars {
             snippet_start: "-->".into(),
@@ -314,6 +951,9 @@ impl Chars {
 
             single_primary_caret: '^',
             single_secondary_caret: '-',
+            single_secondary_caret_subdued: '-',
+            single_primary_underline_fill: None,
+            single_secondary_underline_fill: None,
 
             multi_primary_caret_start: '^',
             multi_primary_caret_end: '^',
@@ -326,6 +966,107 @@ impl Chars {
             multi_left: '|',
 
             pointer_left: '|',
+
+            source_ellipsis: "...".to_owned(),
+            caret_ellipsis: '.',
+            column_guide: '.',
+            whitespace_space: '.',
+            whitespace_tab: '>',
         }
     }
 }
+
+/// Names to use for each severity when rendering diagnostic headers.
+///
+/// These can be changed to fit the categories used by a tool. For example,
+/// a linter might render notes as `perf`, with the color of their headers
+/// configured using [`Styles::header_note`].
+///
+/// [`Styles::header_note`]: Styles::header_note
+#[derive(Clone, Debug)]
+pub struct SeverityNames {
+    /// The name to use for bugs.
+    /// Defaults to: `"bug"`.
+    pub bug: String,
+    /// The name to use for errors.
+    /// Defaults to: `"error"`.
+    pub error: String,
+    /// The name to use for warnings.
+    /// Defaults to: `"warning"`.
+    pub warning: String,
+    /// The name to use for notes.
+    /// Defaults to: `"note"`.
+    pub note: String,
+    /// The name to use for help messages.
+    /// Defaults to: `"help"`.
+    pub help: String,
+}
+
+impl SeverityNames {
+    /// The name used for the given severity.
+    pub fn name(&self, severity: Severity) -> &str {
+        match severity {
+            Severity::Bug => &self.bug,
+            Severity::Error => &self.error,
+            Severity::Warning => &self.warning,
+            Severity::Note => &self.note,
+            Severity::Help => &self.help,
+        }
+    }
+}
+
+impl Default for SeverityNames {
+    fn default() -> SeverityNames {
+        SeverityNames {
+            bug: Severity::Bug.as_str().to_owned(),
+            error: Severity::Error.as_str().to_owned(),
+            warning: Severity::Warning.as_str().to_owned(),
+            note: Severity::Note.as_str().to_owned(),
+            help: Severity::Help.as_str().to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_display_width_ascii() {
+        let config = Config::default();
+
+        assert_eq!(config.line_display_width(""), 0);
+        assert_eq!(config.line_display_width("(+ test \"\")"), 11);
+        assert_eq!(config.line_display_width("(+ test \"\")  \r\n"), 11);
+    }
+
+    #[test]
+    fn line_display_width_tabs() {
+        let config = Config {
+            tab_width: 4,
+            ..Config::default()
+        };
+
+        assert_eq!(config.line_display_width("\thello"), 9);
+        assert_eq!(config.line_display_width("a\thello"), 9);
+        assert_eq!(config.line_display_width("ab\thello"), 9);
+        assert_eq!(config.line_display_width("abcd\thello"), 13);
+        assert_eq!(config.line_display_width("hello\t"), 5);
+
+        let config = Config {
+            tab_width: 0,
+            ..Config::default()
+        };
+
+        assert_eq!(config.line_display_width("\thello"), 5);
+    }
+
+    #[test]
+    fn line_display_width_cjk() {
+        let config = Config::default();
+
+        assert_eq!(config.line_display_width("日本語"), 6);
+        assert_eq!(config.line_display_width("a日本語b"), 8);
+        assert_eq!(config.line_display_width("日\tb"), 5);
+    }
+}
@@ -0,0 +1,126 @@
+use serde::Serialize;
+use std::io::Write;
+
+use crate::diagnostic::{Diagnostic, LabelStyle};
+use crate::files::{Error, Files, Location};
+
+// The fields of each object are declared in alphabetical order, so that the
+// keys of the output are sorted.
+
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'a str>,
+    labels: Vec<JsonLabel<'a>>,
+    message: &'a str,
+    notes: &'a [String],
+    rich_notes: Vec<JsonRichNote<'a>>,
+    severity: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<&'a str>,
+    suggestions: Vec<JsonSuggestion<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonLabel<'a> {
+    end: JsonLocation,
+    file: String,
+    message: &'a str,
+    priority: i32,
+    start: JsonLocation,
+    style: &'static str,
+}
+
+#[derive(Serialize)]
+struct JsonRichNote<'a> {
+    message: &'a str,
+    plain: bool,
+    severity: &'static str,
+}
+
+#[derive(Serialize)]
+struct JsonSuggestion<'a> {
+    end: JsonLocation,
+    file: String,
+    message: &'a str,
+    replacement: &'a str,
+    start: JsonLocation,
+}
+
+#[derive(Serialize)]
+struct JsonLocation {
+    column: usize,
+    line: usize,
+}
+
+impl From<Location> for JsonLocation {
+    fn from(location: Location) -> JsonLocation {
+        JsonLocation {
+            column: location.column_number,
+            line: location.line_number,
+        }
+    }
+}
+
+/// Write a diagnostic as a single line of JSON. See [`emit_json`] for details.
+///
+/// [`emit_json`]: crate::term::emit_json
+pub fn emit<'files, F: Files<'files>>(
+    writer: &mut dyn Write,
+    files: &'files F,
+    diagnostic: &Diagnostic<F::FileId>,
+) -> Result<(), Error> {
+    let mut labels = Vec::with_capacity(diagnostic.labels.len());
+    for label in &diagnostic.labels {
+        labels.push(JsonLabel {
+            end: files.location(label.file_id, label.range.end)?.into(),
+            file: files.name(label.file_id)?.to_string(),
+            message: &label.message,
+            priority: label.priority,
+            start: files.location(label.file_id, label.range.start)?.into(),
+            style: match label.style {
+                LabelStyle::Primary => "primary",
+                LabelStyle::Secondary => "secondary",
+            },
+        });
+    }
+
+    let mut suggestions = Vec::with_capacity(diagnostic.suggestions.len());
+    for suggestion in &diagnostic.suggestions {
+        suggestions.push(JsonSuggestion {
+            end: files
+                .location(suggestion.file_id, suggestion.range.end)?
+                .into(),
+            file: files.name(suggestion.file_id)?.to_string(),
+            message: &suggestion.message,
+            replacement: &suggestion.replacement,
+            start: files
+                .location(suggestion.file_id, suggestion.range.start)?
+                .into(),
+        });
+    }
+
+    let diagnostic = JsonDiagnostic {
+        code: diagnostic.code.as_deref().filter(|code| !code.is_empty()),
+        labels,
+        message: &diagnostic.message,
+        notes: &diagnostic.notes,
+        rich_notes: diagnostic
+            .rich_notes
+            .iter()
+            .map(|note| JsonRichNote {
+                message: &note.message,
+                plain: note.plain,
+                severity: note.severity.as_str(),
+            })
+            .collect(),
+        severity: diagnostic.severity.as_str(),
+        source: diagnostic.source.as_deref(),
+        suggestions,
+    };
+
+    serde_json::to_writer(&mut *writer, &diagnostic).map_err(std::io::Error::from)?;
+    writeln!(writer)?;
+
+    Ok(())
+}
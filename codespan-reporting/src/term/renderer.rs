@@ -4,14 +4,21 @@ use termcolor::{ColorSpec, WriteColor};
 
 use crate::diagnostic::{LabelStyle, Severity};
 use crate::files::{Error, Location};
-use crate::term::{Chars, Config, Styles};
+use crate::term::{Chars, Config, LocusFormat, SecondaryEmphasis, Styles};
 
 /// The 'location focus' of a source code snippet.
 pub struct Locus {
     /// The user-facing name of the file.
     pub name: String,
+    /// The full name of the file, which may differ from the user-facing name
+    /// if [`Config::compact_locus`] is set.
+    ///
+    /// [`Config::compact_locus`]: crate::term::Config::compact_locus
+    pub full_name: String,
     /// The location.
     pub location: Location,
+    /// The byte index of the location in the source.
+    pub byte_index: usize,
 }
 
 /// Single-line label, with an optional message.
@@ -62,6 +69,13 @@ enum VerticalBound {
 
 type Underline = (LabelStyle, VerticalBound);
 
+/// A line of source code that a row of output corresponds to.
+///
+/// The first value is the index of a label in the diagnostic that refers to
+/// the file containing the line, and the second value is the index of the line
+/// in that file.
+pub type SourceLine = (usize, usize);
+
 /// A renderer of display list entries.
 ///
 /// The following diagram gives an overview of each of the parts of the renderer's output:
@@ -111,6 +125,22 @@ type Underline = (LabelStyle, VerticalBound);
 pub struct Renderer<'writer, 'config> {
     writer: &'writer mut dyn WriteColor,
     config: &'config Config,
+    /// The source line that the output currently being written corresponds to.
+    source_line: Option<SourceLine>,
+    /// The source line that each completed row of output corresponds to, if
+    /// this is being tracked.
+    source_lines: Option<Vec<Option<SourceLine>>>,
+    /// The color that was last set on the writer, or `None` if it was reset.
+    current_color: Option<ColorSpec>,
+    /// The color that the next text should be written in. Changes of color
+    /// are only passed on to the writer when text is written, so that
+    /// consecutive changes are coalesced.
+    pending_color: Option<ColorSpec>,
+    /// Whether the color was reset since it was last passed on to the writer.
+    pending_reset: bool,
+    /// Whether secondary labels are being dimmed, because they are on the
+    /// same line as a primary label.
+    dim_secondary: bool,
 }
 
 impl<'writer, 'config> Renderer<'writer, 'config> {
@@ -119,7 +149,51 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         writer: &'writer mut dyn WriteColor,
         config: &'config Config,
     ) -> Renderer<'writer, 'config> {
-        Renderer { writer, config }
+        Renderer {
+            writer,
+            config,
+            source_line: None,
+            source_lines: None,
+            current_color: None,
+            pending_color: None,
+            pending_reset: false,
+            dim_secondary: false,
+        }
+    }
+
+    /// Start keeping track of the source line that each row of output
+    /// corresponds to.
+    pub fn track_source_lines(&mut self) {
+        self.source_lines = Some(Vec::new());
+    }
+
+    /// Set the source line that the following rows of output correspond to.
+    pub fn set_source_line(&mut self, source_line: Option<SourceLine>) {
+        self.source_line = source_line;
+    }
+
+    /// The source line that each row of output corresponded to, if
+    /// [`Renderer::track_source_lines`] was called.
+    pub fn into_source_lines(mut self) -> Option<Vec<Option<SourceLine>>> {
+        self.source_lines.take()
+    }
+
+    /// Pass the pending change of color on to the writer, if the color
+    /// differs from the one that was last set.
+    fn apply_color(&mut self) -> io::Result<()> {
+        if self.pending_color != self.current_color {
+            // Reset before changing to a different color, as some color specs
+            // keep the attributes of the previous color
+            if self.current_color.is_some() && self.pending_reset {
+                self.writer.reset()?;
+            }
+            if let Some(spec) = &self.pending_color {
+                self.writer.set_color(spec)?;
+            }
+            self.current_color = self.pending_color.clone();
+        }
+        self.pending_reset = false;
+        Ok(())
     }
 
     fn chars(&self) -> &'config Chars {
@@ -130,6 +204,57 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         &self.config.styles
     }
 
+    /// The style used to mark a label, taking into account the emphasis of
+    /// secondary labels.
+    fn label_color(&self, severity: Severity, label_style: LabelStyle) -> &'config ColorSpec {
+        match (label_style, self.config.secondary_emphasis) {
+            (LabelStyle::Secondary, SecondaryEmphasis::Subdued) => {
+                &self.styles().secondary_label_subdued
+            }
+            (LabelStyle::Secondary, _) if self.dim_secondary => {
+                &self.styles().secondary_label_subdued
+            }
+            (_, _) => self.styles().label(severity, label_style),
+        }
+    }
+
+    /// The caret used to mark a single-line label, taking into account the
+    /// emphasis of secondary labels.
+    fn single_caret(&self, label_style: LabelStyle) -> char {
+        match (label_style, self.config.secondary_emphasis) {
+            (LabelStyle::Primary, _) => self.chars().single_primary_caret,
+            (LabelStyle::Secondary, SecondaryEmphasis::Equal) => {
+                self.chars().single_secondary_caret
+            }
+            (LabelStyle::Secondary, SecondaryEmphasis::Subdued) => {
+                self.chars().single_secondary_caret_subdued
+            }
+        }
+    }
+
+    /// The character used to fill the underline of a single-line label, up to
+    /// its caret.
+    fn single_underline_fill(&self, label_style: LabelStyle) -> char {
+        let fill = match label_style {
+            LabelStyle::Primary => self.chars().single_primary_underline_fill,
+            LabelStyle::Secondary => self.chars().single_secondary_underline_fill,
+        };
+        fill.unwrap_or_else(|| self.single_caret(label_style))
+    }
+
+    /// The position of a diagnostic in a batch, prefixed to its header.
+    ///
+    /// ```text
+    /// [3/17]
+    /// ```
+    pub fn render_index(&mut self, index: usize, total: usize) -> Result<(), Error> {
+        self.set_color(&self.styles().header_index)?;
+        write!(self, "[{}/{}]", index, total)?;
+        self.reset()?;
+        write!(self, " ")?;
+        Ok(())
+    }
+
     /// Diagnostic header, with severity, code, and message.
     ///
     /// ```text
@@ -149,7 +274,21 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         // ```
         if let Some(locus) = locus {
             self.snippet_locus(locus)?;
-            write!(self, ": ")?;
+            match self.config.locus_format {
+                LocusFormat::ColonTrailing => write!(self, " ")?,
+                LocusFormat::Colon | LocusFormat::Parens => write!(self, ": ")?,
+            }
+        }
+
+        // Write only the diagnostic message if the severity is suppressed
+        //
+        // ```text
+        // Compiling module Foo
+        // ```
+        if self.config.suppress_severity_word {
+            writeln!(self, "{}", message)?;
+
+            return Ok(());
         }
 
         // Write severity name
@@ -158,21 +297,26 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         // error
         // ```
         self.set_color(self.styles().header(severity))?;
-        match severity {
-            Severity::Bug => write!(self, "bug")?,
-            Severity::Error => write!(self, "error")?,
-            Severity::Warning => write!(self, "warning")?,
-            Severity::Help => write!(self, "help")?,
-            Severity::Note => write!(self, "note")?,
-        }
+        write!(self, "{}", self.config.severity_names.name(severity))?;
 
         // Write error code
         //
         // ```text
         // [E0001]
         // ```
-        if let Some(code) = &code.filter(|code| !code.is_empty()) {
-            write!(self, "[{}]", code)?;
+        if self.config.show_code {
+            if let Some(code) = &code.filter(|code| !code.is_empty()) {
+                match self.config.code_link {
+                    // Link to the code with an OSC 8 hyperlink
+                    Some(code_link) if self.supports_color() => write!(
+                        self,
+                        "\x1b]8;;{}\x1b\\[{}]\x1b]8;;\x1b\\",
+                        code_link(code),
+                        code,
+                    )?,
+                    Some(_) | None => write!(self, "[{}]", code)?,
+                }
+            }
         }
 
         // Write diagnostic message
@@ -203,17 +347,173 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
     pub fn render_snippet_start(
         &mut self,
         outer_padding: usize,
+        severity: Severity,
         locus: &Locus,
     ) -> Result<(), Error> {
         self.outer_gutter(outer_padding)?;
 
-        self.set_color(&self.styles().source_border)?;
-        write!(self, "{}", self.chars().snippet_start)?;
-        self.reset()?;
+        if self.config.draw_border {
+            self.set_color(self.styles().border(severity))?;
+            write!(self, "{}", self.chars().snippet_start)?;
+            self.reset()?;
+            write!(self, " ")?;
+        }
+        self.snippet_locus(locus)?;
 
-        write!(self, " ")?;
+        writeln!(self)?;
+
+        Ok(())
+    }
+
+    /// Top left border and file name of a binary file.
+    ///
+    /// ```text
+    /// ┌─ logo.png
+    /// ```
+    pub fn render_binary_snippet_start(
+        &mut self,
+        outer_padding: usize,
+        severity: Severity,
+        name: &str,
+    ) -> Result<(), Error> {
+        self.outer_gutter(outer_padding)?;
+
+        if self.config.draw_border {
+            self.set_color(self.styles().border(severity))?;
+            write!(self, "{}", self.chars().snippet_start)?;
+            self.reset()?;
+            write!(self, " ")?;
+        }
+        writeln!(self, "{}", name)?;
+
+        Ok(())
+    }
+
+    /// A placeholder for the source of a binary file, followed by the byte
+    /// ranges and messages of its labels.
+    ///
+    /// ```text
+    /// │ <binary file, 16 bytes>
+    /// │ ^ bytes 0..8: expected a PNG signature
+    /// ```
+    pub fn render_binary_snippet_source(
+        &mut self,
+        outer_padding: usize,
+        severity: Severity,
+        len: usize,
+        single_labels: &[SingleLabel<'_>],
+    ) -> Result<(), Error> {
+        self.outer_gutter(outer_padding)?;
+        self.border_left(severity)?;
+        self.source_left_pad()?;
+        writeln!(self, "<binary file, {} bytes>", len)?;
+
+        for (label_style, range, message) in single_labels {
+            self.outer_gutter(outer_padding)?;
+            self.border_left(severity)?;
+            self.source_left_pad()?;
+            self.set_color(self.label_color(severity, *label_style))?;
+            write!(
+                self,
+                "{} bytes {}..{}",
+                self.single_caret(*label_style),
+                range.start,
+                range.end,
+            )?;
+            if !message.is_empty() {
+                write!(self, ": ")?;
+                self.label_message(severity, message)?;
+            }
+            self.reset()?;
+            writeln!(self)?;
+        }
+
+        Ok(())
+    }
+
+    /// A locus, line of source code, and label, packed onto a single line.
+    ///
+    /// ```text
+    /// test:2:9 │ (+ test "") │ ^^ expected `Int` but found `String`
+    /// ```
+    pub fn render_linear_source(
+        &mut self,
+        locus: &Locus,
+        source: &str,
+        severity: Severity,
+        single_label: &SingleLabel<'_>,
+    ) -> Result<(), Error> {
+        let (label_style, range, message) = single_label;
+
+        // Trim surrounding whitespace from the source, adjusting the label range to match.
+        let trimmed_start = source.len() - source.trim_start().len();
+        let source = source.trim();
+        let range = range.start.saturating_sub(trimmed_start).min(source.len())
+            ..range.end.saturating_sub(trimmed_start).min(source.len());
+
+        // Write locus
+        //
+        // ```text
+        // test:2:9 │
+        // ```
         self.snippet_locus(locus)?;
+        write!(self, " ")?;
+        self.border_left(severity)?;
+
+        // Write source text
+        //
+        // ```text
+        //  (+ test "") │
+        // ```
+        write!(self, " ")?;
+        let mut caret_width = 0;
+        let mut in_primary = false;
+        for (metrics, ch) in self.char_metrics(source.char_indices()) {
+            let column_range = metrics.byte_index..(metrics.byte_index + ch.len_utf8());
+            let is_overlapping = is_overlapping(&range, &column_range);
+            if is_overlapping {
+                caret_width += metrics.unicode_width;
+            }
+
+            // Set the source color if we are in a primary label
+            let is_primary = *label_style == LabelStyle::Primary && is_overlapping;
+            if is_primary && !in_primary {
+                self.set_color(self.label_color(severity, LabelStyle::Primary))?;
+                in_primary = true;
+            } else if !is_primary && in_primary {
+                self.reset()?;
+                in_primary = false;
+            }
+
+            match ch {
+                '\t' => (0..metrics.unicode_width).try_for_each(|_| write!(self, " "))?,
+                _ => write!(self, "{}", ch)?,
+            }
+        }
+        if in_primary {
+            self.reset()?;
+        }
+        write!(self, " ")?;
+        self.border_left(severity)?;
 
+        // Write label carets and message
+        //
+        // ```text
+        //  ^^ expected `Int` but found `String`
+        // ```
+        write!(self, " ")?;
+        self.set_color(self.label_color(severity, *label_style))?;
+        let caret_char = self.single_caret(*label_style);
+        let fill_char = self.single_underline_fill(*label_style);
+        for _ in 1..std::cmp::max(caret_width, 1) {
+            write!(self, "{}", fill_char)?;
+        }
+        write!(self, "{}", caret_char)?;
+        if !message.is_empty() {
+            write!(self, " ")?;
+            self.label_message(severity, message)?;
+        }
+        self.reset()?;
         writeln!(self)?;
 
         Ok(())
@@ -235,10 +535,49 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         num_multi_labels: usize,
         multi_labels: &[(usize, LabelStyle, MultiLabel<'_>)],
     ) -> Result<(), Error> {
+        // Dim the secondary labels of lines that also have a primary label, so
+        // that the primary label stands out
+        let has_primary = single_labels
+            .iter()
+            .map(|(label_style, _, _)| label_style)
+            .chain(multi_labels.iter().map(|(_, label_style, _)| label_style))
+            .any(|label_style| *label_style == LabelStyle::Primary);
+        self.dim_secondary = self.config.dim_secondary_with_primary && has_primary;
+
         // Trim trailing newlines, linefeeds, and null chars from source, if they exist.
         // FIXME: Use the number of trimmed placeholders when rendering single line carets
         let source = source.trim_end_matches(['\n', '\r', '\0'].as_ref());
 
+        // Window long lines of source around their labels, if they would not
+        // fit in the terminal.
+        let windowed;
+        let (source, single_labels) = match self.window_source(
+            outer_padding,
+            source,
+            single_labels,
+            num_multi_labels,
+            multi_labels,
+        ) {
+            Some(window) => {
+                windowed = window;
+                (windowed.0.as_str(), windowed.1.as_slice())
+            }
+            None => (source, single_labels),
+        };
+
+        // A lone label whose message is written after the source line, in
+        // place of the carets underneath it
+        let inline_label = match single_labels {
+            [(label_style, _, message)]
+                if self.config.inline_label_messages
+                    && multi_labels.is_empty()
+                    && !message.is_empty() =>
+            {
+                Some((*label_style, *message))
+            }
+            _ => None,
+        };
+
         // Write source line
         //
         // ```text
@@ -247,7 +586,7 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         {
             // Write outer gutter (with line number) and border
             self.outer_gutter_number(line_number, outer_padding)?;
-            self.border_left()?;
+            self.border_left(severity)?;
 
             // Write inner gutter (with multi-line continuations on the left if necessary)
             let mut multi_labels_iter = multi_labels.iter().peekable();
@@ -271,27 +610,44 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
                 }
             }
 
+            // Write the source start marker in place of the space before the
+            // source text, so that the carets underneath remain aligned
+            match self.config.source_start_marker {
+                Some(marker) if self.config.source_left_pad > 0 => {
+                    (1..self.config.source_left_pad).try_for_each(|_| write!(self, " "))?;
+                    self.set_color(&self.styles().source_border)?;
+                    write!(self, "{}", marker)?;
+                    self.reset()?;
+                }
+                Some(_) | None => self.source_left_pad()?,
+            }
+
             // Write source text
-            write!(self, " ")?;
             let mut in_primary = false;
             for (metrics, ch) in self.char_metrics(source.char_indices()) {
                 let column_range = metrics.byte_index..(metrics.byte_index + ch.len_utf8());
 
-                // Check if we are overlapping a primary label
-                let is_primary = single_labels.iter().any(|(ls, range, _)| {
-                    *ls == LabelStyle::Primary && is_overlapping(range, &column_range)
-                }) || multi_labels.iter().any(|(_, ls, label)| {
-                    *ls == LabelStyle::Primary
-                        && match label {
-                            MultiLabel::Top(start) => column_range.start >= *start,
-                            MultiLabel::Left => true,
-                            MultiLabel::Bottom(start, _) => column_range.end <= *start,
-                        }
-                });
+                // Find the styles of the labels we are overlapping
+                let mut label_styles = single_labels
+                    .iter()
+                    .filter(|(_, range, _)| is_overlapping(range, &column_range))
+                    .map(|(label_style, _, _)| *label_style)
+                    .chain(
+                        multi_labels
+                            .iter()
+                            .filter(|(_, _, label)| match label {
+                                MultiLabel::Top(start) => column_range.start >= *start,
+                                MultiLabel::Left => true,
+                                MultiLabel::Bottom(start, _) => column_range.end <= *start,
+                            })
+                            .map(|(_, label_style, _)| *label_style),
+                    );
+                let is_primary = label_styles.clone().any(|ls| ls == LabelStyle::Primary);
+                let is_labeled = label_styles.next().is_some();
 
                 // Set the source color if we are in a primary label
                 if is_primary && !in_primary {
-                    self.set_color(self.styles().label(severity, LabelStyle::Primary))?;
+                    self.set_color(self.label_color(severity, LabelStyle::Primary))?;
                     in_primary = true;
                 } else if !is_primary && in_primary {
                     self.reset()?;
@@ -299,16 +655,45 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
                 }
 
                 match ch {
+                    '\t' if is_labeled && self.config.highlight_whitespace => {
+                        for column in 0..metrics.unicode_width {
+                            match column {
+                                0 => write!(self, "{}", self.chars().whitespace_tab)?,
+                                _ => write!(self, " ")?,
+                            }
+                        }
+                    }
                     '\t' => (0..metrics.unicode_width).try_for_each(|_| write!(self, " "))?,
+                    ' ' if is_labeled && self.config.highlight_whitespace => {
+                        write!(self, "{}", self.chars().whitespace_space)?
+                    }
                     _ => write!(self, "{}", ch)?,
                 }
             }
             if in_primary {
                 self.reset()?;
             }
+
+            // Write the inline label message
+            //
+            // ```text
+            // (+ test "")  // expected `Int` but found `String`
+            // ```
+            if let Some((label_style, message)) = inline_label {
+                write!(self, "  ")?;
+                self.set_color(self.label_color(severity, label_style))?;
+                write!(self, "// ")?;
+                self.label_message(severity, message)?;
+                self.reset()?;
+            }
             writeln!(self)?;
         }
 
+        if inline_label.is_some() {
+            self.dim_secondary = false;
+            return Ok(());
+        }
+
         // Write single labels underneath source
         //
         // ```text
@@ -318,6 +703,31 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         //   │     first borrow later used by call
         //   │     help: some help here
         // ```
+        let widened;
+        let single_labels = match self.widen_zero_width_labels(source, single_labels) {
+            Some(labels) => {
+                widened = labels;
+                widened.as_slice()
+            }
+            None => single_labels,
+        };
+        let capped;
+        let capped_labels;
+        let (single_labels, caret_ellipses) = match self.cap_carets(source, single_labels) {
+            Some(caps) => {
+                capped = caps;
+                capped_labels = capped
+                    .0
+                    .iter()
+                    .map(|(label_style, range, message)| {
+                        (*label_style, range.clone(), message.as_str())
+                    })
+                    .collect::<Vec<_>>();
+                (capped_labels.as_slice(), capped.1.as_slice())
+            }
+            None => (single_labels, &[][..]),
+        };
+
         if !single_labels.is_empty() {
             // Our plan is as follows:
             //
@@ -400,16 +810,34 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
             //   │ ^^^^^^  -------^^^^^^^^^-------^^^^^----- ^^^^ trailing label message
             // ```
             self.outer_gutter(outer_padding)?;
-            self.border_left()?;
+            self.border_left(severity)?;
             self.inner_gutter(severity, num_multi_labels, multi_labels)?;
-            write!(self, " ")?;
+            self.source_left_pad()?;
+
+            // Write a guide leading up to carets that are far from the border
+            //
+            // ```text
+            //   │ ·······························^^^ expected `Int`
+            // ```
+            let guide_end = self.column_guide_end(source, single_labels);
+            if guide_end > 0 {
+                self.set_color(&self.styles().column_guide)?;
+                for (metrics, _) in self
+                    .char_metrics(source.char_indices())
+                    .take_while(|(metrics, _)| metrics.byte_index < guide_end)
+                {
+                    (0..metrics.unicode_width)
+                        .try_for_each(|_| write!(self, "{}", self.chars().column_guide))?;
+                }
+                self.reset()?;
+            }
 
             let mut previous_label_style = None;
             let placeholder_metrics = Metrics {
                 byte_index: source.len(),
                 unicode_width: 1,
             };
-            for (metrics, ch) in self
+            let columns = self
                 .char_metrics(source.char_indices())
                 // Add a placeholder source column at the end to allow for
                 // printing carets at the end of lines, eg:
@@ -419,35 +847,67 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
                 //   │             ^
                 // ```
                 .chain(std::iter::once((placeholder_metrics, '\0')))
-            {
-                // Find the current label style at this column
-                let column_range = metrics.byte_index..(metrics.byte_index + ch.len_utf8());
-                let current_label_style = single_labels
-                    .iter()
-                    .filter(|(_, range, _)| is_overlapping(range, &column_range))
-                    .map(|(label_style, _, _)| *label_style)
-                    .max_by_key(label_priority_key);
+                .skip_while(|(metrics, _)| metrics.byte_index < guide_end)
+                .map(|(metrics, ch)| {
+                    // Find the label style at this column
+                    let column_range = metrics.byte_index..(metrics.byte_index + ch.len_utf8());
+                    let label_style = single_labels
+                        .iter()
+                        .filter(|(_, range, _)| is_overlapping(range, &column_range))
+                        .map(|(label_style, _, _)| *label_style)
+                        .max_by_key(label_priority_key);
+                    (metrics, label_style)
+                })
+                .collect::<Vec<_>>();
+            for (column_index, (metrics, current_label_style)) in columns.iter().enumerate() {
+                let current_label_style = *current_label_style;
 
                 // Update writer style if necessary
                 if previous_label_style != current_label_style {
                     match current_label_style {
                         None => self.reset()?,
                         Some(label_style) => {
-                            self.set_color(self.styles().label(severity, label_style))?;
+                            self.set_color(self.label_color(severity, label_style))?;
                         }
                     }
                 }
 
                 let caret_ch = match current_label_style {
-                    Some(LabelStyle::Primary) => Some(self.chars().single_primary_caret),
-                    Some(LabelStyle::Secondary) => Some(self.chars().single_secondary_caret),
+                    Some(label_style) => Some(self.single_caret(label_style)),
                     // Only print padding if we are before the end of the last single line caret
                     None if metrics.byte_index < max_label_end => Some(' '),
                     None => None,
                 };
-                if let Some(caret_ch) = caret_ch {
-                    // FIXME: improve rendering of carets between character boundaries
-                    (0..metrics.unicode_width).try_for_each(|_| write!(self, "{}", caret_ch))?;
+                match caret_ch {
+                    // Mark the end of a capped run of carets
+                    Some(_) if caret_ellipses.contains(&metrics.byte_index) => {
+                        write!(self, "{}", self.chars().caret_ellipsis)?;
+                        (1..metrics.unicode_width).try_for_each(|_| write!(self, " "))?;
+                    }
+                    Some(caret_ch) => {
+                        // Underlines are filled up to their last column, which
+                        // is marked with the caret.
+                        let fill_ch = match current_label_style {
+                            Some(label_style) => self.single_underline_fill(label_style),
+                            None => caret_ch,
+                        };
+                        let next_label_style = columns[column_index + 1..]
+                            .iter()
+                            .find(|(metrics, _)| metrics.unicode_width > 0)
+                            .map(|(_, label_style)| *label_style);
+                        let is_run_end = match next_label_style {
+                            Some(next_label_style) => next_label_style != current_label_style,
+                            None => true,
+                        };
+                        // FIXME: improve rendering of carets between character boundaries
+                        for column in 0..metrics.unicode_width {
+                            match is_run_end && column + 1 == metrics.unicode_width {
+                                true => write!(self, "{}", caret_ch)?,
+                                false => write!(self, "{}", fill_ch)?,
+                            }
+                        }
+                    }
+                    None => {}
                 }
 
                 previous_label_style = current_label_style;
@@ -459,8 +919,8 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
             // Write first trailing label message
             if let Some((_, (label_style, _, message))) = trailing_label {
                 write!(self, " ")?;
-                self.set_color(self.styles().label(severity, *label_style))?;
-                write!(self, "{}", message)?;
+                self.set_color(self.label_color(severity, *label_style))?;
+                self.label_message(severity, message)?;
                 self.reset()?;
             }
             writeln!(self)?;
@@ -480,9 +940,9 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
                 //   │     │ │
                 // ```
                 self.outer_gutter(outer_padding)?;
-                self.border_left()?;
+                self.border_left(severity)?;
                 self.inner_gutter(severity, num_multi_labels, multi_labels)?;
-                write!(self, " ")?;
+                self.source_left_pad()?;
                 self.caret_pointers(
                     severity,
                     max_label_start,
@@ -503,9 +963,9 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
                     hanging_labels(single_labels, trailing_label).rev()
                 {
                     self.outer_gutter(outer_padding)?;
-                    self.border_left()?;
+                    self.border_left(severity)?;
                     self.inner_gutter(severity, num_multi_labels, multi_labels)?;
-                    write!(self, " ")?;
+                    self.source_left_pad()?;
                     self.caret_pointers(
                         severity,
                         max_label_start,
@@ -515,8 +975,8 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
                             .char_indices()
                             .take_while(|(byte_index, _)| *byte_index < range.start),
                     )?;
-                    self.set_color(self.styles().label(severity, *label_style))?;
-                    write!(self, "{}", message)?;
+                    self.set_color(self.label_color(severity, *label_style))?;
+                    self.label_message(severity, message)?;
                     self.reset()?;
                     writeln!(self)?;
                 }
@@ -541,7 +1001,7 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
             };
 
             self.outer_gutter(outer_padding)?;
-            self.border_left()?;
+            self.border_left(severity)?;
 
             // Write inner gutter.
             //
@@ -590,9 +1050,254 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
             }
         }
 
+        self.dim_secondary = false;
         Ok(())
     }
 
+    /// Widen the single-line labels that only cover zero-width characters,
+    /// like combining marks, to start at the preceding visible character, so
+    /// that they are underlined by at least one caret.
+    ///
+    /// ```text
+    /// á́́
+    /// ^ accents
+    /// ```
+    ///
+    /// Returns `None` if no labels need widening.
+    fn widen_zero_width_labels<'labels>(
+        &self,
+        source: &str,
+        single_labels: &[SingleLabel<'labels>],
+    ) -> Option<Vec<SingleLabel<'labels>>> {
+        let is_zero_width = |range: &Range<usize>| {
+            range.start < std::cmp::min(range.end, source.len())
+                && self
+                    .char_metrics(source.char_indices())
+                    .filter(|(metrics, _)| range.contains(&metrics.byte_index))
+                    .all(|(metrics, _)| metrics.unicode_width == 0)
+        };
+
+        if !single_labels
+            .iter()
+            .any(|(_, range, _)| is_zero_width(range))
+        {
+            return None;
+        }
+
+        let widened_labels = single_labels.iter().map(|(label_style, range, message)| {
+            let mut range = range.clone();
+            if is_zero_width(&range) {
+                let preceding_char = self
+                    .char_metrics(source.char_indices())
+                    .take_while(|(metrics, _)| metrics.byte_index < range.start)
+                    .filter(|(metrics, _)| metrics.unicode_width > 0)
+                    .last();
+                if let Some((metrics, _)) = preceding_char {
+                    range.start = metrics.byte_index;
+                }
+            }
+            (*label_style, range, *message)
+        });
+
+        Some(widened_labels.collect())
+    }
+
+    /// Cap the carets of single-line labels that are wider than the configured
+    /// maximum caret length, marking the end of each capped run of carets with
+    /// an ellipsis, and noting the original length in the label's message.
+    ///
+    /// ```text
+    /// ^^^^^^^^^^^^^^^^^^^^… (10000 chars)
+    /// ```
+    ///
+    /// Returns the labels adjusted to match, along with the byte indices at
+    /// which ellipses replace carets, or `None` if no labels need capping.
+    #[allow(clippy::type_complexity)]
+    fn cap_carets(
+        &self,
+        source: &str,
+        single_labels: &[SingleLabel<'_>],
+    ) -> Option<(Vec<(LabelStyle, Range<usize>, String)>, Vec<usize>)> {
+        let max_caret_len = self.config.max_caret_len?;
+
+        let mut capped_labels = Vec::with_capacity(single_labels.len());
+        let mut caret_ellipses = Vec::new();
+        for (label_style, range, message) in single_labels {
+            // Find the column that would exceed the maximum caret length
+            let mut caret_len = 0;
+            let mut ellipsis = None;
+            for (metrics, ch) in self.char_metrics(source.char_indices()) {
+                if metrics.byte_index < range.start {
+                    continue;
+                } else if metrics.byte_index >= range.end {
+                    break;
+                } else if caret_len >= max_caret_len {
+                    ellipsis = Some((metrics, ch));
+                    break;
+                }
+                caret_len += metrics.unicode_width;
+            }
+
+            match ellipsis {
+                None => capped_labels.push((*label_style, range.clone(), message.to_string())),
+                Some((metrics, ch)) => {
+                    let label_source = source
+                        .get(range.start..std::cmp::min(range.end, source.len()))
+                        .unwrap_or("");
+                    let label_len = label_source.chars().count();
+                    let message = if message.is_empty() {
+                        format!("({} chars)", label_len)
+                    } else {
+                        format!("{} ({} chars)", message, label_len)
+                    };
+
+                    let capped_range = range.start..(metrics.byte_index + ch.len_utf8());
+                    capped_labels.push((*label_style, capped_range, message));
+                    caret_ellipses.push(metrics.byte_index);
+                }
+            }
+        }
+
+        if caret_ellipses.is_empty() {
+            None
+        } else {
+            Some((capped_labels, caret_ellipses))
+        }
+    }
+
+    /// Select a window of a long line of source code that fits within the
+    /// configured terminal width, centred on the single-line labels of the
+    /// line. Truncated ends of the line are marked with an ellipsis, and tabs
+    /// are expanded to spaces according to their original columns.
+    ///
+    /// ```text
+    /// …ncy bonbon candy. Apple pie jelly beans muffin chocolate cake…
+    /// ```
+    ///
+    /// Returns the windowed source, along with the labels adjusted to match,
+    /// or `None` if the line does not need to be windowed. Lines containing
+    /// the start or end of a multi-line label are never windowed.
+    fn window_source<'label>(
+        &self,
+        outer_padding: usize,
+        source: &str,
+        single_labels: &[SingleLabel<'label>],
+        num_multi_labels: usize,
+        multi_labels: &[(usize, LabelStyle, MultiLabel<'_>)],
+    ) -> Option<(String, Vec<SingleLabel<'label>>)> {
+        use unicode_width::UnicodeWidthStr;
+
+        let terminal_width = self.config.terminal_width?;
+        if multi_labels.iter().any(|(_, _, label)| match label {
+            MultiLabel::Top(..) | MultiLabel::Bottom(..) => true,
+            MultiLabel::Left => false,
+        }) {
+            return None;
+        }
+
+        // The width of everything to the left of the source: the line number,
+        // border, inner gutter, and padding.
+        let border_width = if self.config.draw_border { 1 } else { 0 };
+        let gutter_width = outer_padding
+            + self.config.gutter_padding
+            + border_width
+            + 2 * num_multi_labels
+            + self.config.source_left_pad;
+        let available_width = terminal_width.saturating_sub(gutter_width);
+        let metrics = self.char_metrics(source.char_indices()).collect::<Vec<_>>();
+        let total_width = metrics.iter().map(|(m, _)| m.unicode_width).sum::<usize>();
+        if total_width <= available_width {
+            return None;
+        }
+
+        // Leave room for an ellipsis on either side of the window.
+        let ellipsis = self.chars().source_ellipsis.as_str();
+        let window_width = std::cmp::max(available_width.saturating_sub(2 * ellipsis.width()), 1);
+        let column_of = |byte_index: usize| {
+            metrics
+                .iter()
+                .take_while(|(m, _)| m.byte_index < byte_index)
+                .map(|(m, _)| m.unicode_width)
+                .sum::<usize>()
+        };
+
+        // Centre the window on the labels, falling back to the start of the
+        // labels if they are too wide to fit.
+        let focus_start = single_labels
+            .iter()
+            .map(|(_, range, _)| column_of(range.start))
+            .min();
+        let focus_end = single_labels
+            .iter()
+            .map(|(_, range, _)| column_of(range.end))
+            .max();
+        let start_column = match (focus_start, focus_end) {
+            (Some(focus_start), Some(focus_end)) if focus_end - focus_start < window_width => {
+                let slack = window_width - (focus_end - focus_start);
+                let start_column = focus_start.saturating_sub(slack / 2);
+                std::cmp::min(start_column, total_width.saturating_sub(window_width))
+            }
+            (Some(focus_start), _) => focus_start,
+            (None, _) => 0,
+        };
+        let end_column = start_column + window_width;
+
+        // Build the windowed source, recording where each visible character
+        // of the original source ends up.
+        let mut windowed = String::new();
+        let mut visible = Vec::new();
+        let mut column = 0;
+        for (metrics, ch) in &metrics {
+            let next_column = column + metrics.unicode_width;
+            if column >= start_column && next_column <= end_column {
+                if visible.is_empty() && column > 0 {
+                    windowed.push_str(ellipsis);
+                }
+                visible.push((metrics.byte_index, windowed.len()));
+                match ch {
+                    '\t' => (0..metrics.unicode_width).for_each(|_| windowed.push(' ')),
+                    ch => windowed.push(*ch),
+                }
+            }
+            column = next_column;
+        }
+        let (visible_start, windowed_start) = *visible.first()?;
+        let visible_end = visible.last().map_or(visible_start, |(byte_index, _)| {
+            byte_index
+                + source[*byte_index..]
+                    .chars()
+                    .next()
+                    .map_or(0, char::len_utf8)
+        });
+        let windowed_end = windowed.len();
+        if visible_end < source.len() {
+            windowed.push_str(ellipsis);
+        }
+
+        let map_index = |byte_index: usize| {
+            if byte_index <= visible_start {
+                windowed_start
+            } else if byte_index >= visible_end {
+                windowed_end
+            } else {
+                visible
+                    .iter()
+                    .find(|(visible_index, _)| *visible_index >= byte_index)
+                    .map_or(windowed_end, |(_, windowed_index)| *windowed_index)
+            }
+        };
+        let single_labels = single_labels
+            .iter()
+            .map(|(label_style, range, message)| {
+                let start = map_index(range.start);
+                let end = std::cmp::max(map_index(range.end), start + 1);
+                (*label_style, start..end, *message)
+            })
+            .collect();
+
+        Some((windowed, single_labels))
+    }
+
     /// An empty source line, for providing additional whitespace to source snippets.
     ///
     /// ```text
@@ -606,7 +1311,7 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         multi_labels: &[(usize, LabelStyle, MultiLabel<'_>)],
     ) -> Result<(), Error> {
         self.outer_gutter(outer_padding)?;
-        self.border_left()?;
+        self.border_left(severity)?;
         self.inner_gutter(severity, num_multi_labels, multi_labels)?;
         writeln!(self)?;
         Ok(())
@@ -625,31 +1330,43 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         multi_labels: &[(usize, LabelStyle, MultiLabel<'_>)],
     ) -> Result<(), Error> {
         self.outer_gutter(outer_padding)?;
-        self.border_left_break()?;
+        self.border_left_break(severity)?;
         self.inner_gutter(severity, num_multi_labels, multi_labels)?;
         writeln!(self)?;
         Ok(())
     }
 
-    /// Additional notes.
+    /// Additional notes, optionally with their own severity.
     ///
     /// ```text
     /// = expected type `Int`
     ///      found type `String`
+    /// = help: try using a conversion method
     /// ```
     pub fn render_snippet_note(
         &mut self,
         outer_padding: usize,
+        severity: Option<Severity>,
         message: &str,
     ) -> Result<(), Error> {
         for (note_line_index, line) in message.lines().enumerate() {
             self.outer_gutter(outer_padding)?;
-            match note_line_index {
-                0 => {
+            match (note_line_index, severity) {
+                (0, None) => {
                     self.set_color(&self.styles().note_bullet)?;
                     write!(self, "{}", self.chars().note_bullet)?;
                     self.reset()?;
                 }
+                (0, Some(severity)) => {
+                    self.set_color(self.styles().header(severity))?;
+                    write!(
+                        self,
+                        "{} {}:",
+                        self.chars().note_bullet,
+                        self.config.severity_names.name(severity),
+                    )?;
+                    self.reset()?;
+                }
                 _ => write!(self, " ")?,
             }
             // Write line of message
@@ -659,26 +1376,62 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         Ok(())
     }
 
+    /// The tool that produced the diagnostic.
+    ///
+    /// ```text
+    /// = generated by clippy
+    /// ```
+    pub fn render_snippet_provenance(
+        &mut self,
+        outer_padding: usize,
+        source: &str,
+    ) -> Result<(), Error> {
+        self.outer_gutter(outer_padding)?;
+        self.set_color(&self.styles().provenance)?;
+        write!(self, "{} generated by {}", self.chars().note_bullet, source)?;
+        self.reset()?;
+        writeln!(self)?;
+        Ok(())
+    }
+
+    /// Additional notes without a bullet, aligned with the text of the other
+    /// notes.
+    ///
+    /// ```text
+    /// = expected type `Int`
+    ///   See the chapter on conversions for more details.
+    /// ```
+    pub fn render_snippet_plain_note(
+        &mut self,
+        outer_padding: usize,
+        message: &str,
+    ) -> Result<(), Error> {
+        for line in message.lines() {
+            self.outer_gutter(outer_padding)?;
+            // Write a space in place of the bullet
+            writeln!(self, "  {}", line)?;
+        }
+
+        Ok(())
+    }
+
     /// Adds tab-stop aware unicode-width computations to an iterator over
     /// character indices. Assumes that the character indices begin at the start
     /// of the line.
-    fn char_metrics(
+    fn char_metrics<'chars>(
         &self,
-        char_indices: impl Iterator<Item = (usize, char)>,
-    ) -> impl Iterator<Item = (Metrics, char)> {
-        use unicode_width::UnicodeWidthChar;
-
-        let tab_width = self.config.tab_width;
+        char_indices: impl 'chars + Iterator<Item = (usize, char)>,
+    ) -> impl 'chars + Iterator<Item = (Metrics, char)>
+    where
+        'config: 'chars,
+    {
+        let config = self.config;
         let mut unicode_column = 0;
 
         char_indices.map(move |(byte_index, ch)| {
             let metrics = Metrics {
                 byte_index,
-                unicode_width: match (ch, tab_width) {
-                    ('\t', 0) => 0, // Guard divide-by-zero
-                    ('\t', _) => tab_width - (unicode_column % tab_width),
-                    (ch, _) => ch.width().unwrap_or(0),
-                },
+                unicode_width: config.char_display_width(unicode_column, ch),
             };
             unicode_column += metrics.unicode_width;
 
@@ -686,21 +1439,50 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         })
     }
 
+    /// The message of a label, prefixed with the severity of the diagnostic
+    /// if `Config::inline_label_severity` is set.
+    ///
+    /// ```text
+    /// error: expected `Int` but found `String`
+    /// ```
+    fn label_message(&mut self, severity: Severity, message: &str) -> Result<(), Error> {
+        if self.config.inline_label_severity {
+            write!(self, "{}: ", self.config.severity_names.name(severity))?;
+        }
+        write!(self, "{}", message)?;
+        Ok(())
+    }
+
     /// Location focus.
     fn snippet_locus(&mut self, locus: &Locus) -> Result<(), Error> {
-        write!(
-            self,
-            "{name}:{line_number}:{column_number}",
-            name = locus.name,
-            line_number = locus.location.line_number,
-            column_number = locus.location.column_number,
-        )?;
+        match self.config.locus_link {
+            // Link to the file with an OSC 8 hyperlink
+            Some(locus_link) if self.supports_color() => write!(
+                self,
+                "\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\",
+                locus_link(&locus.full_name),
+                locus.name,
+            )?,
+            Some(_) | None => write!(self, "{}", locus.name)?,
+        }
+
+        let line_number = locus.location.line_number;
+        let column_number = locus.location.column_number;
+        match self.config.locus_format {
+            LocusFormat::Colon => write!(self, ":{}:{}", line_number, column_number)?,
+            LocusFormat::ColonTrailing => write!(self, ":{}:{}:", line_number, column_number)?,
+            LocusFormat::Parens => write!(self, "({},{})", line_number, column_number)?,
+        }
+        if self.config.show_byte_offset {
+            write!(self, " [offset {}]", locus.byte_index)?;
+        }
         Ok(())
     }
 
     /// The outer gutter of a source line.
     fn outer_gutter(&mut self, outer_padding: usize) -> Result<(), Error> {
-        write!(self, "{space: >width$} ", space = "", width = outer_padding)?;
+        write!(self, "{space: >width$}", space = "", width = outer_padding)?;
+        self.gutter_padding()?;
         Ok(())
     }
 
@@ -718,21 +1500,33 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
             width = outer_padding,
         )?;
         self.reset()?;
-        write!(self, " ")?;
+        self.gutter_padding()?;
+        Ok(())
+    }
+
+    /// The padding between the outer gutter and the left-hand border.
+    fn gutter_padding(&mut self) -> Result<(), Error> {
+        (0..self.config.gutter_padding).try_for_each(|_| write!(self, " "))?;
         Ok(())
     }
 
     /// The left-hand border of a source line.
-    fn border_left(&mut self) -> Result<(), Error> {
-        self.set_color(&self.styles().source_border)?;
+    fn border_left(&mut self, severity: Severity) -> Result<(), Error> {
+        if !self.config.draw_border {
+            return Ok(());
+        }
+        self.set_color(self.styles().border(severity))?;
         write!(self, "{}", self.chars().source_border_left)?;
         self.reset()?;
         Ok(())
     }
 
     /// The broken left-hand border of a source line.
-    fn border_left_break(&mut self) -> Result<(), Error> {
-        self.set_color(&self.styles().source_border)?;
+    fn border_left_break(&mut self, severity: Severity) -> Result<(), Error> {
+        if !self.config.draw_border {
+            return Ok(());
+        }
+        self.set_color(self.styles().border(severity))?;
         write!(self, "{}", self.chars().source_border_left_break)?;
         self.reset()?;
         Ok(())
@@ -757,7 +1551,7 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
             let mut spaces = match label_style {
                 None => 0..metrics.unicode_width,
                 Some(label_style) => {
-                    self.set_color(self.styles().label(severity, label_style))?;
+                    self.set_color(self.label_color(severity, label_style))?;
                     write!(self, "{}", self.chars().pointer_left)?;
                     self.reset()?;
                     1..metrics.unicode_width
@@ -787,12 +1581,12 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
             None => write!(self, " ")?,
             // Continue an underline horizontally
             Some(label_style) => {
-                self.set_color(self.styles().label(severity, label_style))?;
+                self.set_color(self.label_color(severity, label_style))?;
                 write!(self, "{}", self.chars().multi_top)?;
                 self.reset()?;
             }
         }
-        self.set_color(self.styles().label(severity, label_style))?;
+        self.set_color(self.label_color(severity, label_style))?;
         write!(self, "{}", self.chars().multi_left)?;
         self.reset()?;
         Ok(())
@@ -809,7 +1603,7 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         label_style: LabelStyle,
     ) -> Result<(), Error> {
         write!(self, " ")?;
-        self.set_color(self.styles().label(severity, label_style))?;
+        self.set_color(self.label_color(severity, label_style))?;
         write!(self, "{}", self.chars().multi_top_left)?;
         self.reset()?;
         Ok(())
@@ -826,7 +1620,7 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         label_style: LabelStyle,
     ) -> Result<(), Error> {
         write!(self, " ")?;
-        self.set_color(self.styles().label(severity, label_style))?;
+        self.set_color(self.label_color(severity, label_style))?;
         write!(self, "{}", self.chars().multi_bottom_left)?;
         self.reset()?;
         Ok(())
@@ -844,16 +1638,18 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         source: &str,
         start: usize,
     ) -> Result<(), Error> {
-        self.set_color(self.styles().label(severity, label_style))?;
+        self.set_color(self.label_color(severity, label_style))?;
 
-        for (metrics, _) in self
+        // FIXME: improve rendering of carets between character boundaries
+        let width = self
             .char_metrics(source.char_indices())
             .take_while(|(metrics, _)| metrics.byte_index < start + 1)
-        {
-            // FIXME: improve rendering of carets between character boundaries
-            (0..metrics.unicode_width)
-                .try_for_each(|_| write!(self, "{}", self.chars().multi_top))?;
-        }
+            .map(|(metrics, _)| metrics.unicode_width)
+            .sum::<usize>();
+        // The padding before the source includes the column of the character
+        // we are pointing at, so we leave that out here.
+        (0..(width + self.config.source_left_pad).saturating_sub(1))
+            .try_for_each(|_| write!(self, "{}", self.chars().multi_top))?;
 
         let caret_start = match label_style {
             LabelStyle::Primary => self.config.chars.multi_primary_caret_start,
@@ -878,16 +1674,18 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         start: usize,
         message: &str,
     ) -> Result<(), Error> {
-        self.set_color(self.styles().label(severity, label_style))?;
+        self.set_color(self.label_color(severity, label_style))?;
 
-        for (metrics, _) in self
+        // FIXME: improve rendering of carets between character boundaries
+        let width = self
             .char_metrics(source.char_indices())
             .take_while(|(metrics, _)| metrics.byte_index < start)
-        {
-            // FIXME: improve rendering of carets between character boundaries
-            (0..metrics.unicode_width)
-                .try_for_each(|_| write!(self, "{}", self.chars().multi_bottom))?;
-        }
+            .map(|(metrics, _)| metrics.unicode_width)
+            .sum::<usize>();
+        // The caret points at the character before `start`, so we leave its
+        // column out here.
+        (0..(width + self.config.source_left_pad).saturating_sub(1))
+            .try_for_each(|_| write!(self, "{}", self.chars().multi_bottom))?;
 
         let caret_end = match label_style {
             LabelStyle::Primary => self.config.chars.multi_primary_caret_start,
@@ -895,13 +1693,43 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         };
         write!(self, "{}", caret_end)?;
         if !message.is_empty() {
-            write!(self, " {}", message)?;
+            write!(self, " ")?;
+            self.label_message(severity, message)?;
         }
         self.reset()?;
         writeln!(self)?;
         Ok(())
     }
 
+    /// The byte index that the column guide of a line of carets should extend
+    /// to, or `0` if the line should not have a guide.
+    fn column_guide_end(&self, source: &str, single_labels: &[SingleLabel<'_>]) -> usize {
+        if !self.config.show_column_guide {
+            return 0;
+        }
+        let start = match single_labels.iter().map(|(_, range, _)| range.start).min() {
+            Some(start) => start,
+            None => return 0,
+        };
+
+        let column = self
+            .char_metrics(source.char_indices())
+            .take_while(|(metrics, _)| metrics.byte_index < start)
+            .map(|(metrics, _)| metrics.unicode_width)
+            .sum::<usize>();
+        if column > self.config.column_guide_threshold {
+            start
+        } else {
+            0
+        }
+    }
+
+    /// The padding between the inner gutter and the source.
+    fn source_left_pad(&mut self) -> Result<(), Error> {
+        (0..self.config.source_left_pad).try_for_each(|_| write!(self, " "))?;
+        Ok(())
+    }
+
     /// Writes an empty gutter space, or continues an underline horizontally.
     fn inner_gutter_column(
         &mut self,
@@ -911,7 +1739,7 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         match underline {
             None => self.inner_gutter_space(),
             Some((label_style, vertical_bound)) => {
-                self.set_color(self.styles().label(severity, label_style))?;
+                self.set_color(self.label_color(severity, label_style))?;
                 let ch = match vertical_bound {
                     VerticalBound::Top => self.config.chars.multi_top,
                     VerticalBound::Bottom => self.config.chars.multi_bottom,
@@ -959,10 +1787,17 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
 
 impl<'writer, 'config> Write for Renderer<'writer, 'config> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.writer.write(buf)
+        self.apply_color()?;
+        let len = self.writer.write(buf)?;
+        if let Some(source_lines) = &mut self.source_lines {
+            let rows = buf[..len].iter().filter(|&&b| b == b'\n').count();
+            source_lines.resize(source_lines.len() + rows, self.source_line);
+        }
+        Ok(len)
     }
 
     fn flush(&mut self) -> io::Result<()> {
+        self.apply_color()?;
         self.writer.flush()
     }
 }
@@ -973,11 +1808,14 @@ impl<'writer, 'config> WriteColor for Renderer<'writer, 'config> {
     }
 
     fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
-        self.writer.set_color(spec)
+        self.pending_color = Some(spec.clone());
+        Ok(())
     }
 
     fn reset(&mut self) -> io::Result<()> {
-        self.writer.reset()
+        self.pending_color = None;
+        self.pending_reset = true;
+        Ok(())
     }
 
     fn is_synchronous(&self) -> bool {
@@ -985,6 +1823,14 @@ impl<'writer, 'config> WriteColor for Renderer<'writer, 'config> {
     }
 }
 
+impl<'writer, 'config> Drop for Renderer<'writer, 'config> {
+    /// Pass on a pending change of color, like a final reset. Errors are
+    /// ignored, as with [`std::io::BufWriter`].
+    fn drop(&mut self) {
+        let _ = self.apply_color();
+    }
+}
+
 struct Metrics {
     byte_index: usize,
     unicode_width: usize,
@@ -1,9 +1,24 @@
+use std::collections::BTreeMap;
 use std::ops::Range;
 
-use crate::diagnostic::{Diagnostic, LabelStyle};
+use crate::diagnostic::{Diagnostic, LabelStyle, Severity};
 use crate::files::{Error, Files, Location};
 use crate::term::renderer::{Locus, MultiLabel, Renderer, SingleLabel};
-use crate::term::Config;
+use crate::term::{CaretRowOrder, Config, FileSortOrder};
+
+/// The name of a file to show in a locus, which is its short name if
+/// [`Config::compact_locus`] is set.
+fn locus_name<'files, F: Files<'files>>(
+    files: &'files F,
+    compact_locus: bool,
+    file_id: F::FileId,
+) -> Result<String, Error> {
+    if compact_locus {
+        Ok(files.short_name(file_id)?.to_string())
+    } else {
+        Ok(files.name(file_id)?.to_string())
+    }
+}
 
 /// Calculate the number of decimal digits in `n`.
 // TODO: simplify after https://github.com/rust-lang/rust/issues/70887 resolves
@@ -13,10 +28,77 @@ fn count_digits(n: usize) -> usize {
     (n.saturating_add(1) as f64).log10().ceil() as usize
 }
 
+/// The labels of a diagnostic that refer to a source file.
+struct LabeledFile<'diagnostic, FileId> {
+    file_id: FileId,
+    // The index of the first label that refers to this file.
+    label_index: usize,
+    start: usize,
+    name: String,
+    full_name: String,
+    location: Location,
+    num_multi_labels: usize,
+    lines: BTreeMap<usize, Line<'diagnostic>>,
+    max_label_style: LabelStyle,
+}
+
+impl<'diagnostic, FileId> LabeledFile<'diagnostic, FileId> {
+    fn get_or_insert_line(
+        &mut self,
+        line_index: usize,
+        line_range: Range<usize>,
+        line_number: usize,
+    ) -> &mut Line<'diagnostic> {
+        self.lines.entry(line_index).or_insert_with(|| Line {
+            range: line_range,
+            number: line_number,
+            single_labels: vec![],
+            single_label_priorities: vec![],
+            multi_labels: vec![],
+            // This has to be false by default so we know if it must be rendered by another condition already.
+            must_render: false,
+        })
+    }
+}
+
+struct Line<'diagnostic> {
+    number: usize,
+    range: std::ops::Range<usize>,
+    // TODO: How do we reuse these allocations?
+    single_labels: Vec<SingleLabel<'diagnostic>>,
+    // The priorities of the single labels, in the same order.
+    single_label_priorities: Vec<i32>,
+    multi_labels: Vec<(usize, LabelStyle, MultiLabel<'diagnostic>)>,
+    must_render: bool,
+}
+
+struct BinaryFile<'diagnostic, FileId> {
+    file_id: FileId,
+    name: String,
+    single_labels: Vec<SingleLabel<'diagnostic>>,
+}
+
+struct SuggestedLine {
+    number: usize,
+    source: String,
+    // The range of the line covered by the replacement, if any.
+    range: Option<Range<usize>>,
+}
+
+/// The source lines of a diagnostic, grouped by file, ready for rendering.
+struct Layout<'diagnostic, FileId> {
+    labeled_files: Vec<LabeledFile<'diagnostic, FileId>>,
+    binary_files: Vec<BinaryFile<'diagnostic, FileId>>,
+    suggestions: Vec<(&'diagnostic str, Vec<SuggestedLine>)>,
+    outer_padding: usize,
+}
+
 /// Output a richly formatted diagnostic, with source code previews.
 pub struct RichDiagnostic<'diagnostic, 'config, FileId> {
     diagnostic: &'diagnostic Diagnostic<FileId>,
     config: &'config Config,
+    show_header: bool,
+    min_outer_padding: usize,
 }
 
 impl<'diagnostic, 'config, FileId> RichDiagnostic<'diagnostic, 'config, FileId>
@@ -27,7 +109,43 @@ where
         diagnostic: &'diagnostic Diagnostic<FileId>,
         config: &'config Config,
     ) -> RichDiagnostic<'diagnostic, 'config, FileId> {
-        RichDiagnostic { diagnostic, config }
+        RichDiagnostic {
+            diagnostic,
+            config,
+            show_header: true,
+            min_outer_padding: 0,
+        }
+    }
+
+    /// Make the gutter at least as wide as the given number of columns, for
+    /// aligning the borders of several diagnostics.
+    pub fn with_min_outer_padding(
+        self,
+        min_outer_padding: usize,
+    ) -> RichDiagnostic<'diagnostic, 'config, FileId> {
+        RichDiagnostic {
+            min_outer_padding,
+            ..self
+        }
+    }
+
+    /// Leave out the header, rendering only the source snippets and notes.
+    pub fn without_header(self) -> RichDiagnostic<'diagnostic, 'config, FileId> {
+        RichDiagnostic {
+            show_header: false,
+            ..self
+        }
+    }
+
+    /// The width of the line numbers in the gutter of the rendered diagnostic.
+    pub fn outer_padding<'files>(
+        &self,
+        files: &'files impl Files<'files, FileId = FileId>,
+    ) -> Result<usize, Error>
+    where
+        FileId: 'files,
+    {
+        Ok(self.layout(files)?.outer_padding)
     }
 
     pub fn render<'files>(
@@ -38,58 +156,303 @@ where
     where
         FileId: 'files,
     {
-        use std::collections::BTreeMap;
-
-        struct LabeledFile<'diagnostic, FileId> {
-            file_id: FileId,
-            start: usize,
-            name: String,
-            location: Location,
-            num_multi_labels: usize,
-            lines: BTreeMap<usize, Line<'diagnostic>>,
-            max_label_style: LabelStyle,
+        let Layout {
+            labeled_files,
+            binary_files,
+            suggestions,
+            outer_padding,
+        } = self.layout(files)?;
+
+        // Header and message
+        //
+        // ```text
+        // error[E0001]: unexpected type in `+` application
+        // ```
+        if self.show_header {
+            renderer.render_header(
+                None,
+                self.diagnostic.severity,
+                self.diagnostic.code.as_deref(),
+                self.diagnostic.message.as_str(),
+            )?;
         }
 
-        impl<'diagnostic, FileId> LabeledFile<'diagnostic, FileId> {
-            fn get_or_insert_line(
-                &mut self,
-                line_index: usize,
-                line_range: Range<usize>,
-                line_number: usize,
-            ) -> &mut Line<'diagnostic> {
-                self.lines.entry(line_index).or_insert_with(|| Line {
-                    range: line_range,
-                    number: line_number,
-                    single_labels: vec![],
-                    multi_labels: vec![],
-                    // This has to be false by default so we know if it must be rendered by another condition already.
-                    must_render: false,
-                })
+        // Source snippets
+        //
+        // ```text
+        //   ┌─ test:2:9
+        //   │
+        // 2 │ (+ test "")
+        //   │         ^^ expected `Int` but found `String`
+        //   │
+        // ```
+        let mut is_first_snippet = true;
+        let mut labeled_files = labeled_files.into_iter().peekable();
+        while let Some(labeled_file) = labeled_files.next() {
+            let source = files.source(labeled_file.file_id)?;
+            let source = source.as_ref();
+
+            // Top left border and locus.
+            //
+            // ```text
+            // ┌─ test:2:9
+            // ```
+            if !labeled_file.lines.is_empty() {
+                if self.config.show_locus {
+                    renderer.render_snippet_start(
+                        outer_padding,
+                        self.diagnostic.severity,
+                        &Locus {
+                            name: labeled_file.name,
+                            full_name: labeled_file.full_name,
+                            location: labeled_file.location,
+                            byte_index: labeled_file.start,
+                        },
+                    )?;
+                    renderer.render_snippet_empty(
+                        outer_padding,
+                        self.diagnostic.severity,
+                        labeled_file.num_multi_labels,
+                        &[],
+                    )?;
+                } else if !is_first_snippet {
+                    // Separate the snippet from the previous one with a source
+                    // break, in place of the locus.
+                    //
+                    // ```text
+                    // ·
+                    // ```
+                    renderer.render_snippet_break(
+                        outer_padding,
+                        self.diagnostic.severity,
+                        labeled_file.num_multi_labels,
+                        &[],
+                    )?;
+                }
+                is_first_snippet = false;
+            }
+
+            let mut lines = labeled_file
+                .lines
+                .iter()
+                .filter(|(_, line)| line.must_render)
+                .peekable();
+
+            while let Some((line_index, line)) = lines.next() {
+                renderer.set_source_line(Some((labeled_file.label_index, *line_index)));
+                renderer.render_snippet_source(
+                    outer_padding,
+                    line.number,
+                    &source[line.range.clone()],
+                    self.diagnostic.severity,
+                    &line.single_labels,
+                    labeled_file.num_multi_labels,
+                    &line.multi_labels,
+                )?;
+                renderer.set_source_line(None);
+
+                // Check to see if we need to render any intermediate stuff
+                // before rendering the next line.
+                if let Some((next_line_index, next_line)) = lines.peek() {
+                    match next_line_index.checked_sub(*line_index) {
+                        // Consecutive lines
+                        Some(1) => {}
+                        // One line between the current line and the next line
+                        Some(2) => {
+                            // Write a source line
+                            let file_id = labeled_file.file_id;
+
+                            // This line was not intended to be rendered initially.
+                            // To render the line right, we have to get back the original labels.
+                            let labels = labeled_file
+                                .lines
+                                .get(&(line_index + 1))
+                                .map_or(&[][..], |line| &line.multi_labels[..]);
+
+                            renderer
+                                .set_source_line(Some((labeled_file.label_index, line_index + 1)));
+                            renderer.render_snippet_source(
+                                outer_padding,
+                                self.gutter_line_number(files, file_id, line_index + 1)?,
+                                &source[files.line_range(file_id, line_index + 1)?],
+                                self.diagnostic.severity,
+                                &[],
+                                labeled_file.num_multi_labels,
+                                labels,
+                            )?;
+                            renderer.set_source_line(None);
+                        }
+                        // More than one line between the current line and the next line.
+                        Some(_) | None => {
+                            // Source break
+                            //
+                            // ```text
+                            // ·
+                            // ```
+                            renderer.render_snippet_break(
+                                outer_padding,
+                                self.diagnostic.severity,
+                                labeled_file.num_multi_labels,
+                                &next_line.multi_labels,
+                            )?;
+                        }
+                    }
+                }
+            }
+
+            self.render_snippet_end(
+                renderer,
+                outer_padding,
+                labeled_file.num_multi_labels,
+                labeled_files.peek().is_none() && binary_files.is_empty(),
+            )?;
+        }
+
+        // Binary files
+        //
+        // ```text
+        //   ┌─ logo.png
+        //   │
+        //   │ <binary file, 16 bytes>
+        //   │ ^ bytes 0..8: expected a PNG signature
+        // ```
+        let mut binary_files = binary_files.into_iter().peekable();
+        while let Some(binary_file) = binary_files.next() {
+            let len = files.source(binary_file.file_id)?.as_ref().len();
+
+            if self.config.show_locus {
+                renderer.render_binary_snippet_start(
+                    outer_padding,
+                    self.diagnostic.severity,
+                    &binary_file.name,
+                )?;
+                renderer.render_snippet_empty(outer_padding, self.diagnostic.severity, 0, &[])?;
+            } else if !is_first_snippet {
+                renderer.render_snippet_break(outer_padding, self.diagnostic.severity, 0, &[])?;
+            }
+            is_first_snippet = false;
+            renderer.render_binary_snippet_source(
+                outer_padding,
+                self.diagnostic.severity,
+                len,
+                &binary_file.single_labels,
+            )?;
+
+            self.render_snippet_end(renderer, outer_padding, 0, binary_files.peek().is_none())?;
+        }
+
+        // Additional notes
+        //
+        // ```text
+        // = expected type `Int`
+        //      found type `String`
+        // ```
+        for note in &self.diagnostic.notes {
+            renderer.render_snippet_note(outer_padding, None, note)?;
+        }
+        for note in &self.diagnostic.rich_notes {
+            if note.plain {
+                renderer.render_snippet_plain_note(outer_padding, &note.message)?;
+            } else {
+                renderer.render_snippet_note(outer_padding, Some(note.severity), &note.message)?;
             }
         }
 
-        struct Line<'diagnostic> {
-            number: usize,
-            range: std::ops::Range<usize>,
-            // TODO: How do we reuse these allocations?
-            single_labels: Vec<SingleLabel<'diagnostic>>,
-            multi_labels: Vec<(usize, LabelStyle, MultiLabel<'diagnostic>)>,
-            must_render: bool,
+        // Provenance
+        //
+        // ```text
+        // = generated by clippy
+        // ```
+        if let Some(source) = self.provenance() {
+            renderer.render_snippet_provenance(outer_padding, source)?;
         }
 
+        // Suggested edits
+        //
+        // ```text
+        // = help: consider borrowing here
+        //   │
+        // 2 │ (+ test &x)
+        //   │         ^
+        // ```
+        for (message, lines) in &suggestions {
+            renderer.render_snippet_note(outer_padding, Some(Severity::Help), message)?;
+            renderer.render_snippet_empty(outer_padding, Severity::Help, 0, &[])?;
+            for line in lines {
+                let single_labels = match &line.range {
+                    Some(range) => vec![(LabelStyle::Primary, range.clone(), "")],
+                    None => vec![],
+                };
+                renderer.render_snippet_source(
+                    outer_padding,
+                    line.number,
+                    &line.source,
+                    Severity::Help,
+                    &single_labels,
+                    0,
+                    &[],
+                )?;
+            }
+        }
+        renderer.render_empty()
+    }
+
+    /// Group the labels of the diagnostic by file and line, and work out the
+    /// outer padding needed to fit their line numbers.
+    fn layout<'files>(
+        &self,
+        files: &'files impl Files<'files, FileId = FileId>,
+    ) -> Result<Layout<'diagnostic, FileId>, Error>
+    where
+        FileId: 'files,
+    {
         // TODO: Make this data structure external, to allow for allocation reuse
         let mut labeled_files = Vec::<LabeledFile<'_, _>>::new();
+        // Binary files are rendered after the other files, without source snippets.
+        let mut binary_files = Vec::<BinaryFile<'_, _>>::new();
         // Keep track of the outer padding to use when rendering the
         // snippets of source code.
         let mut outer_padding = 0;
 
         // Group labels by file
-        for label in &self.diagnostic.labels {
+        for (label_index, label) in self.diagnostic.labels.iter().enumerate() {
+            if files.is_binary(label.file_id) {
+                let binary_file = match binary_files
+                    .iter_mut()
+                    .find(|binary_file| label.file_id == binary_file.file_id)
+                {
+                    Some(binary_file) => binary_file,
+                    None => {
+                        binary_files.push(BinaryFile {
+                            file_id: label.file_id,
+                            name: locus_name(files, self.config.compact_locus, label.file_id)?,
+                            single_labels: vec![],
+                        });
+                        binary_files
+                            .last_mut()
+                            .expect("just pushed an element that disappeared")
+                    }
+                };
+
+                // Keep the labels sorted by the range of bytes that they cover.
+                let index = binary_file
+                    .single_labels
+                    .iter()
+                    .position(|(_, range, _)| range.start > label.range.start)
+                    .unwrap_or(binary_file.single_labels.len());
+                binary_file.single_labels.insert(
+                    index,
+                    (label.style, label.range.clone(), label.message.as_str()),
+                );
+                continue;
+            }
+
             let start_line_index = files.line_index(label.file_id, label.range.start)?;
-            let start_line_number = files.line_number(label.file_id, start_line_index)?;
+            let start_line_number =
+                self.gutter_line_number(files, label.file_id, start_line_index)?;
             let start_line_range = files.line_range(label.file_id, start_line_index)?;
             let end_line_index = files.line_index(label.file_id, label.range.end)?;
-            let end_line_number = files.line_number(label.file_id, end_line_index)?;
+            let end_line_number = self.gutter_line_number(files, label.file_id, end_line_index)?;
             let end_line_range = files.line_range(label.file_id, end_line_index)?;
 
             outer_padding = std::cmp::max(outer_padding, count_digits(start_line_number));
@@ -119,8 +482,10 @@ where
                     // no other diagnostic referenced this file yet
                     labeled_files.push(LabeledFile {
                         file_id: label.file_id,
+                        label_index,
                         start: label.range.start,
-                        name: files.name(label.file_id)?.to_string(),
+                        name: locus_name(files, self.config.compact_locus, label.file_id)?,
+                        full_name: files.name(label.file_id)?.to_string(),
                         location: files.location(label.file_id, label.range.start)?,
                         num_multi_labels: 0,
                         lines: BTreeMap::new(),
@@ -146,8 +511,10 @@ where
                 };
 
                 if let Ok(range) = files.line_range(label.file_id, index) {
-                    let line =
-                        labeled_file.get_or_insert_line(index, range, start_line_number - offset);
+                    let line_number = self.gutter_line_number(files, label.file_id, index)?;
+                    outer_padding = std::cmp::max(outer_padding, count_digits(line_number));
+
+                    let line = labeled_file.get_or_insert_line(index, range, line_number);
                     line.must_render = true;
                 } else {
                     break;
@@ -162,8 +529,10 @@ where
                     .expect("line index too big");
 
                 if let Ok(range) = files.line_range(label.file_id, index) {
-                    let line =
-                        labeled_file.get_or_insert_line(index, range, end_line_number + offset);
+                    let line_number = self.gutter_line_number(files, label.file_id, index)?;
+                    outer_padding = std::cmp::max(outer_padding, count_digits(line_number));
+
+                    let line = labeled_file.get_or_insert_line(index, range, line_number);
                     line.must_render = true;
                 } else {
                     break;
@@ -191,19 +560,39 @@ where
 
                 // Ensure that the single line labels are lexicographically
                 // sorted by the range of source code that they cover.
-                let index = match line.single_labels.binary_search_by(|(_, range, _)| {
-                    // `Range<usize>` doesn't implement `Ord`, so convert to `(usize, usize)`
-                    // to piggyback off its lexicographic comparison implementation.
-                    (range.start, range.end).cmp(&(label_start, label_end))
-                }) {
-                    // If the ranges are the same, order the labels in reverse
-                    // to how they were originally specified in the diagnostic.
-                    // This helps with printing in the renderer.
-                    Ok(index) | Err(index) => index,
+                //
+                // `Range<usize>` doesn't implement `Ord`, so convert to a tuple
+                // to piggyback off its lexicographic comparison implementation.
+                let caret_row_order = self.config.caret_row_order;
+                let sort_key = |style: &LabelStyle, range: &Range<usize>, priority: i32| {
+                    let is_primary = match caret_row_order {
+                        CaretRowOrder::AsSpecified => false,
+                        // The renderer prints the messages in reverse, so
+                        // sort the primary labels after the secondary labels
+                        // that start at the same position.
+                        CaretRowOrder::PrimaryFirst => *style == LabelStyle::Primary,
+                    };
+                    // Likewise, sort labels with a higher priority after
+                    // those with a lower priority that cover the same range.
+                    (range.start, is_primary, range.end, priority)
                 };
+                let label_key = sort_key(&label.style, &(label_start..label_end), label.priority);
+
+                // If the keys are the same, order the labels in reverse to how
+                // they were originally specified in the diagnostic. This helps
+                // with printing in the renderer.
+                let index = line
+                    .single_labels
+                    .iter()
+                    .zip(&line.single_label_priorities)
+                    .position(|((style, range, _), priority)| {
+                        sort_key(style, range, *priority) >= label_key
+                    })
+                    .unwrap_or(line.single_labels.len());
 
                 line.single_labels
                     .insert(index, (label.style, label_start..label_end, &label.message));
+                line.single_label_priorities.insert(index, label.priority);
 
                 // If this line is not rendered, the SingleLabel is not visible.
                 line.must_render = true;
@@ -248,9 +637,18 @@ where
                 // 6 │ │     0 _ => "Fizz"
                 // 7 │ │     _ 0 => "Buzz"
                 // ```
+                let start_context_lines = self
+                    .config
+                    .multi_line_context
+                    .unwrap_or(self.config.start_context_lines);
+                let end_context_lines = self
+                    .config
+                    .multi_line_context
+                    .unwrap_or(self.config.end_context_lines);
+
                 for line_index in (start_line_index + 1)..end_line_index {
                     let line_range = files.line_range(label.file_id, line_index)?;
-                    let line_number = files.line_number(label.file_id, line_index)?;
+                    let line_number = self.gutter_line_number(files, label.file_id, line_index)?;
 
                     outer_padding = std::cmp::max(outer_padding, count_digits(line_number));
 
@@ -262,10 +660,10 @@ where
                     // The line should be rendered to match the configuration of how much context to show.
                     line.must_render |=
                         // Is this line part of the context after the start of the label?
-                        line_index - start_line_index <= self.config.start_context_lines
+                        line_index - start_line_index <= start_context_lines
                         ||
                         // Is this line part of the context before the end of the label?
-                        end_line_index - line_index <= self.config.end_context_lines;
+                        end_line_index - line_index <= end_context_lines;
                 }
 
                 // Last labeled line
@@ -293,143 +691,242 @@ where
             }
         }
 
-        // Header and message
-        //
-        // ```text
-        // error[E0001]: unexpected type in `+` application
-        // ```
-        renderer.render_header(
-            None,
-            self.diagnostic.severity,
-            self.diagnostic.code.as_deref(),
-            self.diagnostic.message.as_str(),
-        )?;
+        // Locate the file of the primary label at `Diagnostic::primary_label`,
+        // so that the locus agrees with the other display styles.
+        if let Some(label) = self.diagnostic.primary_label() {
+            let labeled_file = labeled_files
+                .iter_mut()
+                .find(|labeled_file| label.file_id == labeled_file.file_id);
+            if let Some(labeled_file) = labeled_file {
+                labeled_file.start = label.range.start;
+                labeled_file.location = files.location(label.file_id, label.range.start)?;
+            }
+        }
 
-        // Source snippets
-        //
-        // ```text
-        //   ┌─ test:2:9
-        //   │
-        // 2 │ (+ test "")
-        //   │         ^^ expected `Int` but found `String`
-        //   │
-        // ```
-        let mut labeled_files = labeled_files.into_iter().peekable();
-        while let Some(labeled_file) = labeled_files.next() {
-            let source = files.source(labeled_file.file_id)?;
+        // Apply the suggested edits to the lines of source code that they
+        // cover, keeping track of where the replacements end up.
+        let mut suggestions = Vec::with_capacity(self.diagnostic.suggestions.len());
+        for suggestion in &self.diagnostic.suggestions {
+            let source = files.source(suggestion.file_id)?;
             let source = source.as_ref();
 
-            // Top left border and locus.
-            //
-            // ```text
-            // ┌─ test:2:9
-            // ```
-            if !labeled_file.lines.is_empty() {
-                renderer.render_snippet_start(
-                    outer_padding,
-                    &Locus {
-                        name: labeled_file.name,
-                        location: labeled_file.location,
-                    },
-                )?;
-                renderer.render_snippet_empty(
-                    outer_padding,
-                    self.diagnostic.severity,
-                    labeled_file.num_multi_labels,
-                    &[],
-                )?;
+            let start_line_index = files.line_index(suggestion.file_id, suggestion.range.start)?;
+            let start_line_range = files.line_range(suggestion.file_id, start_line_index)?;
+            let start_line_number =
+                self.gutter_line_number(files, suggestion.file_id, start_line_index)?;
+            let end_line_index = files.line_index(suggestion.file_id, suggestion.range.end)?;
+            let end_line_range = files.line_range(suggestion.file_id, end_line_index)?;
+
+            let prefix = source
+                .get(start_line_range.start..suggestion.range.start)
+                .ok_or(Error::InvalidCharBoundary {
+                    given: suggestion.range.start,
+                })?;
+            let suffix = source
+                .get(suggestion.range.end..end_line_range.end)
+                .ok_or(Error::InvalidCharBoundary {
+                    given: suggestion.range.end,
+                })?
+                .trim_end_matches(['\n', '\r', '\0'].as_ref());
+            let replacement_start = prefix.len();
+            let replacement_end = replacement_start + suggestion.replacement.len();
+            let suggested_source = [prefix, &suggestion.replacement, suffix].concat();
+
+            let mut lines = Vec::new();
+            let mut line_start = 0;
+            let mut is_replacement_shown = false;
+            for (i, line) in suggested_source.split('\n').enumerate() {
+                let line_end = line_start + line.len();
+                // Don't underline the indentation of the replacement's later lines.
+                let indent = match i {
+                    0 => 0,
+                    _ => line.len() - line.trim_start().len(),
+                };
+                let start = std::cmp::max(replacement_start, line_start + indent);
+                let end = std::cmp::min(replacement_end, line_end);
+                // Insertions of nothing are underlined once, where they occur.
+                let range = if start < end || (start == end && !is_replacement_shown) {
+                    is_replacement_shown = true;
+                    Some((start - line_start)..(end - line_start))
+                } else {
+                    None
+                };
+
+                lines.push(SuggestedLine {
+                    number: start_line_number + i,
+                    source: line.trim_end_matches('\r').to_owned(),
+                    range,
+                });
+                line_start = line_end + 1;
             }
 
-            let mut lines = labeled_file
-                .lines
-                .iter()
-                .filter(|(_, line)| line.must_render)
-                .peekable();
+            if let Some(last_line) = lines.last() {
+                outer_padding = std::cmp::max(outer_padding, count_digits(last_line.number));
+            }
+            suggestions.push((suggestion.message.as_str(), lines));
+        }
 
-            while let Some((line_index, line)) = lines.next() {
-                renderer.render_snippet_source(
-                    outer_padding,
-                    line.number,
-                    &source[line.range.clone()],
-                    self.diagnostic.severity,
-                    &line.single_labels,
-                    labeled_file.num_multi_labels,
-                    &line.multi_labels,
-                )?;
+        if self.config.file_sort == FileSortOrder::PrimaryFirst {
+            // This is a stable sort, so files with the same style of labels
+            // will remain in the order that they first appeared.
+            labeled_files
+                .sort_by_key(|labeled_file| labeled_file.max_label_style != LabelStyle::Primary);
+        }
 
-                // Check to see if we need to render any intermediate stuff
-                // before rendering the next line.
-                if let Some((next_line_index, next_line)) = lines.peek() {
-                    match next_line_index.checked_sub(*line_index) {
-                        // Consecutive lines
-                        Some(1) => {}
-                        // One line between the current line and the next line
-                        Some(2) => {
-                            // Write a source line
-                            let file_id = labeled_file.file_id;
+        Ok(Layout {
+            labeled_files,
+            binary_files,
+            suggestions,
+            outer_padding: std::cmp::max(outer_padding, self.min_outer_padding),
+        })
+    }
 
-                            // This line was not intended to be rendered initially.
-                            // To render the line right, we have to get back the original labels.
-                            let labels = labeled_file
-                                .lines
-                                .get(&(line_index + 1))
-                                .map_or(&[][..], |line| &line.multi_labels[..]);
+    /// The line number to show in the gutter, shifted by the configured offset.
+    fn gutter_line_number<'files>(
+        &self,
+        files: &'files impl Files<'files, FileId = FileId>,
+        file_id: FileId,
+        line_index: usize,
+    ) -> Result<usize, Error>
+    where
+        FileId: 'files,
+    {
+        let line_number = files.line_number(file_id, line_index)?;
+        let line_number = (line_number as isize).saturating_add(self.config.line_number_offset);
+        Ok(std::cmp::max(line_number, 0) as usize)
+    }
 
-                            renderer.render_snippet_source(
-                                outer_padding,
-                                files.line_number(file_id, line_index + 1)?,
-                                &source[files.line_range(file_id, line_index + 1)?],
-                                self.diagnostic.severity,
-                                &[],
-                                labeled_file.num_multi_labels,
-                                labels,
-                            )?;
-                        }
-                        // More than one line between the current line and the next line.
-                        Some(_) | None => {
-                            // Source break
-                            //
-                            // ```text
-                            // ·
-                            // ```
-                            renderer.render_snippet_break(
-                                outer_padding,
-                                self.diagnostic.severity,
-                                labeled_file.num_multi_labels,
-                                &next_line.multi_labels,
-                            )?;
-                        }
-                    }
-                }
-            }
+    /// The tool that produced the diagnostic, if it should be shown.
+    fn provenance(&self) -> Option<&'diagnostic str> {
+        match &self.diagnostic.source {
+            Some(source) if self.config.show_source_provenance => Some(source),
+            _ => None,
+        }
+    }
 
-            // Check to see if we should render a trailing border after the
-            // final line of the snippet.
-            if labeled_files.peek().is_none() && self.diagnostic.notes.is_empty() {
-                // We don't render a border if we are at the final newline
-                // without trailing notes, because it would end up looking too
-                // spaced-out in combination with the final new line.
-            } else {
-                // Render the trailing snippet border.
-                renderer.render_snippet_empty(
-                    outer_padding,
-                    self.diagnostic.severity,
-                    labeled_file.num_multi_labels,
-                    &[],
-                )?;
+    /// Render the border following the final line of a snippet, if needed.
+    fn render_snippet_end(
+        &self,
+        renderer: &mut Renderer<'_, '_>,
+        outer_padding: usize,
+        num_multi_labels: usize,
+        is_last_snippet: bool,
+    ) -> Result<(), Error> {
+        let has_notes = !(self.diagnostic.notes.is_empty()
+            && self.diagnostic.rich_notes.is_empty()
+            && self.diagnostic.suggestions.is_empty()
+            && self.provenance().is_none());
+
+        if is_last_snippet && !has_notes {
+            // We don't render a border if we are at the final newline
+            // without trailing notes, because it would end up looking too
+            // spaced-out in combination with the final new line.
+            Ok(())
+        } else if is_last_snippet && !self.config.border_before_notes {
+            // Separate the final snippet from the notes with an empty line.
+            renderer.render_empty()
+        } else {
+            // Render the trailing snippet border.
+            renderer.render_snippet_empty(
+                outer_padding,
+                self.diagnostic.severity,
+                num_multi_labels,
+                &[],
+            )
+        }
+    }
+}
+
+/// Output a compact diagnostic, falling back to a [`RichDiagnostic`] for
+/// anything more complicated than a single, single-line label in a text file
+/// without notes or suggestions.
+pub struct LinearDiagnostic<'diagnostic, 'config, FileId> {
+    diagnostic: &'diagnostic Diagnostic<FileId>,
+    config: &'config Config,
+}
+
+impl<'diagnostic, 'config, FileId> LinearDiagnostic<'diagnostic, 'config, FileId>
+where
+    FileId: Copy + PartialEq,
+{
+    pub fn new(
+        diagnostic: &'diagnostic Diagnostic<FileId>,
+        config: &'config Config,
+    ) -> LinearDiagnostic<'diagnostic, 'config, FileId> {
+        LinearDiagnostic { diagnostic, config }
+    }
+
+    pub fn render<'files>(
+        &self,
+        files: &'files impl Files<'files, FileId = FileId>,
+        renderer: &mut Renderer<'_, '_>,
+    ) -> Result<(), Error>
+    where
+        FileId: 'files,
+    {
+        let label = match self.diagnostic.labels.as_slice() {
+            [label]
+                if self.diagnostic.notes.is_empty()
+                    && self.diagnostic.rich_notes.is_empty()
+                    && self.diagnostic.suggestions.is_empty()
+                    && (self.diagnostic.source.is_none()
+                        || !self.config.show_source_provenance)
+                    && !files.is_binary(label.file_id) =>
+            {
+                label
             }
+            _ => return RichDiagnostic::new(self.diagnostic, self.config).render(files, renderer),
+        };
+
+        let line_index = files.line_index(label.file_id, label.range.start)?;
+        if files.line_index(label.file_id, label.range.end)? != line_index {
+            return RichDiagnostic::new(self.diagnostic, self.config).render(files, renderer);
         }
 
-        // Additional notes
+        // Header
         //
         // ```text
-        // = expected type `Int`
-        //      found type `String`
+        // error[E0001]: unexpected type in `+` application
         // ```
-        for note in &self.diagnostic.notes {
-            renderer.render_snippet_note(outer_padding, note)?;
-        }
-        renderer.render_empty()
+        renderer.render_header(
+            None,
+            self.diagnostic.severity,
+            self.diagnostic.code.as_deref(),
+            self.diagnostic.message.as_str(),
+        )?;
+
+        // Locus, source, and label
+        //
+        // ```text
+        // test:2:9 │ (+ test "") │ ^^ expected `Int` but found `String`
+        // ```
+        let source = files.source(label.file_id)?;
+        let line_range = files.line_range(label.file_id, line_index)?;
+        let line_source = source
+            .as_ref()
+            .get(line_range.clone())
+            .ok_or(Error::InvalidCharBoundary {
+                given: line_range.start,
+            })?
+            .trim_end_matches(['\n', '\r', '\0'].as_ref());
+        let label_range =
+            (label.range.start - line_range.start)..(label.range.end - line_range.start);
+
+        renderer.set_source_line(Some((0, line_index)));
+        renderer.render_linear_source(
+            &Locus {
+                name: locus_name(files, self.config.compact_locus, label.file_id)?,
+                full_name: files.name(label.file_id)?.to_string(),
+                location: files.location(label.file_id, label.range.start)?,
+                byte_index: label.range.start,
+            },
+            line_source,
+            self.diagnostic.severity,
+            &(label.style, label_range, label.message.as_str()),
+        )?;
+        renderer.set_source_line(None);
+
+        Ok(())
     }
 }
 
@@ -437,6 +934,8 @@ where
 pub struct ShortDiagnostic<'diagnostic, FileId> {
     diagnostic: &'diagnostic Diagnostic<FileId>,
     show_notes: bool,
+    first_note_only: bool,
+    compact_locus: bool,
 }
 
 impl<'diagnostic, FileId> ShortDiagnostic<'diagnostic, FileId>
@@ -446,10 +945,14 @@ where
     pub fn new(
         diagnostic: &'diagnostic Diagnostic<FileId>,
         show_notes: bool,
+        first_note_only: bool,
+        compact_locus: bool,
     ) -> ShortDiagnostic<'diagnostic, FileId> {
         ShortDiagnostic {
             diagnostic,
             show_notes,
+            first_note_only,
+            compact_locus,
         }
     }
 
@@ -466,15 +969,26 @@ where
         // ```text
         // test:2:9: error[E0001]: unexpected type in `+` application
         // ```
+        //
+        // The first header is located at `Diagnostic::primary_label`, like the
+        // locus of the rich display style, and is followed by the headers of
+        // the other primary labels in the order they were added.
+        let primary_label = self.diagnostic.primary_label();
+        let other_primary_labels = self.diagnostic.labels.iter().filter(|label| {
+            label.style == LabelStyle::Primary
+                && !matches!(primary_label, Some(primary_label) if std::ptr::eq(*label, primary_label))
+        });
+
         let mut primary_labels_encountered = 0;
-        let labels = self.diagnostic.labels.iter();
-        for label in labels.filter(|label| label.style == LabelStyle::Primary) {
+        for label in primary_label.into_iter().chain(other_primary_labels) {
             primary_labels_encountered += 1;
 
             renderer.render_header(
                 Some(&Locus {
-                    name: files.name(label.file_id)?.to_string(),
+                    name: locus_name(files, self.compact_locus, label.file_id)?,
+                    full_name: files.name(label.file_id)?.to_string(),
                     location: files.location(label.file_id, label.range.start)?,
+                    byte_index: label.range.start,
                 }),
                 self.diagnostic.severity,
                 self.diagnostic.code.as_deref(),
@@ -496,7 +1010,38 @@ where
             )?;
         }
 
-        if self.show_notes {
+        if self.show_notes && self.first_note_only {
+            // The first line of the first note
+            //
+            // ```text
+            // = expected type `Int`
+            // ```
+            let first_note = self
+                .diagnostic
+                .notes
+                .iter()
+                .map(|note| (None, note.as_str()))
+                .chain(
+                    self.diagnostic
+                        .rich_notes
+                        .iter()
+                        .map(|note| (Some(note), note.message.as_str())),
+                )
+                .next();
+            if let Some((rich_note, note)) = first_note {
+                let line = note.lines().next().unwrap_or("");
+                match rich_note {
+                    Some(rich_note) if rich_note.plain => {
+                        renderer.render_snippet_plain_note(0, line)?
+                    }
+                    rich_note => renderer.render_snippet_note(
+                        0,
+                        rich_note.map(|note| note.severity),
+                        line,
+                    )?,
+                }
+            }
+        } else if self.show_notes {
             // Additional notes
             //
             // ```text
@@ -504,7 +1049,17 @@ where
             //      found type `String`
             // ```
             for note in &self.diagnostic.notes {
-                renderer.render_snippet_note(0, note)?;
+                renderer.render_snippet_note(0, None, note)?;
+            }
+            for note in &self.diagnostic.rich_notes {
+                if note.plain {
+                    renderer.render_snippet_plain_note(0, &note.message)?;
+                } else {
+                    renderer.render_snippet_note(0, Some(note.severity), &note.message)?;
+                }
+            }
+            for suggestion in &self.diagnostic.suggestions {
+                renderer.render_snippet_note(0, Some(Severity::Help), &suggestion.message)?;
             }
         }
 
@@ -1,6 +1,6 @@
 use codespan_reporting::diagnostic::Diagnostic;
 use codespan_reporting::files::Files;
-use codespan_reporting::term::{emit, Config};
+use codespan_reporting::term::{emit_many, Config};
 use termcolor::{Buffer, WriteColor};
 
 mod color_buffer;
@@ -14,9 +14,7 @@ pub struct TestData<'files, F: Files<'files>> {
 
 impl<'files, F: Files<'files>> TestData<'files, F> {
     fn emit<W: WriteColor>(&'files self, mut writer: W, config: &Config) -> W {
-        for diagnostic in &self.diagnostics {
-            emit(&mut writer, config, &self.files, &diagnostic).unwrap();
-        }
+        emit_many(&mut writer, config, &self.files, &self.diagnostics).unwrap();
         writer
     }
 
@@ -28,4 +26,13 @@ impl<'files, F: Files<'files>> TestData<'files, F> {
         let buffer = self.emit(Buffer::no_color(), &config);
         String::from_utf8_lossy(buffer.as_slice()).into_owned()
     }
+
+    #[cfg(feature = "json")]
+    pub fn emit_json(&'files self) -> String {
+        let mut buffer = Vec::new();
+        for diagnostic in &self.diagnostics {
+            codespan_reporting::term::emit_json(&mut buffer, &self.files, diagnostic).unwrap();
+        }
+        String::from_utf8(buffer).unwrap()
+    }
 }
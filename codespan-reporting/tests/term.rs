@@ -1,6 +1,10 @@
-use codespan_reporting::diagnostic::{Diagnostic, Label};
+use codespan_reporting::diagnostic::{Diagnostic, Label, Note, Suggestion};
 use codespan_reporting::files::{SimpleFile, SimpleFiles};
-use codespan_reporting::term::{termcolor::Color, Chars, Config, DisplayStyle, Styles};
+use codespan_reporting::term::{
+    termcolor::{Color, ColorSpec},
+    CaretRowOrder, Chars, Config, DisplayStyle, FileSortOrder, LocusFormat, SecondaryEmphasis,
+    SeverityNames, Styles,
+};
 
 mod support;
 
@@ -81,6 +85,28 @@ macro_rules! test_emit {
             insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
         }
     };
+    (linear_color) => {
+        #[test]
+        fn linear_color() {
+            let config = Config {
+                display_style: DisplayStyle::Linear,
+                ..TEST_CONFIG.clone()
+            };
+
+            insta::assert_snapshot!(TEST_DATA.emit_color(&config));
+        }
+    };
+    (linear_no_color) => {
+        #[test]
+        fn linear_no_color() {
+            let config = Config {
+                display_style: DisplayStyle::Linear,
+                ..TEST_CONFIG.clone()
+            };
+
+            insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+        }
+    };
     (rich_ascii_no_color) => {
         #[test]
         fn rich_ascii_no_color() {
@@ -122,6 +148,11 @@ mod empty {
     test_emit!(medium_no_color);
     test_emit!(short_no_color);
     test_emit!(rich_ascii_no_color);
+
+    #[test]
+    fn is_empty() {
+        assert!(TEST_DATA.diagnostics.iter().all(Diagnostic::is_empty));
+    }
 }
 
 /// Based on:
@@ -173,6 +204,156 @@ mod same_line {
     test_emit!(medium_no_color);
     test_emit!(short_no_color);
     test_emit!(rich_ascii_no_color);
+    test_emit!(linear_no_color);
+
+    #[test]
+    fn source_start_marker_rich_no_color() {
+        let config = Config {
+            source_start_marker: Some('▏'),
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn line_number_offset_rich_no_color() {
+        let config = Config {
+            line_number_offset: 99,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn secondary_emphasis_subdued_rich_color() {
+        let config = Config {
+            secondary_emphasis: SecondaryEmphasis::Subdued,
+            chars: Chars {
+                single_secondary_caret_subdued: '.',
+                ..Chars::default()
+            },
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_color(&config));
+    }
+
+    #[test]
+    fn underline_fill_rich_no_color() {
+        let config = Config {
+            chars: Chars {
+                single_primary_underline_fill: Some('~'),
+                single_secondary_underline_fill: Some('~'),
+                ..Chars::default()
+            },
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn show_byte_offset_rich_no_color() {
+        let config = Config {
+            show_byte_offset: true,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn show_byte_offset_short_no_color() {
+        let config = Config {
+            display_style: DisplayStyle::Short,
+            show_byte_offset: true,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn code_link() {
+        let config = Config {
+            code_link: Some(|code| format!("https://example.com/errors/{}", code)),
+            ..TEST_CONFIG.clone()
+        };
+
+        let link = "\x1b]8;;https://example.com/errors/E0499\x1b\\[E0499]\x1b]8;;\x1b\\";
+        assert!(TEST_DATA.emit_color(&config).contains(link));
+
+        let output = TEST_DATA.emit_no_color(&config);
+        assert!(!output.contains('\x1b'));
+        assert_eq!(output, TEST_DATA.emit_no_color(&TEST_CONFIG));
+    }
+
+    #[test]
+    fn line_map() {
+        let (output, line_map) = codespan_reporting::term::emit_with_line_map(
+            &TEST_CONFIG,
+            &TEST_DATA.files,
+            &TEST_DATA.diagnostics[0],
+        )
+        .unwrap();
+
+        assert_eq!(output.lines().count(), line_map.len());
+        assert_eq!(
+            line_map,
+            [
+                None,         // error[E0499]: cannot borrow `v` as mutable more than once at a time
+                None,         //   ┌─ one_line.rs:3:12
+                None,         //   │
+                Some((0, 2)), // 3 │     v.push(v.pop().unwrap());
+                Some((0, 2)), //   │     - ---- ^ second mutable borrow occurs here
+                Some((0, 2)), //   │     │ │
+                Some((0, 2)), //   │     │ first mutable borrow occurs here
+                Some((0, 2)), //   │     first borrow later used by call
+                None,         //
+            ],
+        );
+    }
+}
+
+mod linear {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add(
+                "test",
+                unindent::unindent(r#"
+                    (let test "")
+                    (+ test "")
+                        (+ 1 test)
+                "#),
+            );
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_code("E0001")
+                    .with_message("unexpected type in `+` application")
+                    .with_labels(vec![
+                        Label::primary(file_id, 22..24)
+                            .with_message("expected `Int` but found `String`"),
+                    ]),
+                Diagnostic::warning()
+                    .with_message("unused binding")
+                    .with_labels(vec![
+                        Label::secondary(file_id, 36..40),
+                    ]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(linear_color);
+    test_emit!(linear_no_color);
 }
 
 /// Based on:
@@ -345,6 +526,11 @@ mod message {
     test_emit!(medium_no_color);
     test_emit!(short_no_color);
     test_emit!(rich_ascii_no_color);
+
+    #[test]
+    fn is_empty() {
+        assert!(!TEST_DATA.diagnostics.iter().any(Diagnostic::is_empty));
+    }
 }
 
 mod message_and_notes {
@@ -374,250 +560,1463 @@ mod message_and_notes {
     test_emit!(rich_ascii_no_color);
 }
 
-mod message_errorcode {
+mod rich_notes {
     use super::*;
 
     lazy_static::lazy_static! {
-        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, &'static str>> = {
-            let files = SimpleFiles::new();
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add(
+                "test",
+                unindent::unindent(r#"
+                    (let five 5)
+                    (+ five "five")
+                "#),
+            );
 
             let diagnostics = vec![
-                Diagnostic::error().with_message("a message").with_code("E0001"),
-                Diagnostic::warning().with_message("a message").with_code("W001"),
-                Diagnostic::note().with_message("a message").with_code("N0815"),
-                Diagnostic::help().with_message("a message").with_code("H4711"),
-                Diagnostic::error().with_message("where did my errorcode go?").with_code(""),
-                Diagnostic::warning().with_message("where did my errorcode go?").with_code(""),
-                Diagnostic::note().with_message("where did my errorcode go?").with_code(""),
-                Diagnostic::help().with_message("where did my errorcode go?").with_code(""),
+                Diagnostic::error()
+                    .with_message("unexpected type in `+` application")
+                    .with_labels(vec![
+                        Label::primary(file_id, 21..27).with_message("expected `Int` but found `String`"),
+                    ])
+                    .with_notes(vec![unindent::unindent(
+                        "
+                            expected type `Int`
+                               found type `String`
+                        ",
+                    )])
+                    .with_rich_notes(vec![
+                        Note::help("try using a number literal, like `5`"),
+                        Note::note("`+` is only defined for numbers,\nuse `++` to concatenate strings"),
+                    ]),
+                Diagnostic::warning()
+                    .with_message("unused binding")
+                    .with_rich_notes(vec![Note::help("remove the binding")]),
             ];
 
             TestData { files, diagnostics }
         };
     }
 
-    test_emit!(rich_no_color);
-    test_emit!(short_no_color);
-    test_emit!(rich_ascii_no_color);
+    test_emit!(rich_color);
+    test_emit!(medium_no_color);
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn json() {
+        insta::assert_snapshot!(TEST_DATA.emit_json());
+    }
 }
 
-mod empty_ranges {
+mod plain_notes {
     use super::*;
 
     lazy_static::lazy_static! {
-        static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, &'static str>> = {
-            let file = SimpleFile::new("hello", "Hello world!\nBye world!\n   ");
-            let eof = file.source().len();
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add("test", "(+ five \"five\")\n".to_owned());
 
             let diagnostics = vec![
-                Diagnostic::note()
-                    .with_message("middle")
-                    .with_labels(vec![Label::primary((), 6..6).with_message("middle")]),
-                Diagnostic::note()
-                    .with_message("end of line")
-                    .with_labels(vec![Label::primary((), 12..12).with_message("end of line")]),
-                Diagnostic::note()
-                    .with_message("end of line")
-                    .with_labels(vec![Label::primary((), 23..23).with_message("end of line")]),
-                Diagnostic::note()
-                    .with_message("end of file")
-                    .with_labels(vec![Label::primary((), eof..eof).with_message("end of file")]),
+                Diagnostic::error()
+                    .with_message("unexpected type in `+` application")
+                    .with_labels(vec![
+                        Label::primary(file_id, 8..14).with_message("expected `Int` but found `String`"),
+                    ])
+                    .with_notes(vec!["expected type `Int`\n   found type `String`".to_owned()])
+                    .with_rich_notes(vec![
+                        Note::plain("Strings can be converted to numbers with `parse`.\nSee the chapter on conversions for more details."),
+                    ]),
             ];
 
-            TestData { files: file, diagnostics }
+            TestData { files, diagnostics }
         };
     }
 
-    test_emit!(rich_color);
-    test_emit!(medium_color);
-    test_emit!(short_color);
     test_emit!(rich_no_color);
     test_emit!(medium_no_color);
-    test_emit!(short_no_color);
-    test_emit!(rich_ascii_no_color);
 }
 
-mod same_ranges {
+mod source_provenance {
     use super::*;
 
     lazy_static::lazy_static! {
-        static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, &'static str>> = {
-            let file = SimpleFile::new("same_range", "::S { }");
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add("main.rs", "let x = 1;\n".to_owned());
 
             let diagnostics = vec![
+                Diagnostic::warning()
+                    .with_message("unused variable: `x`")
+                    .with_labels(vec![Label::primary(file_id, 4..5)])
+                    .with_notes(vec!["`#[warn(unused_variables)]` on by default".to_owned()])
+                    .with_source("rustc"),
+                Diagnostic::warning()
+                    .with_message("redundant binding")
+                    .with_labels(vec![Label::primary(file_id, 0..10)])
+                    .with_source("clippy"),
                 Diagnostic::error()
-                    .with_message("Unexpected token")
-                    .with_labels(vec![
-                        Label::primary((), 4..4).with_message("Unexpected '{'"),
-                        Label::secondary((), 4..4).with_message("Expected '('"),
-                    ]),
+                    .with_message("aborting due to previous error")
+                    .with_source("rustc"),
             ];
 
-            TestData { files: file, diagnostics }
+            TestData { files, diagnostics }
         };
     }
 
-    test_emit!(rich_color);
-    test_emit!(medium_color);
-    test_emit!(short_color);
-    test_emit!(rich_no_color);
-    test_emit!(medium_no_color);
-    test_emit!(short_no_color);
-    test_emit!(rich_ascii_no_color);
+    #[test]
+    fn rich_no_color() {
+        let config = Config {
+            show_source_provenance: true,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn rich_color() {
+        let config = Config {
+            show_source_provenance: true,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_color(&config));
+    }
+
+    #[test]
+    fn hidden_rich_no_color() {
+        let config = TEST_CONFIG.clone();
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
 }
 
-mod multifile {
+mod border_before_notes {
     use super::*;
 
     lazy_static::lazy_static! {
         static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
             let mut files = SimpleFiles::new();
 
-            let file_id1 = files.add(
-                "Data/Nat.fun",
-                unindent::unindent(
-                    "
-                        module Data.Nat where
+            let file_id = files.add(
+                "test",
+                unindent::unindent(r#"
+                    (let five 5)
+                    (+ five "five")
+                "#),
+            );
 
-                        data Nat : Type where
-                            zero : Nat
-                            succ : Nat → Nat
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("unexpected type in `+` application")
+                    .with_labels(vec![
+                        Label::primary(file_id, 21..27).with_message("expected `Int` but found `String`"),
+                    ])
+                    .with_notes(vec![unindent::unindent(
+                        "
+                            expected type `Int`
+                               found type `String`
+                        ",
+                    )]),
+            ];
 
-                        {-# BUILTIN NATRAL Nat #-}
+            TestData { files, diagnostics }
+        };
+    }
 
-                        infixl 6 _+_ _-_
+    #[test]
+    fn bordered_rich_no_color() {
+        let config = Config {
+            border_before_notes: true,
+            ..TEST_CONFIG.clone()
+        };
 
-                        _+_ : Nat → Nat → Nat
-                        zero    + n₂ = n₂
-                        succ n₁ + n₂ = succ (n₁ + n₂)
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
 
-                        _-_ : Nat → Nat → Nat
-                        n₁      - zero    = n₁
-                        zero    - succ n₂ = zero
-                        succ n₁ - succ n₂ = n₁ - n₂
+    #[test]
+    fn unbordered_rich_no_color() {
+        let config = Config {
+            border_before_notes: false,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+}
+
+mod binary_files {
+    use codespan_reporting::files::{self, Files};
+    use std::ops::Range;
+
+    use super::*;
+
+    /// A file database that treats files containing a NUL byte as binary.
+    struct BinaryAwareFiles {
+        files: SimpleFiles<&'static str, String>,
+    }
+
+    impl<'a> Files<'a> for BinaryAwareFiles {
+        type FileId = usize;
+        type Name = &'static str;
+        type Source = &'a str;
+
+        fn name(&'a self, id: usize) -> Result<&'static str, files::Error> {
+            self.files.name(id)
+        }
+
+        fn source(&'a self, id: usize) -> Result<&'a str, files::Error> {
+            self.files.source(id)
+        }
+
+        fn line_index(&'a self, id: usize, byte_index: usize) -> Result<usize, files::Error> {
+            self.files.line_index(id, byte_index)
+        }
+
+        fn line_range(
+            &'a self,
+            id: usize,
+            line_index: usize,
+        ) -> Result<Range<usize>, files::Error> {
+            self.files.line_range(id, line_index)
+        }
+
+        fn is_binary(&'a self, id: usize) -> bool {
+            match self.files.source(id) {
+                Ok(source) => source.contains('\0'),
+                Err(_) => false,
+            }
+        }
+    }
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, BinaryAwareFiles> = {
+            let mut files = SimpleFiles::new();
+
+            let image_id = files.add("logo.png", "\u{7f}PNG\r\n\u{1a}\n\0\0\0\rIHDR".to_owned());
+            let source_id = files.add(
+                "build.rs",
+                unindent::unindent(r#"
+                    let logo = include_bytes!("logo.png");
+                "#),
+            );
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("invalid image")
+                    .with_labels(vec![
+                        Label::primary(image_id, 0..4).with_message("expected a PNG signature"),
+                        Label::secondary(image_id, 8..12).with_message("in this chunk"),
+                        Label::secondary(source_id, 26..36).with_message("image included here"),
+                    ])
+                    .with_notes(vec!["images must be in PNG format".to_owned()]),
+                Diagnostic::warning()
+                    .with_message("unused chunk")
+                    .with_labels(vec![Label::primary(image_id, 12..16)]),
+            ];
+
+            TestData { files: BinaryAwareFiles { files }, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(rich_no_color);
+    test_emit!(linear_no_color);
+}
+
+mod max_caret_len {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_CONFIG: Config = Config {
+            styles: Styles::with_blue(Color::Blue),
+            max_caret_len: Some(20),
+            ..Config::default()
+        };
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let identifier = "generated_".repeat(10);
+            let file_id = files.add("generated.rs", format!("let {} = 1;\n", identifier));
+            let identifier_range = 4..(4 + identifier.len());
+
+            let diagnostics = vec![
+                Diagnostic::warning()
+                    .with_message("unused variable")
+                    .with_labels(vec![
+                        Label::primary(file_id, identifier_range.clone()),
+                    ]),
+                Diagnostic::error()
+                    .with_message("mismatched types")
+                    .with_labels(vec![
+                        Label::secondary(file_id, identifier_range.clone()).with_message("expected due to this"),
+                        Label::primary(file_id, (identifier_range.end + 3)..(identifier_range.end + 4))
+                            .with_message("expected `String`, found integer"),
+                    ]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(rich_no_color);
+    test_emit!(rich_ascii_no_color);
+}
+
+mod message_errorcode {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, &'static str>> = {
+            let files = SimpleFiles::new();
+
+            let diagnostics = vec![
+                Diagnostic::error().with_message("a message").with_code("E0001"),
+                Diagnostic::warning().with_message("a message").with_code("W001"),
+                Diagnostic::note().with_message("a message").with_code("N0815"),
+                Diagnostic::help().with_message("a message").with_code("H4711"),
+                Diagnostic::error().with_message("where did my errorcode go?").with_code(""),
+                Diagnostic::warning().with_message("where did my errorcode go?").with_code(""),
+                Diagnostic::note().with_message("where did my errorcode go?").with_code(""),
+                Diagnostic::help().with_message("where did my errorcode go?").with_code(""),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_no_color);
+    test_emit!(short_no_color);
+    test_emit!(rich_ascii_no_color);
+
+    #[test]
+    fn hide_code_rich_no_color() {
+        let config = Config {
+            show_code: false,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn hide_code_short_no_color() {
+        let config = Config {
+            display_style: DisplayStyle::Short,
+            show_code: false,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+}
+
+mod empty_ranges {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, &'static str>> = {
+            let file = SimpleFile::new("hello", "Hello world!\nBye world!\n   ");
+            let eof = file.source().len();
+
+            let diagnostics = vec![
+                Diagnostic::note()
+                    .with_message("middle")
+                    .with_labels(vec![Label::primary((), 6..6).with_message("middle")]),
+                Diagnostic::note()
+                    .with_message("end of line")
+                    .with_labels(vec![Label::primary((), 12..12).with_message("end of line")]),
+                Diagnostic::note()
+                    .with_message("end of line")
+                    .with_labels(vec![Label::primary((), 23..23).with_message("end of line")]),
+                Diagnostic::note()
+                    .with_message("end of file")
+                    .with_labels(vec![Label::primary((), eof..eof).with_message("end of file")]),
+            ];
+
+            TestData { files: file, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(medium_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(medium_no_color);
+    test_emit!(short_no_color);
+    test_emit!(rich_ascii_no_color);
+}
+
+mod same_ranges {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, &'static str>> = {
+            let file = SimpleFile::new("same_range", "::S { }");
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("Unexpected token")
+                    .with_labels(vec![
+                        Label::primary((), 4..4).with_message("Unexpected '{'"),
+                        Label::secondary((), 4..4).with_message("Expected '('"),
+                    ]),
+            ];
+
+            TestData { files: file, diagnostics }
+        };
+
+        static ref SECONDARY_FIRST_TEST_DATA: TestData<'static, SimpleFile<&'static str, &'static str>> = {
+            let file = SimpleFile::new("same_range", "::S { }");
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("Unexpected token")
+                    .with_labels(vec![
+                        Label::secondary((), 4..4).with_message("Expected '('"),
+                        Label::primary((), 4..4).with_message("Unexpected '{'"),
+                    ]),
+            ];
+
+            TestData { files: file, diagnostics }
+        };
+
+        static ref PRIORITY_TEST_DATA: TestData<'static, SimpleFile<&'static str, &'static str>> = {
+            let file = SimpleFile::new("same_range", "::S { }");
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("Unexpected token")
+                    .with_labels(vec![
+                        Label::secondary((), 4..4).with_message("Expected '('"),
+                        Label::secondary((), 4..4).with_message("Expected '::'"),
+                        Label::primary((), 4..4).with_message("Unexpected '{'").with_priority(1),
+                        Label::secondary((), 4..4).with_message("Expected ';'").with_priority(-1),
+                    ]),
+            ];
+
+            TestData { files: file, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(medium_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(medium_no_color);
+    test_emit!(short_no_color);
+    test_emit!(rich_ascii_no_color);
+
+    #[test]
+    fn priority_no_color() {
+        insta::assert_snapshot!(PRIORITY_TEST_DATA.emit_no_color(&TEST_CONFIG));
+    }
+
+    #[test]
+    fn caret_row_order_as_specified_color() {
+        let config = Config {
+            caret_row_order: CaretRowOrder::AsSpecified,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(SECONDARY_FIRST_TEST_DATA.emit_color(&config));
+    }
+
+    #[test]
+    fn caret_row_order_primary_first_color() {
+        let config = Config {
+            caret_row_order: CaretRowOrder::PrimaryFirst,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(SECONDARY_FIRST_TEST_DATA.emit_color(&config));
+    }
+
+    #[test]
+    fn dim_secondary_with_primary_rich_color() {
+        let config = Config {
+            dim_secondary_with_primary: true,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_color(&config));
+    }
+}
+
+mod multifile {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id1 = files.add(
+                "Data/Nat.fun",
+                unindent::unindent(
+                    "
+                        module Data.Nat where
+
+                        data Nat : Type where
+                            zero : Nat
+                            succ : Nat → Nat
+
+                        {-# BUILTIN NATRAL Nat #-}
+
+                        infixl 6 _+_ _-_
+
+                        _+_ : Nat → Nat → Nat
+                        zero    + n₂ = n₂
+                        succ n₁ + n₂ = succ (n₁ + n₂)
+
+                        _-_ : Nat → Nat → Nat
+                        n₁      - zero    = n₁
+                        zero    - succ n₂ = zero
+                        succ n₁ - succ n₂ = n₁ - n₂
                     ",
                 ),
             );
 
-            let file_id2 = files.add(
-                "Test.fun",
-                unindent::unindent(
-                    r#"
-                        module Test where
+            let file_id2 = files.add(
+                "Test.fun",
+                unindent::unindent(
+                    r#"
+                        module Test where
+
+                        _ : Nat
+                        _ = 123 + "hello"
+                    "#,
+                ),
+            );
+
+            let diagnostics = vec![
+                // Unknown builtin error
+                Diagnostic::error()
+                    .with_message("unknown builtin: `NATRAL`")
+                    .with_labels(vec![Label::primary(file_id1, 96..102).with_message("unknown builtin")])
+                    .with_notes(vec![
+                        "there is a builtin with a similar name: `NATURAL`".to_owned(),
+                    ]),
+                // Unused parameter warning
+                Diagnostic::warning()
+                    .with_message("unused parameter pattern: `n₂`")
+                    .with_labels(vec![Label::primary(file_id1, 285..289).with_message("unused parameter")])
+                    .with_notes(vec!["consider using a wildcard pattern: `_`".to_owned()]),
+                // Unexpected type error
+                Diagnostic::error()
+                    .with_message("unexpected type in application of `_+_`")
+                    .with_code("E0001")
+                    .with_labels(vec![
+                        Label::primary(file_id2, 37..44).with_message("expected `Nat`, found `String`"),
+                        Label::secondary(file_id1, 130..155).with_message("based on the definition of `_+_`"),
+                    ])
+                    .with_notes(vec![unindent::unindent(
+                        "
+                            expected type `Nat`
+                               found type `String`
+                        ",
+                    )]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(medium_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(medium_no_color);
+    test_emit!(short_no_color);
+    test_emit!(rich_ascii_no_color);
+
+    #[test]
+    fn hide_locus_rich_no_color() {
+        let config = Config {
+            show_locus: false,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+}
+
+mod fizz_buzz {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add(
+                "FizzBuzz.fun",
+                unindent::unindent(
+                    r#"
+                        module FizzBuzz where
+
+                        fizz₁ : Nat → String
+                        fizz₁ num = case (mod num 5) (mod num 3) of
+                            0 0 => "FizzBuzz"
+                            0 _ => "Fizz"
+                            _ 0 => "Buzz"
+                            _ _ => num
+
+                        fizz₂ : Nat → String
+                        fizz₂ num =
+                            case (mod num 5) (mod num 3) of
+                                0 0 => "FizzBuzz"
+                                0 _ => "Fizz"
+                                _ 0 => "Buzz"
+                                _ _ => num
+                    "#,
+                ),
+            );
+
+            let diagnostics = vec![
+                // Incompatible match clause error
+                Diagnostic::error()
+                    .with_message("`case` clauses have incompatible types")
+                    .with_code("E0308")
+                    .with_labels(vec![
+                        Label::primary(file_id, 163..166).with_message("expected `String`, found `Nat`"),
+                        Label::secondary(file_id, 62..166).with_message("`case` clauses have incompatible types"),
+                        Label::secondary(file_id, 41..47).with_message("expected type `String` found here"),
+                    ])
+                    .with_notes(vec![unindent::unindent(
+                        "
+                            expected type `String`
+                               found type `Nat`
+                        ",
+                    )]),
+                // Incompatible match clause error
+                Diagnostic::error()
+                    .with_message("`case` clauses have incompatible types")
+                    .with_code("E0308")
+                    .with_labels(vec![
+                        Label::primary(file_id, 328..331).with_message("expected `String`, found `Nat`"),
+                        Label::secondary(file_id, 211..331).with_message("`case` clauses have incompatible types"),
+                        Label::secondary(file_id, 258..268).with_message("this is found to be of type `String`"),
+                        Label::secondary(file_id, 284..290).with_message("this is found to be of type `String`"),
+                        Label::secondary(file_id, 306..312).with_message("this is found to be of type `String`"),
+                        Label::secondary(file_id, 186..192).with_message("expected type `String` found here"),
+                    ])
+                    .with_notes(vec![unindent::unindent(
+                        "
+                            expected type `String`
+                               found type `Nat`
+                        ",
+                    )]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(medium_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(medium_no_color);
+    test_emit!(short_no_color);
+    test_emit!(rich_ascii_no_color);
+
+    #[test]
+    fn source_left_pad_rich_no_color() {
+        let config = Config {
+            source_left_pad: 3,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn medium_includes_first_note_no_color() {
+        let config = Config {
+            display_style: DisplayStyle::Medium,
+            medium_includes_first_note: true,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn gutter_padding_rich_no_color() {
+        let config = Config {
+            gutter_padding: 3,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn no_border_rich_no_color() {
+        let config = Config {
+            draw_border: false,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn solarized_dark_rich_color() {
+        let config = Config {
+            styles: Styles::solarized_dark(),
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_color(&config));
+    }
+
+    #[test]
+    fn monokai_rich_color() {
+        let config = Config {
+            styles: Styles::monokai(),
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_color(&config));
+    }
+
+    #[test]
+    fn gruvbox_dark_rich_color() {
+        let config = Config {
+            styles: Styles::gruvbox_dark(),
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_color(&config));
+    }
+
+    #[test]
+    fn dracula_rich_color() {
+        let config = Config {
+            styles: Styles::dracula(),
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_color(&config));
+    }
+}
+
+mod multiline_overlapping {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, String>> = {
+            let file = SimpleFile::new(
+                "codespan/src/file.rs",
+                [
+                    "        match line_index.compare(self.last_line_index()) {",
+                    "            Ordering::Less => Ok(self.line_starts()[line_index.to_usize()]),",
+                    "            Ordering::Equal => Ok(self.source_span().end()),",
+                    "            Ordering::Greater => LineIndexOutOfBoundsError {",
+                    "                given: line_index,",
+                    "                max: self.last_line_index(),",
+                    "            },",
+                    "        }",
+                ].join("\n"),
+            );
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("match arms have incompatible types")
+                    .with_code("E0308")
+                    .with_labels(vec![
+                        // this secondary label is before the primary label to test the locus calculation (see issue #259)
+                        Label::secondary((), 89..134).with_message("this is found to be of type `Result<ByteIndex, LineIndexOutOfBoundsError>`"),
+                        Label::primary((), 230..351).with_message("expected enum `Result`, found struct `LineIndexOutOfBoundsError`"),
+                        Label::secondary((), 8..362).with_message("`match` arms have incompatible types"),
+                        Label::secondary((), 167..195).with_message("this is found to be of type `Result<ByteIndex, LineIndexOutOfBoundsError>`"),
+                    ])
+                    .with_notes(vec![unindent::unindent(
+                        "
+                            expected type `Result<ByteIndex, LineIndexOutOfBoundsError>`
+                               found type `LineIndexOutOfBoundsError`
+                        ",
+                    )]),
+            ];
+
+            TestData { files: file, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(medium_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(medium_no_color);
+    test_emit!(short_no_color);
+    test_emit!(rich_ascii_no_color);
+
+    #[test]
+    fn rich_ascii_is_ascii() {
+        let config = Config {
+            chars: Chars::ascii(),
+            ..TEST_CONFIG.clone()
+        };
+
+        assert!(TEST_DATA.emit_no_color(&config).is_ascii());
+    }
+}
+
+mod tabbed {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add(
+                "tabbed",
+                [
+                    "Entity:",
+                    "\tArmament:",
+                    "\t\tWeapon: DogJaw",
+                    "\t\tReloadingCondition:\tattack-cooldown",
+                    "\tFoo: Bar",
+                ]
+                .join("\n"),
+            );
+
+            let diagnostics = vec![
+                Diagnostic::warning()
+                    .with_message("unknown weapon `DogJaw`")
+                    .with_labels(vec![Label::primary(file_id, 29..35).with_message("the weapon")]),
+                Diagnostic::warning()
+                    .with_message("unknown condition `attack-cooldown`")
+                    .with_labels(vec![Label::primary(file_id, 58..73).with_message("the condition")]),
+                Diagnostic::warning()
+                    .with_message("unknown field `Foo`")
+                    .with_labels(vec![Label::primary(file_id, 75..78).with_message("the field")]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    #[test]
+    fn tab_width_default_no_color() {
+        let config = TEST_CONFIG.clone();
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn tab_width_3_no_color() {
+        let config = Config {
+            tab_width: 3,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn tab_width_6_no_color() {
+        let config = Config {
+            tab_width: 6,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+}
+
+mod highlight_whitespace {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add("whitespace", "let x = 1;  \n\tfoo(a,\tb)\n".to_owned());
+
+            let diagnostics = vec![
+                Diagnostic::warning()
+                    .with_message("trailing whitespace")
+                    .with_labels(vec![Label::primary(file_id, 10..12)]),
+                Diagnostic::warning()
+                    .with_message("mixed tabs and spaces")
+                    .with_labels(vec![
+                        Label::primary(file_id, 20..21).with_message("tab used here"),
+                        Label::secondary(file_id, 18..22).with_message("in these arguments"),
+                    ]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    #[test]
+    fn rich_no_color() {
+        let config = Config {
+            highlight_whitespace: true,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn rich_ascii_no_color() {
+        let config = Config {
+            highlight_whitespace: true,
+            chars: Chars::ascii(),
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+}
+
+mod inline_label_messages {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add(
+                "add",
+                "(+ test \"\")\n(+ 1 \"two\")\n(+ (+ 3\n      4) five)\n".to_owned(),
+            );
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("unexpected type in `+` application")
+                    .with_labels(vec![
+                        Label::primary(file_id, 8..10).with_message("expected `Int`"),
+                        Label::primary(file_id, 17..22),
+                    ]),
+                Diagnostic::error()
+                    .with_message("unexpected type in `+` application")
+                    .with_labels(vec![
+                        Label::primary(file_id, 17..22).with_message("expected `Int`"),
+                        Label::secondary(file_id, 12..13).with_message("added to this"),
+                    ]),
+                Diagnostic::error()
+                    .with_message("unknown variable")
+                    .with_labels(vec![
+                        Label::secondary(file_id, 27..38).with_message("in this expression"),
+                        Label::primary(file_id, 40..44).with_message("not in scope"),
+                    ]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    #[test]
+    fn rich_no_color() {
+        let config = Config {
+            inline_label_messages: true,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn rich_color() {
+        let config = Config {
+            inline_label_messages: true,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_color(&config));
+    }
+}
+
+mod locus_format {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add("add", "(+ test \"\")\n".to_owned());
+
+            let diagnostics = vec![Diagnostic::error()
+                .with_message("unexpected type in `+` application")
+                .with_labels(vec![
+                    Label::primary(file_id, 8..10).with_message("expected `Int`")
+                ])];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    #[test]
+    fn rich_colon_trailing_no_color() {
+        let config = Config {
+            locus_format: LocusFormat::ColonTrailing,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn short_colon_trailing_no_color() {
+        let config = Config {
+            display_style: DisplayStyle::Short,
+            locus_format: LocusFormat::ColonTrailing,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn rich_parens_no_color() {
+        let config = Config {
+            locus_format: LocusFormat::Parens,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn short_parens_no_color() {
+        let config = Config {
+            display_style: DisplayStyle::Short,
+            locus_format: LocusFormat::Parens,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+}
+
+mod tab_columns {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let source = [
+                "\thello",
+                "∙\thello",
+                "∙∙\thello",
+                "∙∙∙\thello",
+                "∙∙∙∙\thello",
+                "∙∙∙∙∙\thello",
+                "∙∙∙∙∙∙\thello",
+            ].join("\n");
+            let hello_ranges = source
+                .match_indices("hello")
+                .map(|(start, hello)| start..(start+hello.len()))
+                .collect::<Vec<_>>();
+
+            let file_id = files.add("tab_columns", source);
+
+            let diagnostics = vec![
+                Diagnostic::warning()
+                    .with_message("tab test")
+                    .with_labels(
+                        hello_ranges
+                            .into_iter()
+                            .map(|range| Label::primary(file_id, range))
+                            .collect(),
+                    ),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    #[test]
+    fn tab_width_default_no_color() {
+        let config = TEST_CONFIG.clone();
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn tab_width_2_no_color() {
+        let config = Config {
+            tab_width: 2,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn tab_width_3_no_color() {
+        let config = Config {
+            tab_width: 3,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn tab_width_6_no_color() {
+        let config = Config {
+            tab_width: 6,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+}
+
+/// Based on:
+/// - https://github.com/TheSamsa/rust/blob/75cf41afb468152611212271bae026948cd3ba46/src/test/ui/codemap_tests/unicode.stderr
+mod unicode {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, String>> = {
+            let prefix = r#"extern "#;
+            let abi = r#""路濫狼á́́""#;
+            let suffix = r#" fn foo() {}"#;
+
+            let file = SimpleFile::new(
+                "unicode.rs",
+                format!("{}{}{}", prefix, abi, suffix),
+            );
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_code("E0703")
+                    .with_message("invalid ABI: found `路濫狼á́́`")
+                    .with_labels(vec![
+                        Label::primary((), prefix.len()..(prefix.len() + abi.len()))
+                            .with_message("invalid ABI"),
+                    ])
+                    .with_notes(vec![unindent::unindent(
+                        "
+                            valid ABIs:
+                              - aapcs
+                              - amdgpu-kernel
+                              - C
+                              - cdecl
+                              - efiapi
+                              - fastcall
+                              - msp430-interrupt
+                              - platform-intrinsic
+                              - ptx-kernel
+                              - Rust
+                              - rust-call
+                              - rust-intrinsic
+                              - stdcall
+                              - system
+                              - sysv64
+                              - thiscall
+                              - unadjusted
+                              - vectorcall
+                              - win64
+                              - x86-interrupt
+                        ",
+                    )]),
+                Diagnostic::error()
+                    .with_message("aborting due to previous error")
+                    .with_notes(vec![
+                        "For more information about this error, try `rustc --explain E0703`.".to_owned(),
+                    ]),
+            ];
+
+            TestData { files: file, diagnostics }
+        };
+    }
+
+    test_emit!(rich_no_color);
+    test_emit!(medium_no_color);
+    test_emit!(short_no_color);
+}
+
+mod unicode_spans {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, String>> = {
+            let moon_phases = format!("{}", r#"🐄🌑🐄🌒🐄🌓🐄🌔🐄🌕🐄🌖🐄🌗🐄🌘🐄"#);
+            let invalid_start = 1;
+            let invalid_end = "🐄".len() - 1;
+            assert_eq!(moon_phases.is_char_boundary(invalid_start), false);
+            assert_eq!(moon_phases.is_char_boundary(invalid_end), false);
+            assert_eq!("🐄".len(), 4);
+            let file = SimpleFile::new(
+                "moon_jump.rs",
+                moon_phases,
+            );
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_code("E01")
+                    .with_message("cow may not jump during new moon.")
+                    .with_labels(vec![
+                        Label::primary((), invalid_start..invalid_end)
+                            .with_message("Invalid jump"),
+                    ]),
+                Diagnostic::note()
+                    .with_message("invalid unicode range")
+                    .with_labels(vec![
+                        Label::secondary((), invalid_start.."🐄".len())
+                            .with_message("Cow range does not start at boundary."),
+                    ]),
+                Diagnostic::note()
+                    .with_message("invalid unicode range")
+                    .with_labels(vec![
+                        Label::secondary((), "🐄🌑".len().."🐄🌑🐄".len() - 1)
+                            .with_message("Cow range does not end at boundary."),
+                    ]),
+                Diagnostic::note()
+                    .with_message("invalid unicode range")
+                    .with_labels(vec![
+                        Label::secondary((), invalid_start.."🐄🌑🐄".len() - 1)
+                            .with_message("Cow does not start or end at boundary."),
+                    ]),
+            ];
+            TestData{files: file, diagnostics }
+        };
+    }
+
+    test_emit!(rich_no_color);
+    test_emit!(medium_no_color);
+    test_emit!(short_no_color);
+}
+
+mod position_indicator {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, String>> = {
+            let file = SimpleFile::new(
+                "tests/main.js",
+                [
+                    "\"use strict\";",
+                    "let zero=0;",
+                    "function foo() {",
+                    "  \"use strict\";",
+                    "  one=1;",
+                    "}",
+                ].join("\n"),
+            );
+            let diagnostics = vec![
+                Diagnostic::warning()
+                    .with_code("ParserWarning")
+                    .with_message("The strict mode declaration in the body of function `foo` is redundant, as the outer scope is already in strict mode")
+                    .with_labels(vec![
+                        Label::primary((), 45..57)
+                            .with_message("This strict mode declaration is redundant"),
+                        Label::secondary((), 0..12)
+                            .with_message("Strict mode is first declared here"),
+                    ]),
+            ];
+            TestData{files: file, diagnostics }
+        };
+    }
+
+    test_emit!(rich_no_color);
+    test_emit!(medium_no_color);
+    test_emit!(short_no_color);
+    test_emit!(rich_ascii_no_color);
+}
 
-                        _ : Nat
-                        _ = 123 + "hello"
-                    "#,
-                ),
+mod multiline_omit {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_CONFIG: Config = Config {
+            styles: Styles::with_blue(Color::Blue),
+            start_context_lines: 2,
+            end_context_lines: 1,
+            ..Config::default()
+        };
+
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id1 = files.add(
+                "empty_if_comments.lua",
+                [
+                    "elseif 3 then", // primary label starts here
+                    "",              // context line
+                    "",
+                    "",
+                    "",
+                    "",
+                    "",
+                    "",
+                    "",     // context line
+                    "else", // primary label ends here
+                ]
+                .join("\n"),
+            );
+
+            let file_id2 = files.add(
+                "src/lib.rs",
+                [
+                    "fn main() {",
+                    "    1",   // primary label starts here
+                    "    + 1", // context line
+                    "    + 1", // skip
+                    "    + 1", // skip
+                    "    + 1", // skip
+                    "    +1",  // secondary label here
+                    "    + 1", // this single line will not be skipped; the previously filtered out label must be retrieved
+                    "    + 1", // context line
+                    "    + 1", // primary label ends here
+                    "}",
+                ]
+                .join("\n"),
             );
 
             let diagnostics = vec![
-                // Unknown builtin error
                 Diagnostic::error()
-                    .with_message("unknown builtin: `NATRAL`")
-                    .with_labels(vec![Label::primary(file_id1, 96..102).with_message("unknown builtin")])
-                    .with_notes(vec![
-                        "there is a builtin with a similar name: `NATURAL`".to_owned(),
+                    .with_message("empty elseif block")
+                    .with_code("empty_if")
+                    .with_labels(vec![
+                        Label::primary(file_id1, 0..23),
+                        Label::secondary(file_id1, 15..21).with_message("content should be in here"),
                     ]),
-                // Unused parameter warning
-                Diagnostic::warning()
-                    .with_message("unused parameter pattern: `n₂`")
-                    .with_labels(vec![Label::primary(file_id1, 285..289).with_message("unused parameter")])
-                    .with_notes(vec!["consider using a wildcard pattern: `_`".to_owned()]),
-                // Unexpected type error
                 Diagnostic::error()
-                    .with_message("unexpected type in application of `_+_`")
-                    .with_code("E0001")
+                    .with_message("mismatched types")
+                    .with_code("E0308")
                     .with_labels(vec![
-                        Label::primary(file_id2, 37..44).with_message("expected `Nat`, found `String`"),
-                        Label::secondary(file_id1, 130..155).with_message("based on the definition of `_+_`"),
+                        Label::primary(file_id2, 17..80).with_message("expected (), found integer"),
+                        Label::secondary(file_id2, 55..55).with_message("missing whitespace"),
                     ])
-                    .with_notes(vec![unindent::unindent(
-                        "
-                            expected type `Nat`
-                               found type `String`
-                        ",
-                    )]),
+                    .with_notes(vec![
+                        "note:\texpected type `()`\n\tfound type `{integer}`".to_owned()
+                    ]),
             ];
 
             TestData { files, diagnostics }
         };
     }
 
-    test_emit!(rich_color);
-    test_emit!(medium_color);
-    test_emit!(short_color);
     test_emit!(rich_no_color);
-    test_emit!(medium_no_color);
-    test_emit!(short_no_color);
-    test_emit!(rich_ascii_no_color);
+
+    #[test]
+    fn multi_line_context_rich_no_color() {
+        let config = Config {
+            multi_line_context: Some(1),
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
 }
 
-mod fizz_buzz {
+mod surrounding_lines {
     use super::*;
 
     lazy_static::lazy_static! {
+        static ref TEST_CONFIG: Config = Config {
+            styles: Styles::with_blue(Color::Blue),
+            before_label_lines: 2,
+            after_label_lines: 1,
+            ..Config::default()
+        };
         static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
             let mut files = SimpleFiles::new();
 
             let file_id = files.add(
-                "FizzBuzz.fun",
+                "surroundingLines.fun",
                 unindent::unindent(
                     r#"
-                        module FizzBuzz where
-
-                        fizz₁ : Nat → String
-                        fizz₁ num = case (mod num 5) (mod num 3) of
-                            0 0 => "FizzBuzz"
-                            0 _ => "Fizz"
-                            _ 0 => "Buzz"
-                            _ _ => num
+                    #[foo]
+                    fn main() {
+                        println!(
+                            "{}",
+                            Foo
+                        );
+                    }
 
-                        fizz₂ : Nat → String
-                        fizz₂ num =
-                            case (mod num 5) (mod num 3) of
-                                0 0 => "FizzBuzz"
-                                0 _ => "Fizz"
-                                _ 0 => "Buzz"
-                                _ _ => num
-                    "#,
+                    struct Foo"#,
                 ),
             );
 
             let diagnostics = vec![
-                // Incompatible match clause error
                 Diagnostic::error()
-                    .with_message("`case` clauses have incompatible types")
-                    .with_code("E0308")
+                    .with_message("Unknown attribute macro")
                     .with_labels(vec![
-                        Label::primary(file_id, 163..166).with_message("expected `String`, found `Nat`"),
-                        Label::secondary(file_id, 62..166).with_message("`case` clauses have incompatible types"),
-                        Label::secondary(file_id, 41..47).with_message("expected type `String` found here"),
-                    ])
-                    .with_notes(vec![unindent::unindent(
-                        "
-                            expected type `String`
-                               found type `Nat`
-                        ",
-                    )]),
-                // Incompatible match clause error
+                        Label::primary(file_id, 2..5).with_message("No attribute macro `foo` known"),
+                    ]),
                 Diagnostic::error()
-                    .with_message("`case` clauses have incompatible types")
-                    .with_code("E0308")
+                    .with_message("Missing argument for format")
                     .with_labels(vec![
-                        Label::primary(file_id, 328..331).with_message("expected `String`, found `Nat`"),
-                        Label::secondary(file_id, 211..331).with_message("`case` clauses have incompatible types"),
-                        Label::secondary(file_id, 258..268).with_message("this is found to be of type `String`"),
-                        Label::secondary(file_id, 284..290).with_message("this is found to be of type `String`"),
-                        Label::secondary(file_id, 306..312).with_message("this is found to be of type `String`"),
-                        Label::secondary(file_id, 186..192).with_message("expected type `String` found here"),
-                    ])
-                    .with_notes(vec![unindent::unindent(
-                        "
-                            expected type `String`
-                               found type `Nat`
-                        ",
-                    )]),
+                        Label::primary(file_id, 55..58).with_message("No instance of std::fmt::Display exists for type Foo"),
+                        Label::secondary(file_id, 42..44).with_message("Unable to use `{}`-directive to display `Foo`"),
+                    ]),
+                Diagnostic::error()
+                    .with_message("Syntax error")
+                    .with_labels(vec![
+                        Label::primary(file_id, 79..79).with_message("Missing a semicolon"),
+                    ]),
+                Diagnostic::warning()
+                    .with_message("Unused struct")
+                    .with_labels(vec![
+                        Label::primary(file_id, 76..79).with_message("Never constructed"),
+                        Label::secondary(file_id, 0..6).with_message("Not applicable to structs"),
+                    ]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_no_color);
+}
+
+mod border_by_severity {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_CONFIG: Config = {
+            let border = |color| ColorSpec::new().set_fg(Some(color)).clone();
+
+            Config {
+                styles: Styles {
+                    source_border_by_severity: Some([
+                        border(Color::Cyan),
+                        border(Color::Green),
+                        border(Color::Yellow),
+                        border(Color::Red),
+                        border(Color::Magenta),
+                    ]),
+                    ..Styles::with_blue(Color::Blue)
+                },
+                ..Config::default()
+            }
+        };
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add(
+                "test",
+                unindent::unindent(r#"
+                    (let test "")
+                    (+ test "")
+                "#),
+            );
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("unexpected type in `+` application")
+                    .with_labels(vec![
+                        Label::primary(file_id, 22..24)
+                            .with_message("expected `Int` but found `String`"),
+                    ]),
+                Diagnostic::note()
+                    .with_message("`test` defined here")
+                    .with_labels(vec![
+                        Label::primary(file_id, 5..9),
+                    ]),
             ];
 
             TestData { files, diagnostics }
@@ -625,257 +2024,300 @@ mod fizz_buzz {
     }
 
     test_emit!(rich_color);
-    test_emit!(medium_color);
-    test_emit!(short_color);
-    test_emit!(rich_no_color);
-    test_emit!(medium_no_color);
-    test_emit!(short_no_color);
-    test_emit!(rich_ascii_no_color);
 }
 
-mod multiline_overlapping {
+mod show_index {
     use super::*;
 
     lazy_static::lazy_static! {
-        static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, String>> = {
-            let file = SimpleFile::new(
-                "codespan/src/file.rs",
-                [
-                    "        match line_index.compare(self.last_line_index()) {",
-                    "            Ordering::Less => Ok(self.line_starts()[line_index.to_usize()]),",
-                    "            Ordering::Equal => Ok(self.source_span().end()),",
-                    "            Ordering::Greater => LineIndexOutOfBoundsError {",
-                    "                given: line_index,",
-                    "                max: self.last_line_index(),",
-                    "            },",
-                    "        }",
-                ].join("\n"),
+        static ref TEST_CONFIG: Config = Config {
+            styles: Styles::with_blue(Color::Blue),
+            show_index: true,
+            ..Config::default()
+        };
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add(
+                "test",
+                unindent::unindent(r#"
+                    (let test "")
+                    (+ test "")
+                "#),
             );
 
             let diagnostics = vec![
                 Diagnostic::error()
-                    .with_message("match arms have incompatible types")
-                    .with_code("E0308")
+                    .with_code("E0001")
+                    .with_message("unexpected type in `+` application")
                     .with_labels(vec![
-                        // this secondary label is before the primary label to test the locus calculation (see issue #259)
-                        Label::secondary((), 89..134).with_message("this is found to be of type `Result<ByteIndex, LineIndexOutOfBoundsError>`"),
-                        Label::primary((), 230..351).with_message("expected enum `Result`, found struct `LineIndexOutOfBoundsError`"),
-                        Label::secondary((), 8..362).with_message("`match` arms have incompatible types"),
-                        Label::secondary((), 167..195).with_message("this is found to be of type `Result<ByteIndex, LineIndexOutOfBoundsError>`"),
-                    ])
-                    .with_notes(vec![unindent::unindent(
-                        "
-                            expected type `Result<ByteIndex, LineIndexOutOfBoundsError>`
-                               found type `LineIndexOutOfBoundsError`
-                        ",
-                    )]),
+                        Label::primary(file_id, 22..24)
+                            .with_message("expected `Int` but found `String`"),
+                    ]),
+                Diagnostic::warning()
+                    .with_message("unused binding")
+                    .with_labels(vec![
+                        Label::primary(file_id, 5..9),
+                    ]),
+                Diagnostic::error()
+                    .with_code("E0002")
+                    .with_message("Bad config found"),
             ];
 
-            TestData { files: file, diagnostics }
+            TestData { files, diagnostics }
         };
     }
 
     test_emit!(rich_color);
-    test_emit!(medium_color);
-    test_emit!(short_color);
-    test_emit!(rich_no_color);
-    test_emit!(medium_no_color);
     test_emit!(short_no_color);
-    test_emit!(rich_ascii_no_color);
 }
 
-mod tabbed {
+mod file_sort {
     use super::*;
 
     lazy_static::lazy_static! {
         static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
             let mut files = SimpleFiles::new();
 
-            let file_id = files.add(
-                "tabbed",
-                [
-                    "Entity:",
-                    "\tArmament:",
-                    "\t\tWeapon: DogJaw",
-                    "\t\tReloadingCondition:\tattack-cooldown",
-                    "\tFoo: Bar",
-                ]
-                .join("\n"),
+            let file_id1 = files.add(
+                "macros.rs",
+                unindent::unindent(r#"
+                    macro_rules! add {
+                        ($a:expr, $b:expr) => { $a + $b };
+                    }
+                "#),
+            );
+            let file_id2 = files.add(
+                "main.rs",
+                unindent::unindent(r#"
+                    fn main() {
+                        let x: u32 = add!(1, "two");
+                    }
+                "#),
             );
 
             let diagnostics = vec![
-                Diagnostic::warning()
-                    .with_message("unknown weapon `DogJaw`")
-                    .with_labels(vec![Label::primary(file_id, 29..35).with_message("the weapon")]),
-                Diagnostic::warning()
-                    .with_message("unknown condition `attack-cooldown`")
-                    .with_labels(vec![Label::primary(file_id, 58..73).with_message("the condition")]),
-                Diagnostic::warning()
-                    .with_message("unknown field `Foo`")
-                    .with_labels(vec![Label::primary(file_id, 75..78).with_message("the field")]),
+                Diagnostic::error()
+                    .with_code("E0277")
+                    .with_message("cannot add `&str` to `{integer}`")
+                    .with_labels(vec![
+                        Label::secondary(file_id1, 47..54)
+                            .with_message("in this macro expansion"),
+                        Label::primary(file_id2, 29..43)
+                            .with_message("no implementation for `{integer} + &str`"),
+                    ]),
             ];
 
             TestData { files, diagnostics }
         };
     }
 
-    #[test]
-    fn tab_width_default_no_color() {
-        let config = TEST_CONFIG.clone();
-
-        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
-    }
+    test_emit!(rich_no_color);
 
     #[test]
-    fn tab_width_3_no_color() {
+    fn primary_first_rich_no_color() {
         let config = Config {
-            tab_width: 3,
+            file_sort: FileSortOrder::PrimaryFirst,
             ..TEST_CONFIG.clone()
         };
 
         insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
     }
+}
 
-    #[test]
-    fn tab_width_6_no_color() {
-        let config = Config {
-            tab_width: 6,
-            ..TEST_CONFIG.clone()
+mod severity_names {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_CONFIG: Config = Config {
+            styles: Styles {
+                header_note: ColorSpec::new().set_fg(Some(Color::Magenta)).set_bold(true).clone(),
+                ..Styles::with_blue(Color::Blue)
+            },
+            severity_names: SeverityNames {
+                warning: "style".to_owned(),
+                note: "perf".to_owned(),
+                ..SeverityNames::default()
+            },
+            ..Config::default()
         };
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
 
-        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+            let file_id = files.add(
+                "main.rs",
+                unindent::unindent(r#"
+                    fn main() {
+                        let names = vec![String::from("a")];
+                        let first = names.clone().into_iter().next();
+                    }
+                "#),
+            );
+
+            let diagnostics = vec![
+                Diagnostic::note()
+                    .with_code("P001")
+                    .with_message("redundant clone")
+                    .with_labels(vec![
+                        Label::primary(file_id, 74..82).with_message("this clone is unnecessary"),
+                    ]),
+                Diagnostic::warning()
+                    .with_code("S001")
+                    .with_message("unused variable: `first`")
+                    .with_labels(vec![
+                        Label::primary(file_id, 61..66),
+                    ]),
+            ];
+
+            TestData { files, diagnostics }
+        };
     }
+
+    test_emit!(rich_color);
+    test_emit!(short_no_color);
 }
 
-mod tab_columns {
+mod terminal_width {
     use super::*;
 
     lazy_static::lazy_static! {
+        static ref TEST_CONFIG: Config = Config {
+            styles: Styles::with_blue(Color::Blue),
+            terminal_width: Some(60),
+            ..Config::default()
+        };
         static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
             let mut files = SimpleFiles::new();
 
-            let source = [
-                "\thello",
-                "∙\thello",
-                "∙∙\thello",
-                "∙∙∙\thello",
-                "∙∙∙∙\thello",
-                "∙∙∙∙∙\thello",
-                "∙∙∙∙∙∙\thello",
-            ].join("\n");
-            let hello_ranges = source
-                .match_indices("hello")
-                .map(|(start, hello)| start..(start+hello.len()))
-                .collect::<Vec<_>>();
-
-            let file_id = files.add("tab_columns", source);
+            let file_id = files.add(
+                "query.sql",
+                [
+                    "SELECT id, name, email, created_at, updated_at, last_login, status, role, \
+                     department, manager_id FROM users WHERE status = 'active' AND role = 'admin' \
+                     AND department = 'engineering' ORDER BY created_at DESC LIMIT 10;\n",
+                    "SELECT * FROM users;\n",
+                    "\tSELECT\tid,\tname,\temail,\tcreated_at,\tupdated_at,\tlast_login\tFROM\tusers\t\
+                     WHERE\tstatus\t=\t'inactive';\n",
+                ]
+                .concat(),
+            );
 
             let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("unknown department")
+                    .with_labels(vec![
+                        Label::primary(file_id, 168..181).with_message("no such department"),
+                        Label::secondary(file_id, 155..165).with_message("compared here"),
+                    ]),
                 Diagnostic::warning()
-                    .with_message("tab test")
-                    .with_labels(
-                        hello_ranges
-                            .into_iter()
-                            .map(|range| Label::primary(file_id, range))
-                            .collect(),
-                    ),
+                    .with_message("unbounded query")
+                    .with_labels(vec![
+                        Label::primary(file_id, 231..236).with_message("consider adding a `LIMIT`"),
+                    ]),
+                Diagnostic::error()
+                    .with_message("unknown status")
+                    .with_labels(vec![
+                        Label::primary(file_id, 324..334).with_message("no such status"),
+                        Label::secondary(file_id, 315..321),
+                    ]),
             ];
 
             TestData { files, diagnostics }
         };
     }
 
-    #[test]
-    fn tab_width_default_no_color() {
-        let config = TEST_CONFIG.clone();
+    test_emit!(rich_no_color);
+    test_emit!(rich_ascii_no_color);
+}
 
-        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
-    }
+mod inline_label_severity {
+    use super::*;
 
-    #[test]
-    fn tab_width_2_no_color() {
-        let config = Config {
-            tab_width: 2,
-            ..TEST_CONFIG.clone()
+    lazy_static::lazy_static! {
+        static ref TEST_CONFIG: Config = Config {
+            styles: Styles::with_blue(Color::Blue),
+            inline_label_severity: true,
+            ..Config::default()
         };
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
 
-        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
-    }
+            let file_id = files.add(
+                "main.rs",
+                unindent::unindent(r#"
+                    fn main() {
+                        let unused: u32 = "zero";
+                    }
+                "#),
+            );
 
-    #[test]
-    fn tab_width_3_no_color() {
-        let config = Config {
-            tab_width: 3,
-            ..TEST_CONFIG.clone()
-        };
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_code("E0308")
+                    .with_message("mismatched types")
+                    .with_labels(vec![
+                        Label::primary(file_id, 34..40).with_message("expected `u32`, found `&str`"),
+                        Label::secondary(file_id, 28..31).with_message("expected due to this"),
+                    ]),
+                Diagnostic::warning()
+                    .with_message("unused variable: `unused`")
+                    .with_labels(vec![
+                        Label::primary(file_id, 20..26).with_message("never used after this binding"),
+                    ]),
+            ];
 
-        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+            TestData { files, diagnostics }
+        };
     }
 
-    #[test]
-    fn tab_width_6_no_color() {
-        let config = Config {
-            tab_width: 6,
-            ..TEST_CONFIG.clone()
+    test_emit!(rich_no_color);
+}
+
+mod suppress_severity_word {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_CONFIG: Config = Config {
+            styles: Styles::with_blue(Color::Blue),
+            suppress_severity_word: true,
+            ..Config::default()
         };
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, &'static str>> = {
+            let files = SimpleFiles::new();
 
-        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+            let diagnostics = vec![
+                Diagnostic::note().with_message("Compiling module Foo"),
+                Diagnostic::help().with_code("I0001").with_message("Compiling module Bar"),
+            ];
+
+            TestData { files, diagnostics }
+        };
     }
-}
 
-/// Based on:
-/// - https://github.com/TheSamsa/rust/blob/75cf41afb468152611212271bae026948cd3ba46/src/test/ui/codemap_tests/unicode.stderr
-mod unicode {
+    test_emit!(rich_color);
+    test_emit!(short_no_color);
+}
+
+mod unordered_primary_labels {
     use super::*;
 
     lazy_static::lazy_static! {
         static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, String>> = {
-            let prefix = r#"extern "#;
-            let abi = r#""路濫狼á́́""#;
-            let suffix = r#" fn foo() {}"#;
-
-            let file = SimpleFile::new(
-                "unicode.rs",
-                format!("{}{}{}", prefix, abi, suffix),
-            );
+            let file = SimpleFile::new("unordered.rs", unindent::unindent(r#"
+                fn main() {
+                    let x = 1;
+                    let y = x + z;
+                }
+            "#));
 
             let diagnostics = vec![
                 Diagnostic::error()
-                    .with_code("E0703")
-                    .with_message("invalid ABI: found `路濫狼á́́`")
+                    .with_message("cannot find value `z` in this scope")
                     .with_labels(vec![
-                        Label::primary((), prefix.len()..(prefix.len() + abi.len()))
-                            .with_message("invalid ABI"),
-                    ])
-                    .with_notes(vec![unindent::unindent(
-                        "
-                            valid ABIs:
-                              - aapcs
-                              - amdgpu-kernel
-                              - C
-                              - cdecl
-                              - efiapi
-                              - fastcall
-                              - msp430-interrupt
-                              - platform-intrinsic
-                              - ptx-kernel
-                              - Rust
-                              - rust-call
-                              - rust-intrinsic
-                              - stdcall
-                              - system
-                              - sysv64
-                              - thiscall
-                              - unadjusted
-                              - vectorcall
-                              - win64
-                              - x86-interrupt
-                        ",
-                    )]),
-                Diagnostic::error()
-                    .with_message("aborting due to previous error")
-                    .with_notes(vec![
-                        "For more information about this error, try `rustc --explain E0703`.".to_owned(),
+                        Label::primary((), 43..44).with_message("not found in this scope"),
+                        Label::primary((), 20..21).with_message("a local variable with a similar name exists"),
+                        Label::primary((), 35..36).with_message("a local variable with a similar name exists"),
+                        Label::secondary((), 0..7),
                     ]),
             ];
 
@@ -884,164 +2326,131 @@ mod unicode {
     }
 
     test_emit!(rich_no_color);
-    test_emit!(medium_no_color);
     test_emit!(short_no_color);
+
+    #[test]
+    fn rich_and_short_locus_agree() {
+        let rich = TEST_DATA.emit_no_color(&Config {
+            display_style: DisplayStyle::Rich,
+            ..TEST_CONFIG.clone()
+        });
+        let short = TEST_DATA.emit_no_color(&Config {
+            display_style: DisplayStyle::Short,
+            ..TEST_CONFIG.clone()
+        });
+
+        let rich_locus = rich
+            .lines()
+            .find_map(|line| line.trim_start().strip_prefix("┌─ "))
+            .unwrap();
+        let short_locus = short.lines().next().unwrap().split(": ").next().unwrap();
+
+        assert_eq!(rich_locus, "unordered.rs:2:9");
+        assert_eq!(short_locus, rich_locus);
+    }
 }
 
-mod unicode_spans {
+mod suggestions {
     use super::*;
 
     lazy_static::lazy_static! {
         static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, String>> = {
-            let moon_phases = format!("{}", r#"🐄🌑🐄🌒🐄🌓🐄🌔🐄🌕🐄🌖🐄🌗🐄🌘🐄"#);
-            let invalid_start = 1;
-            let invalid_end = "🐄".len() - 1;
-            assert_eq!(moon_phases.is_char_boundary(invalid_start), false);
-            assert_eq!(moon_phases.is_char_boundary(invalid_end), false);
-            assert_eq!("🐄".len(), 4);
-            let file = SimpleFile::new(
-                "moon_jump.rs",
-                moon_phases,
-            );
+            let file = SimpleFile::new("suggestions.rs", unindent::unindent(r#"
+                fn main() {
+                    let s = String::from("hi");
+                    takes_str(s);
+                    let n = 1
+                    if n > 0 { println!("{}", n) }
+                }
+            "#));
+
             let diagnostics = vec![
                 Diagnostic::error()
-                    .with_code("E01")
-                    .with_message("cow may not jump during new moon.")
-                    .with_labels(vec![
-                        Label::primary((), invalid_start..invalid_end)
-                            .with_message("Invalid jump"),
-                    ]),
-                Diagnostic::note()
-                    .with_message("invalid unicode range")
-                    .with_labels(vec![
-                        Label::secondary((), invalid_start.."🐄".len())
-                            .with_message("Cow range does not start at boundary."),
-                    ]),
-                Diagnostic::note()
-                    .with_message("invalid unicode range")
+                    .with_code("E0308")
+                    .with_message("mismatched types")
                     .with_labels(vec![
-                        Label::secondary((), "🐄🌑".len().."🐄🌑🐄".len() - 1)
-                            .with_message("Cow range does not end at boundary."),
+                        Label::primary((), 58..59).with_message("expected `&str`, found struct `String`"),
+                    ])
+                    .with_suggestions(vec![
+                        Suggestion::new((), 58..58, "&").with_message("consider borrowing here"),
                     ]),
-                Diagnostic::note()
-                    .with_message("invalid unicode range")
+                Diagnostic::error()
+                    .with_message("expected `;`, found keyword `if`")
                     .with_labels(vec![
-                        Label::secondary((), invalid_start.."🐄🌑🐄".len() - 1)
-                            .with_message("Cow does not start or end at boundary."),
+                        Label::primary((), 75..75).with_message("expected `;`"),
+                    ])
+                    .with_suggestions(vec![
+                        Suggestion::new((), 75..75, ";").with_message("add `;` here"),
+                        Suggestion::new((), 80..110, "if n > 0 {\n        println!(\"{}\", n);\n    }")
+                            .with_message("consider splitting the block over several lines"),
                     ]),
             ];
-            TestData{files: file, diagnostics }
+
+            TestData { files: file, diagnostics }
         };
     }
 
-    test_emit!(rich_no_color);
+    test_emit!(rich_color);
     test_emit!(medium_no_color);
     test_emit!(short_no_color);
+    test_emit!(rich_no_color);
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn json() {
+        insta::assert_snapshot!(TEST_DATA.emit_json());
+    }
 }
 
-mod position_indicator {
+mod combining_marks {
     use super::*;
 
     lazy_static::lazy_static! {
-        static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, String>> = {
-            let file = SimpleFile::new(
-                "tests/main.js",
-                [
-                    "\"use strict\";",
-                    "let zero=0;",
-                    "function foo() {",
-                    "  \"use strict\";",
-                    "  one=1;",
-                    "}",
-                ].join("\n"),
-            );
+        static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, &'static str>> = {
+            let file = SimpleFile::new("combining_marks", "let a\u{0301}\u{0301} = \"a\u{0301}\u{0301}\";");
+
             let diagnostics = vec![
-                Diagnostic::warning()
-                    .with_code("ParserWarning")
-                    .with_message("The strict mode declaration in the body of function `foo` is redundant, as the outer scope is already in strict mode")
+                Diagnostic::error()
+                    .with_message("unexpected accents")
                     .with_labels(vec![
-                        Label::primary((), 45..57)
-                            .with_message("This strict mode declaration is redundant"),
-                        Label::secondary((), 0..12)
-                            .with_message("Strict mode is first declared here"),
+                        Label::primary((), 5..9).with_message("accents"),
+                        Label::secondary((), 13..18).with_message("accented character"),
                     ]),
+                Diagnostic::warning()
+                    .with_message("unexpected second accent")
+                    .with_labels(vec![Label::primary((), 7..9).with_message("second accent")]),
             ];
-            TestData{files: file, diagnostics }
+
+            TestData { files: file, diagnostics }
         };
     }
 
     test_emit!(rich_no_color);
-    test_emit!(medium_no_color);
-    test_emit!(short_no_color);
-    test_emit!(rich_ascii_no_color);
 }
 
-mod multiline_omit {
+mod numbered_labels {
     use super::*;
 
     lazy_static::lazy_static! {
         static ref TEST_CONFIG: Config = Config {
-            styles: Styles::with_blue(Color::Blue),
-            start_context_lines: 2,
-            end_context_lines: 1,
+            number_labels: true,
             ..Config::default()
         };
 
-        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, &'static str>> = {
             let mut files = SimpleFiles::new();
 
-            let file_id1 = files.add(
-                "empty_if_comments.lua",
-                [
-                    "elseif 3 then", // primary label starts here
-                    "",              // context line
-                    "",
-                    "",
-                    "",
-                    "",
-                    "",
-                    "",
-                    "",     // context line
-                    "else", // primary label ends here
-                ]
-                .join("\n"),
-            );
-
-            let file_id2 = files.add(
-                "src/lib.rs",
-                [
-                    "fn main() {",
-                    "    1",   // primary label starts here
-                    "    + 1", // context line
-                    "    + 1", // skip
-                    "    + 1", // skip
-                    "    + 1", // skip
-                    "    +1",  // secondary label here
-                    "    + 1", // this single line will not be skipped; the previously filtered out label must be retrieved
-                    "    + 1", // context line
-                    "    + 1", // primary label ends here
-                    "}",
-                ]
-                .join("\n"),
-            );
+            let lib_id = files.add("lib.rs", "fn add(a: u32, b: u32) -> u32 {\n    a + b\n}\n");
+            let main_id = files.add("main.rs", "fn main() {\n    let x = add(1, \"two\");\n}\n");
 
             let diagnostics = vec![
                 Diagnostic::error()
-                    .with_message("empty elseif block")
-                    .with_code("empty_if")
-                    .with_labels(vec![
-                        Label::primary(file_id1, 0..23),
-                        Label::secondary(file_id1, 15..21).with_message("content should be in here"),
-                    ]),
-                Diagnostic::error()
-                    .with_message("mismatched types")
-                    .with_code("E0308")
+                    .with_message("mismatched types in call to `add`, see [3]")
                     .with_labels(vec![
-                        Label::primary(file_id2, 17..80).with_message("expected (), found integer"),
-                        Label::secondary(file_id2, 55..55).with_message("missing whitespace"),
-                    ])
-                    .with_notes(vec![
-                        "note:\texpected type `()`\n\tfound type `{integer}`".to_owned()
+                        Label::primary(main_id, 31..36).with_message("expected `u32`, found `&str`"),
+                        Label::secondary(main_id, 24..27),
+                        Label::secondary(lib_id, 15..21).with_message("parameter defined here"),
+                        Label::secondary(lib_id, 30..43).with_message("function defined here"),
                     ]),
             ];
 
@@ -1052,57 +2461,117 @@ mod multiline_omit {
     test_emit!(rich_no_color);
 }
 
-mod surrounding_lines {
+mod column_guide {
     use super::*;
 
     lazy_static::lazy_static! {
         static ref TEST_CONFIG: Config = Config {
-            styles: Styles::with_blue(Color::Blue),
-            before_label_lines: 2,
-            after_label_lines: 1,
+            show_column_guide: true,
+            column_guide_threshold: 12,
             ..Config::default()
         };
-        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
-            let mut files = SimpleFiles::new();
-
-            let file_id = files.add(
-                "surroundingLines.fun",
-                unindent::unindent(
-                    r#"
-                    #[foo]
-                    fn main() {
-                        println!(
-                            "{}",
-                            Foo
-                        );
-                    }
 
-                    struct Foo"#,
-                ),
-            );
+        static ref TEST_DATA: TestData<'static, SimpleFile<&'static str, &'static str>> = {
+            let file = SimpleFile::new("cities.tsv", "id\tname\tcity\tscore\n1\talice\t東京\tabc\n");
 
             let diagnostics = vec![
                 Diagnostic::error()
-                    .with_message("Unknown attribute macro")
-                    .with_labels(vec![
-                        Label::primary(file_id, 2..5).with_message("No attribute macro `foo` known"),
-                    ]),
-                Diagnostic::error()
-                    .with_message("Missing argument for format")
+                    .with_message("expected a number")
                     .with_labels(vec![
-                        Label::primary(file_id, 55..58).with_message("No instance of std::fmt::Display exists for type Foo"),
-                        Label::secondary(file_id, 42..44).with_message("Unable to use `{}`-directive to display `Foo`"),
+                        Label::primary((), 34..37).with_message("not a number"),
+                        Label::secondary((), 13..18).with_message("in this column"),
                     ]),
+                Diagnostic::warning()
+                    .with_message("city name is not ASCII")
+                    .with_labels(vec![Label::primary((), 27..33)]),
+            ];
+
+            TestData { files: file, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(rich_no_color);
+    test_emit!(rich_ascii_no_color);
+}
+
+mod custom_line_numbers {
+    use codespan_reporting::files::{self, Files};
+    use std::ops::Range;
+
+    use super::*;
+
+    /// A file database that numbers lines in steps of ten, starting at zero.
+    struct TenfoldLineNumbers {
+        file: SimpleFile<&'static str, String>,
+    }
+
+    impl<'a> Files<'a> for TenfoldLineNumbers {
+        type FileId = ();
+        type Name = &'static str;
+        type Source = &'a str;
+
+        fn name(&'a self, (): ()) -> Result<&'static str, files::Error> {
+            Files::name(&self.file, ())
+        }
+
+        fn source(&'a self, (): ()) -> Result<&'a str, files::Error> {
+            Files::source(&self.file, ())
+        }
+
+        fn line_index(&'a self, (): (), byte_index: usize) -> Result<usize, files::Error> {
+            self.file.line_index((), byte_index)
+        }
+
+        fn line_number(&'a self, (): (), line_index: usize) -> Result<usize, files::Error> {
+            Ok(line_index * 10)
+        }
+
+        fn line_range(&'a self, (): (), line_index: usize) -> Result<Range<usize>, files::Error> {
+            self.file.line_range((), line_index)
+        }
+    }
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, TenfoldLineNumbers> = {
+            let file = SimpleFile::new("tenfold", unindent::unindent(r#"
+                10 PRINT "HELLO"
+                20 LET X = 1
+                30 IF X > 0 THEN
+                40   GOTO 10
+                50 END IF
+                60 REM
+                70 REM
+                80 REM
+                90 PRINT X
+                100 END
+            "#));
+
+            let diagnostics = vec![
                 Diagnostic::error()
-                    .with_message("Syntax error")
+                    .with_message("infinite loop")
                     .with_labels(vec![
-                        Label::primary(file_id, 79..79).with_message("Missing a semicolon"),
+                        Label::primary((), 30..69).with_message("this loops forever"),
+                        Label::secondary((), 17..29).with_message("because of this"),
+                        Label::secondary((), 91..101).with_message("unreachable"),
                     ]),
             ];
 
-            TestData { files, diagnostics }
+            TestData { files: TenfoldLineNumbers { file }, diagnostics }
         };
     }
 
     test_emit!(rich_no_color);
+    test_emit!(short_no_color);
+
+    #[test]
+    fn context_lines_rich_no_color() {
+        let config = Config {
+            before_label_lines: 1,
+            after_label_lines: 1,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
 }
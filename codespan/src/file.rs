@@ -1,8 +1,10 @@
+use codespan_reporting::diagnostic::Label;
 use codespan_reporting::files::Error;
 #[cfg(feature = "serialization")]
 use serde::{Deserialize, Serialize};
 use std::ffi::{OsStr, OsString};
 use std::num::NonZeroU32;
+use std::ops::Range;
 
 use crate::{ByteIndex, ColumnIndex, LineIndex, LineOffset, Location, RawIndex, Span};
 
@@ -67,11 +69,23 @@ where
         file_id
     }
 
-    /// Update a source file in place.
+    /// Update a source file in place, returning the source that was replaced.
     ///
     /// This will mean that any outstanding byte indexes will now point to
     /// invalid locations.
-    pub fn update(&mut self, file_id: FileId, source: Source) {
+    ///
+    /// ```rust
+    /// use codespan::Files;
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "hello world!");
+    ///
+    /// let old_source = files.update(file_id, "goodbye world!");
+    ///
+    /// assert_eq!(old_source, "hello world!");
+    /// assert_eq!(*files.source(file_id), "goodbye world!");
+    /// ```
+    pub fn update(&mut self, file_id: FileId, source: Source) -> Source {
         self.get_mut(file_id).update(source)
     }
 
@@ -173,6 +187,35 @@ where
         self.get(file_id).location(byte_index.into())
     }
 
+    /// Get the locations of the start and end of a span in the source file.
+    ///
+    /// ```rust
+    /// use codespan::{Files, Location, Span};
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "foo\nbar\r\n\nbaz");
+    ///
+    /// assert_eq!(
+    ///     files.location_range(file_id, Span::new(1, 7)).unwrap(),
+    ///     Location::new(0, 1)..Location::new(1, 3),
+    /// );
+    /// assert_eq!(
+    ///     files.location_range(file_id, Span::new(9, 13)).unwrap(),
+    ///     Location::new(2, 0)..Location::new(3, 3),
+    /// );
+    /// assert!(files.location_range(file_id, Span::new(9, 100)).is_err());
+    /// ```
+    pub fn location_range(
+        &self,
+        file_id: FileId,
+        span: impl Into<Span>,
+    ) -> Result<Range<Location>, Error> {
+        let span = span.into();
+        let start = self.location(file_id, span.start())?;
+        let end = self.location(file_id, span.end())?;
+        Ok(start..end)
+    }
+
     /// Get the source of the file.
     ///
     /// ```rust
@@ -207,6 +250,11 @@ where
 
     /// Return a slice of the source file, given a span.
     ///
+    /// Returns [`Error::InvalidCharBoundary`] if the span starts or ends in the
+    /// middle of a character.
+    ///
+    /// [`Error::InvalidCharBoundary`]: codespan_reporting::files::Error::InvalidCharBoundary
+    ///
     /// ```rust
     /// use codespan::{Files, Span};
     ///
@@ -219,6 +267,123 @@ where
     pub fn source_slice(&self, file_id: FileId, span: impl Into<Span>) -> Result<&str, Error> {
         self.get(file_id).source_slice(span.into())
     }
+
+    /// Return the number of characters in the source file.
+    ///
+    /// ```rust
+    /// use codespan::Files;
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "hi萤\n");
+    ///
+    /// assert_eq!(files.char_count(file_id), 4);
+    /// ```
+    pub fn char_count(&self, file_id: FileId) -> usize {
+        self.get(file_id).source().as_ref().chars().count()
+    }
+
+    /// Return the number of characters in the source file covered by a span.
+    ///
+    /// ```rust
+    /// use codespan::{Files, Span};
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "hi萤\n");
+    ///
+    /// assert_eq!(files.char_count_in(file_id, Span::new(1, 5)).unwrap(), 2);
+    /// assert!(files.char_count_in(file_id, Span::new(1, 3)).is_err());
+    /// ```
+    pub fn char_count_in(&self, file_id: FileId, span: impl Into<Span>) -> Result<usize, Error> {
+        self.get(file_id).char_count_in(span.into())
+    }
+
+    /// Convert a byte range in the source file to a span, checking that it
+    /// lies within the bounds of the source.
+    ///
+    /// A range whose start is after its end cannot be a span, so its start is
+    /// reported as an [`Error::InvalidCharBoundary`].
+    ///
+    /// ```rust
+    /// use codespan::{Files, Span};
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test",  "hello world!");
+    ///
+    /// assert_eq!(files.range_to_span(file_id, 0..5).unwrap(), Span::new(0, 5));
+    /// assert!(files.range_to_span(file_id, 0..100).is_err());
+    /// ```
+    pub fn range_to_span(&self, file_id: FileId, range: Range<usize>) -> Result<Span, Error> {
+        self.get(file_id).range_to_span(range)
+    }
+
+    /// Return the smallest span enclosing the labels that are in the given
+    /// file, or `None` if none of the labels are in that file.
+    ///
+    /// ```rust
+    /// use codespan::{Files, Span};
+    /// use codespan_reporting::diagnostic::Label;
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "hello world!");
+    /// let other_file_id = files.add("other", "goodbye world!");
+    ///
+    /// let labels = [
+    ///     Label::primary(file_id, 6..11),
+    ///     Label::secondary(other_file_id, 0..7),
+    ///     Label::secondary(file_id, 0..5),
+    /// ];
+    ///
+    /// assert_eq!(files.merged_label_span(file_id, &labels).unwrap(), Some(Span::new(0, 11)));
+    /// assert_eq!(files.merged_label_span(other_file_id, &labels[2..]).unwrap(), None);
+    /// ```
+    pub fn merged_label_span(
+        &self,
+        file_id: FileId,
+        labels: &[Label<FileId>],
+    ) -> Result<Option<Span>, Error> {
+        let mut merged = None;
+        for label in labels.iter().filter(|label| label.file_id == file_id) {
+            let span = self.range_to_span(file_id, label.range.clone())?;
+            merged = Some(match merged {
+                Some(merged) => Span::merge(merged, span),
+                None => span,
+            });
+        }
+        Ok(merged)
+    }
+}
+
+impl Files<String> {
+    /// Replace a byte range of a source file in place.
+    ///
+    /// Unlike [`Files::update`], this only recomputes the line starts from the
+    /// start of the range onward, shifting the line starts after the range by
+    /// the change in length. This makes small edits to large files cheap.
+    ///
+    /// This will mean that any outstanding byte indexes after the start of the
+    /// range will now point to invalid locations.
+    ///
+    /// ```rust
+    /// use codespan::Files;
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "foo\nbar\nbaz".to_owned());
+    ///
+    /// files.update_range(file_id, 4..7, "hello\nworld");
+    ///
+    /// assert_eq!(files.source(file_id), "foo\nhello\nworld\nbaz");
+    /// assert_eq!(files.line_index(file_id, 16).to_usize(), 3);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, or does not lie on character
+    /// boundaries.
+    ///
+    /// [`Files::update`]: Files::update
+    pub fn update_range(&mut self, file_id: FileId, byte_range: Range<usize>, replacement: &str) {
+        self.get_mut(file_id).update_range(byte_range, replacement)
+    }
 }
 
 impl<'a, Source> codespan_reporting::files::Files<'a> for Files<Source>
@@ -286,12 +451,11 @@ where
         }
     }
 
-    fn update(&mut self, source: Source) {
-        let line_starts = line_starts(source.as_ref())
+    fn update(&mut self, source: Source) -> Source {
+        self.line_starts = line_starts(source.as_ref())
             .map(|i| ByteIndex::from(i as u32))
             .collect();
-        self.source = source;
-        self.line_starts = line_starts;
+        std::mem::replace(&mut self.source, source)
     }
 
     fn name(&self) -> &OsStr {
@@ -366,17 +530,90 @@ where
         Span::from_str(self.source.as_ref())
     }
 
+    fn char_count_in(&self, span: Span) -> Result<usize, Error> {
+        Ok(self.source_slice(span)?.chars().count())
+    }
+
+    fn range_to_span(&self, range: Range<usize>) -> Result<Span, Error> {
+        use std::convert::TryFrom;
+
+        let to_index = |given: usize| {
+            RawIndex::try_from(given).map_err(|_| Error::IndexTooLarge {
+                given,
+                max: RawIndex::MAX as usize,
+            })
+        };
+        let start = to_index(range.start)?;
+        let end = to_index(range.end)?;
+
+        let max = self.source.as_ref().len();
+        if range.end > max {
+            return Err(Error::IndexTooLarge {
+                given: range.end,
+                max,
+            });
+        }
+        if range.start > range.end {
+            return Err(Error::InvalidCharBoundary { given: range.start });
+        }
+
+        Ok(Span::new(start, end))
+    }
+
     fn source_slice(&self, span: Span) -> Result<&str, Error> {
+        let source = self.source.as_ref();
         let start = span.start().to_usize();
         let end = span.end().to_usize();
 
-        self.source.as_ref().get(start..end).ok_or_else(|| {
-            let max = self.source().as_ref().len() - 1;
-            Error::IndexTooLarge {
-                given: if start > max { start } else { end },
-                max,
+        if end > source.len() {
+            return Err(Error::IndexTooLarge {
+                given: end,
+                max: source.len(),
+            });
+        }
+        for &given in &[start, end] {
+            if !source.is_char_boundary(given) {
+                return Err(Error::InvalidCharBoundary { given });
             }
-        })
+        }
+
+        Ok(&source[start..end])
+    }
+}
+
+impl File<String> {
+    fn update_range(&mut self, byte_range: Range<usize>, replacement: &str) {
+        let Range { start, end } = byte_range;
+        self.source.replace_range(start..end, replacement);
+
+        // Line starts up to the start of the range are preceded by unchanged
+        // newlines, and line starts after the end of the range only need to
+        // be shifted by the change in length.
+        let first_changed = match self
+            .line_starts
+            .binary_search(&ByteIndex::from(start as RawIndex))
+        {
+            Ok(index) => index + 1,
+            Err(index) => index,
+        };
+        let first_shifted = match self
+            .line_starts
+            .binary_search(&ByteIndex::from(end as RawIndex))
+        {
+            Ok(index) => index + 1,
+            Err(index) => index,
+        };
+
+        for line_start in &mut self.line_starts[first_shifted..] {
+            let shifted = line_start.to_usize() - end + start + replacement.len();
+            *line_start = ByteIndex::from(shifted as RawIndex);
+        }
+
+        let inserted = replacement
+            .match_indices('\n')
+            .map(|(i, _)| ByteIndex::from((start + i + 1) as RawIndex));
+        self.line_starts
+            .splice(first_changed..first_shifted, inserted);
     }
 }
 
@@ -407,6 +644,32 @@ mod test {
         );
     }
 
+    #[test]
+    fn update_range_line_starts() {
+        let edits = [
+            (0..0, ""),
+            (4..4, "\n"),
+            (3..5, "x"),
+            (0..3, "a\nb\nc"),
+            (9..13, ""),
+            (11..11, "end\n"),
+            (0..4, "\r\n"),
+        ];
+
+        let mut files = Files::<String>::new();
+        let file_id = files.add("test", TEST_SOURCE.to_owned());
+        let mut expected = TEST_SOURCE.to_owned();
+
+        for (range, replacement) in edits.iter().cloned() {
+            files.update_range(file_id, range.clone(), replacement);
+            expected.replace_range(range, replacement);
+
+            let expected_file = File::new("test".into(), expected.clone());
+            assert_eq!(files.source(file_id), &expected);
+            assert_eq!(files.get(file_id).line_starts, expected_file.line_starts);
+        }
+    }
+
     #[test]
     fn line_span_sources() {
         // Also make sure we can use `Arc` for source
@@ -425,6 +688,128 @@ mod test {
         assert_eq!(line_sources, ["foo\n", "bar\r\n", "\n", "baz"],);
     }
 
+    #[test]
+    fn range_to_span() {
+        let mut files = Files::<String>::new();
+        let file_id = files.add("test", TEST_SOURCE.to_owned());
+
+        assert_eq!(files.range_to_span(file_id, 0..3).unwrap(), Span::new(0, 3));
+        assert_eq!(files.range_to_span(file_id, 4..4).unwrap(), Span::new(4, 4));
+        assert_eq!(
+            files.range_to_span(file_id, 0..13).unwrap(),
+            Span::new(0, 13)
+        );
+    }
+
+    #[test]
+    fn range_to_span_out_of_bounds() {
+        let mut files = Files::<String>::new();
+        let file_id = files.add("test", TEST_SOURCE.to_owned());
+
+        assert!(matches!(
+            files.range_to_span(file_id, 4..14),
+            Err(Error::IndexTooLarge { given: 14, max: 13 })
+        ));
+        assert!(matches!(
+            files.range_to_span(file_id, Range { start: 7, end: 4 }),
+            Err(Error::InvalidCharBoundary { given: 7 })
+        ));
+    }
+
+    #[test]
+    fn range_to_span_overflow() {
+        let mut files = Files::<String>::new();
+        let file_id = files.add("test", TEST_SOURCE.to_owned());
+
+        assert!(matches!(
+            files.range_to_span(file_id, 0..usize::MAX),
+            Err(Error::IndexTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn merged_label_span() {
+        let mut files = Files::<String>::new();
+        let file_id = files.add("test", TEST_SOURCE.to_owned());
+        let other_file_id = files.add("other", TEST_SOURCE.to_owned());
+
+        let labels = vec![
+            Label::secondary(file_id, 9..10),
+            Label::primary(other_file_id, 0..13),
+            Label::primary(file_id, 4..7),
+        ];
+
+        assert_eq!(
+            files.merged_label_span(file_id, &labels).unwrap(),
+            Some(Span::new(4, 10))
+        );
+        assert_eq!(
+            files.merged_label_span(other_file_id, &labels).unwrap(),
+            Some(Span::new(0, 13))
+        );
+        assert_eq!(files.merged_label_span(file_id, &[]).unwrap(), None);
+        assert!(matches!(
+            files.merged_label_span(file_id, &[Label::primary(file_id, 4..14)]),
+            Err(Error::IndexTooLarge { given: 14, max: 13 })
+        ));
+    }
+
+    #[test]
+    fn char_count() {
+        let mut files = Files::<String>::new();
+        let file_id = files.add("test", "hi萤\n".to_owned());
+
+        assert_eq!(files.char_count(file_id), 4);
+        assert_eq!(files.char_count_in(file_id, Span::new(0, 6)).unwrap(), 4);
+        assert_eq!(files.char_count_in(file_id, Span::new(2, 5)).unwrap(), 1);
+        assert_eq!(files.char_count_in(file_id, Span::new(5, 5)).unwrap(), 0);
+    }
+
+    #[test]
+    fn char_count_in_invalid() {
+        let mut files = Files::<String>::new();
+        let file_id = files.add("test", "hi萤\n".to_owned());
+
+        assert!(matches!(
+            files.char_count_in(file_id, Span::new(0, 3)),
+            Err(Error::InvalidCharBoundary { given: 3 })
+        ));
+        assert!(matches!(
+            files.char_count_in(file_id, Span::new(4, 6)),
+            Err(Error::InvalidCharBoundary { given: 4 })
+        ));
+        assert!(matches!(
+            files.char_count_in(file_id, Span::new(0, 7)),
+            Err(Error::IndexTooLarge { given: 7, max: 6 })
+        ));
+    }
+
+    #[test]
+    fn source_slice_invalid() {
+        let mut files = Files::<String>::new();
+        let file_id = files.add("test", "hi萤\n".to_owned());
+
+        assert_eq!(files.source_slice(file_id, Span::new(2, 5)).unwrap(), "萤");
+        assert!(matches!(
+            files.source_slice(file_id, Span::new(0, 3)),
+            Err(Error::InvalidCharBoundary { given: 3 })
+        ));
+        assert!(matches!(
+            files.source_slice(file_id, Span::new(4, 6)),
+            Err(Error::InvalidCharBoundary { given: 4 })
+        ));
+        assert!(matches!(
+            files.source_slice(file_id, Span::new(0, 7)),
+            Err(Error::IndexTooLarge { given: 7, max: 6 })
+        ));
+
+        let empty_id = files.add("empty", String::new());
+        assert!(matches!(
+            files.source_slice(empty_id, Span::new(0, 1)),
+            Err(Error::IndexTooLarge { given: 1, max: 0 })
+        ));
+    }
+
     #[test]
     fn interoperability() {
         extern crate termcolor;
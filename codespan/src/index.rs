@@ -34,6 +34,21 @@ impl LineIndex {
     pub const fn to_usize(self) -> usize {
         self.0 as usize
     }
+
+    /// Iterate over the line indices from `start` up to, but not including,
+    /// `end`.
+    ///
+    /// ```rust
+    /// use codespan::LineIndex;
+    ///
+    /// let lines = LineIndex::range(LineIndex(2), LineIndex(5)).collect::<Vec<_>>();
+    ///
+    /// assert_eq!(lines, vec![LineIndex(2), LineIndex(3), LineIndex(4)]);
+    /// assert_eq!(LineIndex::range(LineIndex(5), LineIndex(2)).count(), 0);
+    /// ```
+    pub fn range(start: LineIndex, end: LineIndex) -> impl Iterator<Item = LineIndex> {
+        (start.0..end.0).map(LineIndex)
+    }
 }
 
 impl Default for LineIndex {
@@ -84,6 +84,74 @@ impl Span {
         first.end <= last.start
     }
 
+    /// The region where two spans overlap, or `None` if they do not overlap.
+    ///
+    /// Spans that only touch each other do not overlap.
+    ///
+    /// ```rust
+    /// use codespan::Span;
+    ///
+    /// let span1 = Span::new(0, 4);
+    /// let span2 = Span::new(2, 10);
+    /// let span3 = Span::new(4, 10);
+    /// let span4 = Span::new(1, 3);
+    ///
+    /// assert_eq!(span1.intersection(span2), Some(Span::new(2, 4)));
+    /// assert_eq!(span1.intersection(Span::new(10, 16)), None);
+    /// assert_eq!(span1.intersection(span3), None);
+    /// assert_eq!(span1.intersection(span4), Some(span4));
+    /// ```
+    pub fn intersection(self, other: Span) -> Option<Span> {
+        use std::cmp::{max, min};
+
+        let start = max(self.start, other.start);
+        let end = min(self.end, other.end);
+        if start < end {
+            Some(Span::new(start, end))
+        } else {
+            None
+        }
+    }
+
+    /// Split the span at a byte index into two adjacent spans, the first
+    /// ending and the second starting at the index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is not within the span.
+    ///
+    /// ```rust
+    /// use codespan::{ByteIndex, Span};
+    ///
+    /// let span = Span::new(2, 8);
+    ///
+    /// assert_eq!(span.split_at(ByteIndex(5)), (Span::new(2, 5), Span::new(5, 8)));
+    /// assert_eq!(span.split_at(ByteIndex(2)), (Span::new(2, 2), span));
+    /// assert_eq!(span.split_at(ByteIndex(8)), (span, Span::new(8, 8)));
+    /// ```
+    pub fn split_at(self, at: ByteIndex) -> (Span, Span) {
+        assert!(
+            self.start <= at && at <= self.end,
+            "byte index {} is outside of the span {}",
+            at,
+            self,
+        );
+
+        (Span::new(self.start, at), Span::new(at, self.end))
+    }
+
+    /// Whether the span has a length of zero.
+    ///
+    /// ```rust
+    /// use codespan::Span;
+    ///
+    /// assert!(Span::new(4, 4).is_empty());
+    /// assert!(!Span::new(0, 4).is_empty());
+    /// ```
+    pub fn is_empty(self) -> bool {
+        self.start == self.end
+    }
+
     /// Get the starting byte index.
     ///
     /// ```rust
@@ -137,6 +205,27 @@ where
     }
 }
 
+/// Convert a span to a byte range, like the ranges of the labels in
+/// `codespan_reporting`. This allows spans to be used to make labels directly:
+///
+/// ```rust
+/// use codespan::{Files, Span};
+/// use codespan_reporting::diagnostic::Label;
+///
+/// let mut files = Files::new();
+/// let file_id = files.add("test", "hello world!");
+///
+/// let label = Label::primary(file_id, Span::new(6, 11));
+/// assert_eq!(label.range, 6..11);
+///
+/// // Byte ranges can be converted back with `Files::range_to_span`, which checks
+/// // that the range is within the bounds of the file.
+/// assert_eq!(files.range_to_span(file_id, label.range).unwrap(), Span::new(6, 11));
+///
+/// let span = Span::new(0, 5);
+/// let label = Label::secondary(file_id, span).with_message("greeting");
+/// assert_eq!(label.range, 0..5);
+/// ```
 impl From<Span> for Range<usize> {
     fn from(span: Span) -> Range<usize> {
         span.start.into()..span.end.into()
@@ -207,4 +296,42 @@ mod test {
         assert!(a.disjoint(d));
         assert!(d.disjoint(a));
     }
+
+    #[test]
+    fn test_intersection() {
+        use super::Span;
+
+        // overlap
+        let a = Span::from(1..5);
+        let b = Span::from(3..10);
+        assert_eq!(a.intersection(b), Some((3..5).into()));
+        assert_eq!(b.intersection(a), Some((3..5).into()));
+
+        // subset
+        let two_four = (2..4).into();
+        assert_eq!(a.intersection(two_four), Some(two_four));
+        assert_eq!(two_four.intersection(a), Some(two_four));
+
+        // disjoint
+        let ten_twenty = (10..20).into();
+        assert_eq!(a.intersection(ten_twenty), None);
+        assert_eq!(ten_twenty.intersection(a), None);
+
+        // identity
+        assert_eq!(a.intersection(a), Some(a));
+
+        // touching
+        let c = Span::from(5..10);
+        assert_eq!(a.intersection(c), None);
+        assert_eq!(c.intersection(a), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "byte index 6 is outside of the span [1, 5)")]
+    fn test_split_at_outside() {
+        use super::Span;
+        use crate::ByteIndex;
+
+        Span::from(1..5).split_at(ByteIndex(6));
+    }
 }